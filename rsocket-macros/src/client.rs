@@ -0,0 +1,259 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    Error, FnArg, GenericArgument, Ident, ItemTrait, LitStr, Pat, PatIdent, PathArguments, Result,
+    ReturnType, TraitItem, TraitItemFn, Type,
+};
+
+use super::{parse_route, take_var};
+
+enum Interaction {
+    FireAndForget,
+    /// Whether the response may have no data.
+    RequestResponse(bool),
+    RequestStream,
+    RequestChannel,
+}
+
+pub(crate) fn client(trait_: &mut ItemTrait) -> Result<TokenStream2> {
+    if !trait_.generics.params.is_empty() || trait_.generics.where_clause.is_some() {
+        let e = "#[rsocket_client] doesn't support generic traits";
+        return Err(Error::new(trait_.generics.span(), e));
+    }
+    let mut methods = vec![];
+    for item in trait_.items.iter_mut() {
+        if let TraitItem::Fn(method) = item {
+            if let Some(method) = method_of(method)? {
+                methods.push(method);
+            }
+        }
+    }
+    let async_trait = if trait_.attrs.iter().any(|it| {
+        it.path()
+            .segments
+            .last()
+            .is_some_and(|s| s.ident == "async_trait")
+    }) {
+        quote!()
+    } else {
+        quote!(#[::rsocket_rust::async_trait])
+    };
+
+    let vis = &trait_.vis;
+    let ident = &trait_.ident;
+    let name = format_ident!("{}Client", ident);
+    let doc = format!(
+        "A client of `{}`, sending requests with a `Requester`.",
+        ident
+    );
+    Ok(quote! {
+        #async_trait
+        #trait_
+
+        #[doc = #doc]
+        #vis struct #name {
+            requester: ::rsocket_rust_messaging::Requester,
+        }
+
+        impl #name {
+            #vis fn new(requester: ::rsocket_rust_messaging::Requester) -> Self {
+                #name { requester }
+            }
+        }
+
+        #async_trait
+        impl #ident for #name {
+            #(#methods)*
+        }
+    })
+}
+
+/// Generates the implementation of a routed method, removing the attributes of it.
+fn method_of(method: &mut TraitItemFn) -> Result<Option<TokenStream2>> {
+    let mut route = None;
+    let mut errors = vec![];
+    method.attrs.retain(|attr| {
+        let name = match attr.path().get_ident() {
+            Some(it) => it.to_string(),
+            None => return true,
+        };
+        match name.as_str() {
+            "route" => match attr.parse_args::<LitStr>() {
+                Ok(it) => route = Some(it),
+                Err(e) => errors.push(e),
+            },
+            "fire_and_forget" | "request_response" | "request_stream" | "request_channel" => {
+                let e = "the interaction model of clients follows the signature";
+                errors.push(Error::new(attr.span(), e));
+            }
+            _ => return true,
+        }
+        false
+    });
+    if let Some(e) = errors.into_iter().next() {
+        return Err(e);
+    }
+    let route = match route {
+        Some(it) => it,
+        None if method.default.is_some() => return Ok(None),
+        None => {
+            let e = "missing #[route(\"...\")]";
+            return Err(Error::new(method.sig.ident.span(), e));
+        }
+    };
+    if let Some(body) = &method.default {
+        return Err(Error::new(body.span(), "routed methods can't have a body"));
+    }
+    let variables = parse_route(&route)?;
+    if route.value().contains('*') {
+        let e = "routes of clients can't have wildcards";
+        return Err(Error::new(route.span(), e));
+    }
+
+    let mut sig = method.sig.clone();
+    let mut inputs = method.sig.inputs.iter_mut();
+    match inputs.next() {
+        Some(FnArg::Receiver(it)) if it.reference.is_some() && it.mutability.is_none() => (),
+        _ => {
+            let e = "the method must take `&self` as the first parameter";
+            return Err(Error::new(method.sig.ident.span(), e));
+        }
+    }
+    let mut bound: Vec<Option<Ident>> = vec![None; variables.len()];
+    let mut data: Option<(Ident, Type)> = None;
+    for (k, input) in inputs.enumerate() {
+        let input = match input {
+            FnArg::Typed(it) => it,
+            FnArg::Receiver(it) => return Err(Error::new(it.span(), "unexpected receiver")),
+        };
+        let arg = format_ident!("__arg{}", k);
+        if let FnArg::Typed(it) = &mut sig.inputs[k + 1] {
+            it.attrs.clear();
+            *it.pat = Pat::Ident(PatIdent {
+                attrs: vec![],
+                by_ref: None,
+                mutability: None,
+                ident: arg.clone(),
+                subpat: None,
+            });
+        }
+        match take_var(&mut input.attrs, &input.pat)? {
+            Some(name) => match variables.iter().position(|it| *it == name.value()) {
+                Some(i) if bound[i].is_none() => bound[i] = Some(arg),
+                Some(_) => {
+                    let e = format!("variable {} is bound more than once", name.value());
+                    return Err(Error::new(name.span(), e));
+                }
+                None => {
+                    let e = format!("no variable {} in the route", name.value());
+                    return Err(Error::new(name.span(), e));
+                }
+            },
+            None if data.is_some() => {
+                let e = "only one parameter can be bound to data, annotate variables with #[var]";
+                return Err(Error::new(input.span(), e));
+            }
+            None => data = Some((arg, (*input.ty).clone())),
+        }
+    }
+    let mut args = vec![];
+    for (name, arg) in variables.iter().zip(bound) {
+        match arg {
+            Some(arg) => args.push(arg),
+            None => {
+                let e = format!("variable {} isn't bound to any parameter", name);
+                return Err(Error::new(route.span(), e));
+            }
+        }
+    }
+
+    let interaction = interaction_of(&sig, data.as_ref().map(|(_, ty)| ty))?;
+    let spec = if args.is_empty() {
+        quote! { self.requester.route(#route) }
+    } else {
+        quote! { self.requester.route_with(#route, &[#(&#args),*]) }
+    };
+    // the arguments of variables are borrowed by a temporary, which can't live across awaits
+    let body = match (interaction, data) {
+        (Interaction::RequestChannel, Some((inputs, _))) => {
+            quote! { #spec.retrieve_channel(#inputs).flux() }
+        }
+        (interaction, data) => {
+            let spec = match data {
+                Some((data, _)) => quote! { let __spec = #spec.data(#data); },
+                None => quote! { let __spec = #spec; },
+            };
+            let call = match interaction {
+                Interaction::FireAndForget => quote! { __spec.retrieve().await },
+                Interaction::RequestResponse(true) => {
+                    quote! { __spec.retrieve_mono().await.decode() }
+                }
+                Interaction::RequestResponse(false) => quote! {
+                    __spec.retrieve_mono().await.decode()?.ok_or_else(|| {
+                        let desc = format!("empty response of route: {}", #route);
+                        ::rsocket_rust::error::RSocketError::WithDescription(desc).into()
+                    })
+                },
+                _ => quote! { __spec.retrieve_flux().flux() },
+            };
+            quote! {
+                #spec
+                #call
+            }
+        }
+    };
+    Ok(Some(quote! {
+        #sig {
+            #body
+        }
+    }))
+}
+
+/// Infers the interaction model of a method by its signature.
+fn interaction_of(sig: &syn::Signature, data: Option<&Type>) -> Result<Interaction> {
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) => ty.as_ref(),
+        ReturnType::Default => {
+            return Err(Error::new(sig.span(), "the method must return a result"));
+        }
+    };
+    if sig.asyncness.is_some() {
+        return match generic_of(output, "Result") {
+            Some(Type::Tuple(it)) if it.elems.is_empty() => Ok(Interaction::FireAndForget),
+            Some(ty) => Ok(Interaction::RequestResponse(
+                generic_of(ty, "Option").is_some(),
+            )),
+            None => Err(Error::new(
+                output.span(),
+                "async methods must return `Result<T>`",
+            )),
+        };
+    }
+    if generic_of(output, "Flux").is_none() {
+        let e = "methods of streams must return `Flux<Result<T>>`, others must be async";
+        return Err(Error::new(output.span(), e));
+    }
+    match data {
+        Some(ty) if generic_of(ty, "Flux").is_some() => Ok(Interaction::RequestChannel),
+        _ => Ok(Interaction::RequestStream),
+    }
+}
+
+/// Returns the first generic argument of a type like `Name<T>`.
+fn generic_of<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+    let last = match ty {
+        Type::Path(it) => it.path.segments.last()?,
+        _ => return None,
+    };
+    if last.ident != name {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(it) => it.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
@@ -0,0 +1,349 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{Error, FnArg, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, Result, Type};
+
+use super::{parse_route, take_var};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Interaction {
+    FireAndForget,
+    RequestResponse,
+    RequestStream,
+    RequestChannel,
+}
+
+enum Param {
+    Variable(String, Type),
+    Data(Type),
+}
+
+struct Handler {
+    route: LitStr,
+    interaction: Interaction,
+    method: Ident,
+    params: Vec<Param>,
+}
+
+pub(crate) fn controller(imp: &mut ItemImpl) -> Result<TokenStream2> {
+    if let Some((_, path, _)) = &imp.trait_ {
+        return Err(Error::new(
+            path.span(),
+            "#[rsocket_controller] must be used on an inherent impl block",
+        ));
+    }
+    let mut handlers = vec![];
+    for item in imp.items.iter_mut() {
+        if let ImplItem::Fn(method) = item {
+            if let Some(handler) = handler(method)? {
+                handlers.push(handler);
+            }
+        }
+    }
+
+    let self_ty = &imp.self_ty;
+    let (impl_generics, _, where_clause) = imp.generics.split_for_impl();
+    let fire_and_forget = fire_and_forget(&handlers);
+    let request_response = request_response(&handlers);
+    let request_stream = request_stream(&handlers);
+    let request_channel = request_channel(&handlers);
+    let responder = responder(&handlers);
+    Ok(quote! {
+        #imp
+
+        #[::rsocket_rust::async_trait]
+        impl #impl_generics ::rsocket_rust_messaging::controller::Dispatch for #self_ty #where_clause {
+            #fire_and_forget
+            #request_response
+            #request_stream
+            #request_channel
+        }
+
+        #[::rsocket_rust::async_trait]
+        impl #impl_generics ::rsocket_rust::prelude::RSocket for #self_ty #where_clause {
+            #responder
+        }
+    })
+}
+
+/// Parses the handler of an annotated method, removing the attributes of it.
+fn handler(method: &mut ImplItemFn) -> Result<Option<Handler>> {
+    let mut route = None;
+    let mut interaction = None;
+    let mut errors = vec![];
+    method.attrs.retain(|attr| {
+        let found = match attr.path().get_ident().map(|it| it.to_string()) {
+            Some(name) => name,
+            None => return true,
+        };
+        let found = match found.as_str() {
+            "route" => {
+                match attr.parse_args::<LitStr>() {
+                    Ok(it) => route = Some(it),
+                    Err(e) => errors.push(e),
+                }
+                return false;
+            }
+            "fire_and_forget" => Interaction::FireAndForget,
+            "request_response" => Interaction::RequestResponse,
+            "request_stream" => Interaction::RequestStream,
+            "request_channel" => Interaction::RequestChannel,
+            _ => return true,
+        };
+        if interaction.replace(found).is_some() {
+            errors.push(Error::new(attr.span(), "duplicated interaction model"));
+        }
+        false
+    });
+    if let Some(e) = errors.into_iter().next() {
+        return Err(e);
+    }
+    let sig = &method.sig;
+    let route = match (route, interaction) {
+        (Some(route), _) => route,
+        (None, Some(_)) => return Err(Error::new(sig.ident.span(), "missing #[route(\"...\")]")),
+        (None, None) => return Ok(None),
+    };
+    let variables = parse_route(&route)?;
+    let interaction = interaction.unwrap_or(Interaction::RequestResponse);
+    match interaction {
+        Interaction::FireAndForget | Interaction::RequestResponse if sig.asyncness.is_none() => {
+            return Err(Error::new(sig.span(), "the handler must be async"));
+        }
+        Interaction::RequestStream | Interaction::RequestChannel if sig.asyncness.is_some() => {
+            return Err(Error::new(sig.span(), "the handler must not be async"));
+        }
+        _ => (),
+    }
+
+    let mut inputs = method.sig.inputs.iter_mut();
+    match inputs.next() {
+        Some(FnArg::Receiver(it)) if it.reference.is_some() && it.mutability.is_none() => (),
+        _ => {
+            let e = "the handler must take `&self` as the first parameter";
+            return Err(Error::new(method.sig.ident.span(), e));
+        }
+    }
+    let mut params = vec![];
+    let mut has_data = false;
+    for input in inputs {
+        let input = match input {
+            FnArg::Typed(it) => it,
+            FnArg::Receiver(it) => return Err(Error::new(it.span(), "unexpected receiver")),
+        };
+        let var = take_var(&mut input.attrs, &input.pat)?;
+        let ty = (*input.ty).clone();
+        match var {
+            Some(name) => {
+                if !variables.contains(&name.value()) {
+                    let e = format!("no variable {} in the route", name.value());
+                    return Err(Error::new(name.span(), e));
+                }
+                params.push(Param::Variable(name.value(), ty));
+            }
+            None if has_data => {
+                let e = "only one parameter can be bound to data, annotate variables with #[var]";
+                return Err(Error::new(input.span(), e));
+            }
+            None => {
+                has_data = true;
+                params.push(Param::Data(ty));
+            }
+        }
+    }
+    if interaction == Interaction::RequestChannel && !has_data {
+        let e = "the channel handler must take a parameter of inputs";
+        return Err(Error::new(method.sig.ident.span(), e));
+    }
+    Ok(Some(Handler {
+        route,
+        interaction,
+        method: method.sig.ident.clone(),
+        params,
+    }))
+}
+
+fn of(handlers: &[Handler], interaction: Interaction) -> Vec<&Handler> {
+    handlers
+        .iter()
+        .filter(|it| it.interaction == interaction)
+        .collect()
+}
+
+/// Generates the selection of a handler, whose arms evaluate the call of the handler method.
+fn dispatch<F>(handlers: &[&Handler], this: TokenStream2, call: F) -> TokenStream2
+where
+    F: Fn(TokenStream2) -> TokenStream2,
+{
+    let routes: Vec<&LitStr> = handlers.iter().map(|it| &it.route).collect();
+    let arms = handlers.iter().enumerate().map(|(i, handler)| {
+        let mut bindings = vec![];
+        let mut args = vec![];
+        for (k, param) in handler.params.iter().enumerate() {
+            let arg = format_ident!("__arg{}", k);
+            let binding = match param {
+                Param::Variable(name, ty) => {
+                    quote! { let #arg: #ty = __request.variable(&__vars, #name)?; }
+                }
+                Param::Data(ty) if handler.interaction == Interaction::RequestChannel => {
+                    quote! { let #arg: #ty = __request.data_stream(__reqs); }
+                }
+                Param::Data(ty) => quote! { let #arg: #ty = __request.data()?; },
+            };
+            bindings.push(binding);
+            args.push(arg);
+        }
+        let method = &handler.method;
+        let call = call(quote! { #this.#method(#(#args),*) });
+        quote! {
+            #i => {
+                #(#bindings)*
+                #call
+            }
+        }
+    });
+    quote! {
+        static ROUTES: ::std::sync::OnceLock<::rsocket_rust_messaging::controller::Routes> =
+            ::std::sync::OnceLock::new();
+        let __routes = ROUTES
+            .get_or_init(|| ::rsocket_rust_messaging::controller::Routes::new(&[#(#routes),*]));
+        let (__i, __vars) = __request.select(__routes)?;
+        match __i {
+            #(#arms)*
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn fire_and_forget(handlers: &[Handler]) -> TokenStream2 {
+    let handlers = of(handlers, Interaction::FireAndForget);
+    let body = if handlers.is_empty() {
+        quote! { __request.reject() }
+    } else {
+        dispatch(&handlers, quote!(self), |call| quote! { #call.await })
+    };
+    quote! {
+        async fn dispatch_fire_and_forget(
+            &self,
+            __request: ::rsocket_rust_messaging::controller::Request,
+        ) -> ::rsocket_rust::Result<()> {
+            #body
+        }
+    }
+}
+
+fn request_response(handlers: &[Handler]) -> TokenStream2 {
+    let handlers = of(handlers, Interaction::RequestResponse);
+    let body = if handlers.is_empty() {
+        quote! { __request.reject() }
+    } else {
+        dispatch(&handlers, quote!(self), |call| {
+            quote! {
+                __request
+                    .respond(#call.await.map_err(::std::convert::Into::into))
+                    .map(Some)
+            }
+        })
+    };
+    quote! {
+        async fn dispatch_request_response(
+            &self,
+            __request: ::rsocket_rust_messaging::controller::Request,
+        ) -> ::rsocket_rust::Result<Option<::rsocket_rust::prelude::Payload>> {
+            #body
+        }
+    }
+}
+
+fn request_stream(handlers: &[Handler]) -> TokenStream2 {
+    let handlers = of(handlers, Interaction::RequestStream);
+    let body = if handlers.is_empty() {
+        quote! { __request.reject() }
+    } else {
+        dispatch(&handlers, quote!(self), |call| {
+            quote! { Ok(__request.encode_stream(#call)) }
+        })
+    };
+    quote! {
+        fn dispatch_request_stream(
+            &self,
+            __request: ::rsocket_rust_messaging::controller::Request,
+        ) -> ::rsocket_rust::Result<::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>>> {
+            #body
+        }
+    }
+}
+
+fn request_channel(handlers: &[Handler]) -> TokenStream2 {
+    let handlers = of(handlers, Interaction::RequestChannel);
+    let body = if handlers.is_empty() {
+        quote! { __request.reject() }
+    } else {
+        dispatch(&handlers, quote!(self), |call| {
+            quote! { Ok(__request.encode_stream(#call)) }
+        })
+    };
+    quote! {
+        fn dispatch_request_channel(
+            &self,
+            __request: ::rsocket_rust_messaging::controller::Request,
+            __reqs: ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>>,
+        ) -> ::rsocket_rust::Result<::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>>> {
+            #body
+        }
+    }
+}
+
+/// Generates the `RSocket` implementation, which dispatches with the default context.
+fn responder(handlers: &[Handler]) -> TokenStream2 {
+    let (this, channel) = if of(handlers, Interaction::RequestChannel).is_empty() {
+        (quote!(), quote! { __request.reject() })
+    } else {
+        let this = quote! { let __this = ::std::clone::Clone::clone(self); };
+        let channel = quote! {
+            ::rsocket_rust_messaging::controller::Dispatch::dispatch_request_channel(
+                &__this, __request, __reqs,
+            )
+        };
+        (this, channel)
+    };
+    quote! {
+        async fn metadata_push(&self, _req: ::rsocket_rust::prelude::Payload) -> ::rsocket_rust::Result<()> {
+            Ok(())
+        }
+
+        async fn fire_and_forget(&self, req: ::rsocket_rust::prelude::Payload) -> ::rsocket_rust::Result<()> {
+            let __request = ::rsocket_rust_messaging::controller::Request::new(req)?;
+            ::rsocket_rust_messaging::controller::Dispatch::dispatch_fire_and_forget(self, __request).await
+        }
+
+        async fn request_response(
+            &self,
+            req: ::rsocket_rust::prelude::Payload,
+        ) -> ::rsocket_rust::Result<Option<::rsocket_rust::prelude::Payload>> {
+            let __request = ::rsocket_rust_messaging::controller::Request::new(req)?;
+            ::rsocket_rust_messaging::controller::Dispatch::dispatch_request_response(self, __request).await
+        }
+
+        fn request_stream(
+            &self,
+            req: ::rsocket_rust::prelude::Payload,
+        ) -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>> {
+            let __context = ::std::default::Default::default();
+            ::rsocket_rust_messaging::controller::stream(req, &__context, |__request| {
+                ::rsocket_rust_messaging::controller::Dispatch::dispatch_request_stream(self, __request)
+            })
+        }
+
+        fn request_channel(
+            &self,
+            reqs: ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>>,
+        ) -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>> {
+            #this
+            let __context = ::std::default::Default::default();
+            ::rsocket_rust_messaging::controller::channel(reqs, &__context, move |__request, __reqs| {
+                #channel
+            })
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Attribute macros for declaring RSocket controllers and clients.
+//!
+//! Both of them route requests with composite metadata. Routes are `RoutePattern`s, whose
+//! variables are bound to parameters annotated with `#[var]` or `#[var("name")]`. The
+//! remaining parameter, if any, is the data of requests, (de)serialized by its MIME type.
+
+mod client;
+mod controller;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use rsocket_rust::router::RoutePattern;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Error, ItemImpl, ItemTrait, LitStr, Pat, Result};
+
+/// Generates an `RSocket` implementation for the type of an impl block, dispatching requests
+/// to the methods annotated with `#[route("...")]`.
+///
+/// Methods are request-response handlers unless annotated with `#[fire_and_forget]`,
+/// `#[request_stream]` or `#[request_channel]`:
+///
+/// - request-response: `async fn(&self, ..) -> Result<T>`
+/// - fire-and-forget: `async fn(&self, ..) -> Result<()>`
+/// - request-stream: `fn(&self, ..) -> S` where `S: Stream<Item = Result<T>>`
+/// - request-channel: `fn(&self, Flux<Result<I>>, ..) -> S` where `S: Stream<Item = Result<T>>`
+///
+/// A `controller::Dispatch` implementation is generated too, so that a `Controller` can serve
+/// the type with a registry of codecs and the data MIME type of each connection. Used as an
+/// `RSocket` directly, controllers with request-channel handlers must implement `Clone`, since
+/// channels are routed by their first payload, after the method returns.
+///
+/// # Example
+/// ```no_run,ignore
+/// #[derive(Clone)]
+/// struct StudentController;
+///
+/// #[rsocket_controller]
+/// impl StudentController {
+///     #[route("student.{id}")]
+///     async fn get(&self, #[var] id: i64) -> Result<Student> {
+///         ...
+///     }
+///
+///     #[route("students")]
+///     #[request_stream]
+///     fn list(&self, query: Query) -> Flux<Result<Student>> {
+///         ...
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn rsocket_controller(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let e = Error::new(
+            Span::call_site(),
+            "#[rsocket_controller] takes no arguments",
+        );
+        return e.to_compile_error().into();
+    }
+    let mut imp = parse_macro_input!(item as ItemImpl);
+    match controller::controller(&mut imp) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Generates a client of a trait, named after the trait with a `Client` suffix, which wraps
+/// a `Requester` and sends a request to the route of every method annotated with
+/// `#[route("...")]`.
+///
+/// The interaction model of a method follows its signature:
+///
+/// - fire-and-forget: `async fn(&self, ..) -> Result<()>`
+/// - request-response: `async fn(&self, ..) -> Result<T>`, or `Result<Option<T>>` if the
+///   response may have no data
+/// - request-stream: `fn(&self, ..) -> Flux<Result<T>>`
+/// - request-channel: `fn(&self, Flux<I>, ..) -> Flux<Result<T>>`
+///
+/// Routes of clients can't have wildcards, and every variable must be bound to a parameter.
+/// Data parameters must be owned, since requests are encoded lazily.
+///
+/// # Example
+/// ```no_run,ignore
+/// #[rsocket_client]
+/// pub trait Students {
+///     #[route("student.{id}")]
+///     async fn get(&self, #[var] id: i64) -> Result<Student>;
+///
+///     #[route("students")]
+///     fn list(&self, query: Query) -> Flux<Result<Student>>;
+/// }
+///
+/// let client = StudentsClient::new(requester);
+/// let student = client.get(1).await?;
+/// ```
+#[proc_macro_attribute]
+pub fn rsocket_client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let e = Error::new(Span::call_site(), "#[rsocket_client] takes no arguments");
+        return e.to_compile_error().into();
+    }
+    let mut trait_ = parse_macro_input!(item as ItemTrait);
+    match client::client(&mut trait_) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+macro_rules! marker {
+    ($name:ident) => {
+        /// Marks a method of `#[rsocket_controller]` or `#[rsocket_client]`.
+        #[proc_macro_attribute]
+        pub fn $name(_attr: TokenStream, _item: TokenStream) -> TokenStream {
+            let desc = concat!(
+                "#[",
+                stringify!($name),
+                "] must be used in #[rsocket_controller] or #[rsocket_client]"
+            );
+            Error::new(Span::call_site(), desc)
+                .to_compile_error()
+                .into()
+        }
+    };
+}
+
+marker!(route);
+marker!(fire_and_forget);
+marker!(request_response);
+marker!(request_stream);
+marker!(request_channel);
+
+/// Removes `#[var]` or `#[var("name")]` from a parameter, returning the variable name.
+fn take_var(attrs: &mut Vec<Attribute>, pat: &Pat) -> Result<Option<LitStr>> {
+    let i = match attrs.iter().position(|it| it.path().is_ident("var")) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    let attr = attrs.remove(i);
+    if matches!(attr.meta, syn::Meta::List(_)) {
+        return attr.parse_args::<LitStr>().map(Some);
+    }
+    match pat {
+        Pat::Ident(it) => {
+            let name = it.ident.to_string();
+            Ok(Some(LitStr::new(
+                name.trim_start_matches("r#"),
+                it.ident.span(),
+            )))
+        }
+        _ => Err(Error::new(
+            pat.span(),
+            "name the variable with #[var(\"...\")] for patterns",
+        )),
+    }
+}
+
+/// Checks a route pattern with `RoutePattern::parse`, returning the names of its variables.
+fn parse_route(route: &LitStr) -> Result<Vec<String>> {
+    let pattern = RoutePattern::parse(&route.value()).map_err(|e| Error::new(route.span(), e))?;
+    Ok(pattern.variables().map(String::from).collect())
+}
@@ -0,0 +1,233 @@
+//! CloudEvents 1.0 in the structured mode of `application/cloudevents+json`.
+//!
+//! Events are sent as the data of requests with `RequestSpec::event`, and received with
+//! `Unpacker::event` or `Unpackers::events`. Routers ingest them with
+//! `handler::fire_and_forget(cloudevents(), ..)` and emit them with `handler::event_stream`.
+//!
+//! # Example
+//! ```no_run,ignore
+//! let event = CloudEvent::builder("1", "/orders", "com.example.order.created")
+//!     .subject("order-1")
+//!     .data(order)
+//!     .build()?;
+//! requester.route("orders.events").event(event).retrieve().await?;
+//! ```
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use rsocket_rust::{error::RSocketError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const SPEC_VERSION: &str = "1.0";
+
+/// A CloudEvent whose data is serialized as JSON, extension attributes are kept as JSON values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawEvent<T>")]
+pub struct CloudEvent<T> {
+    specversion: String,
+    id: String,
+    source: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datacontenttype: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dataschema: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(flatten)]
+    extensions: BTreeMap<String, Value>,
+}
+
+pub struct CloudEventBuilder<T> {
+    inner: CloudEvent<T>,
+}
+
+/// A CloudEvent before its attributes are validated.
+#[derive(Deserialize)]
+struct RawEvent<T> {
+    specversion: String,
+    id: String,
+    source: String,
+    #[serde(rename = "type")]
+    ty: String,
+    datacontenttype: Option<String>,
+    dataschema: Option<String>,
+    subject: Option<String>,
+    time: Option<String>,
+    data: Option<T>,
+    #[serde(flatten)]
+    extensions: BTreeMap<String, Value>,
+}
+
+impl<T> CloudEvent<T> {
+    /// Creates an event with the required attributes, which must not be empty.
+    pub fn builder<I, S, Y>(id: I, source: S, ty: Y) -> CloudEventBuilder<T>
+    where
+        I: Into<String>,
+        S: Into<String>,
+        Y: Into<String>,
+    {
+        CloudEventBuilder {
+            inner: CloudEvent {
+                specversion: SPEC_VERSION.to_owned(),
+                id: id.into(),
+                source: source.into(),
+                ty: ty.into(),
+                datacontenttype: None,
+                dataschema: None,
+                subject: None,
+                time: None,
+                data: None,
+                extensions: BTreeMap::new(),
+            },
+        }
+    }
+
+    pub fn specversion(&self) -> &str {
+        &self.specversion
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the `type` attribute.
+    pub fn ty(&self) -> &str {
+        &self.ty
+    }
+
+    pub fn datacontenttype(&self) -> Option<&str> {
+        self.datacontenttype.as_deref()
+    }
+
+    pub fn dataschema(&self) -> Option<&str> {
+        self.dataschema.as_deref()
+    }
+
+    pub fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    /// Returns the `time` attribute, which is an RFC 3339 timestamp.
+    pub fn time(&self) -> Option<&str> {
+        self.time.as_deref()
+    }
+
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    pub fn into_data(self) -> Option<T> {
+        self.data
+    }
+
+    pub fn extension(&self, name: &str) -> Option<&Value> {
+        self.extensions.get(name)
+    }
+
+    pub fn extensions(&self) -> &BTreeMap<String, Value> {
+        &self.extensions
+    }
+}
+
+impl<T> CloudEventBuilder<T> {
+    pub fn datacontenttype<I>(mut self, datacontenttype: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.inner.datacontenttype = Some(datacontenttype.into());
+        self
+    }
+
+    pub fn dataschema<I>(mut self, dataschema: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.inner.dataschema = Some(dataschema.into());
+        self
+    }
+
+    pub fn subject<I>(mut self, subject: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.inner.subject = Some(subject.into());
+        self
+    }
+
+    /// Sets the `time` attribute, which should be an RFC 3339 timestamp.
+    pub fn time<I>(mut self, time: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.inner.time = Some(time.into());
+        self
+    }
+
+    pub fn data(mut self, data: T) -> Self {
+        self.inner.data = Some(data);
+        self
+    }
+
+    /// Adds an extension attribute, whose name should be lowercase alphanumeric.
+    pub fn extension<I, V>(mut self, name: I, value: V) -> Self
+    where
+        I: Into<String>,
+        V: Into<Value>,
+    {
+        self.inner.extensions.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the event, or an error if a required attribute is empty.
+    pub fn build(self) -> Result<CloudEvent<T>> {
+        let inner = self.inner;
+        validate(&inner.id, &inner.source, &inner.ty)
+            .map_err(|e| RSocketError::WithDescription(e).into())
+            .map(|_| inner)
+    }
+}
+
+impl<T> TryFrom<RawEvent<T>> for CloudEvent<T> {
+    type Error = String;
+
+    fn try_from(raw: RawEvent<T>) -> std::result::Result<Self, Self::Error> {
+        if raw.specversion != SPEC_VERSION {
+            return Err(format!("unsupported specversion: {}", raw.specversion));
+        }
+        validate(&raw.id, &raw.source, &raw.ty)?;
+        Ok(CloudEvent {
+            specversion: raw.specversion,
+            id: raw.id,
+            source: raw.source,
+            ty: raw.ty,
+            datacontenttype: raw.datacontenttype,
+            dataschema: raw.dataschema,
+            subject: raw.subject,
+            time: raw.time,
+            data: raw.data,
+            extensions: raw.extensions,
+        })
+    }
+}
+
+/// Checks that the required attributes are not empty.
+fn validate(id: &str, source: &str, ty: &str) -> std::result::Result<(), String> {
+    for (name, value) in [("id", id), ("source", source), ("type", ty)] {
+        if value.is_empty() {
+            return Err(format!("empty attribute: {}", name));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,380 @@
+//! Codecs (de)serializing data by MIME type.
+//!
+//! A `Codecs` registry ships with JSON, CBOR and CloudEvents JSON, plus MessagePack, bincode and
+//! protobuf when the features `rmp-serde`, `bincode` and `prost` are enabled. Protobuf messages
+//! go through serde wrapped in `Protobuf`. Other formats are added by implementing `Codec`.
+//!
+//! # Example
+//! ```no_run,ignore
+//! let mut codecs = Codecs::default();
+//! codecs.register("application/x-yaml", YamlCodec);
+//! let requester = Requester::builder()
+//!     .codecs(codecs)
+//!     .data_mime_type("application/x-yaml")
+//!     .connect_tcp("127.0.0.1", 7878)
+//!     .build()
+//!     .await?;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::{error::RSocketError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+pub const APPLICATION_MSGPACK: &str = "application/x-msgpack";
+pub const APPLICATION_BINCODE: &str = "application/x-bincode";
+
+/// Visits the deserializer of raw data.
+pub type Visitor<'a> = dyn FnMut(&mut dyn erased_serde::Deserializer) -> Result<()> + 'a;
+
+/// An object safe serialization format, which can be registered in `Codecs`.
+///
+/// This is the extension point of formats: every `Codec` is a `SerDe` too, whereas a `SerDe`
+/// can't be registered, since its generic methods can't be called on a trait object. Implement
+/// `Codec` with `erased_serde` instead, see `Visitor`.
+pub trait Codec: Send + Sync {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>>;
+
+    /// Hands the deserializer of the raw data to the visitor.
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()>;
+}
+
+/// A registry of codecs keyed by MIME type.
+#[derive(Clone)]
+pub struct Codecs {
+    inner: HashMap<String, Arc<dyn Codec>>,
+}
+
+#[derive(Default)]
+pub(crate) struct JsonCodec;
+
+#[derive(Default)]
+pub(crate) struct CborCodec;
+
+#[cfg(feature = "rmp-serde")]
+#[derive(Default)]
+pub(crate) struct MsgpackCodec;
+
+#[cfg(feature = "bincode")]
+#[derive(Default)]
+pub(crate) struct BincodeCodec;
+
+#[cfg(feature = "prost")]
+#[derive(Default)]
+pub(crate) struct ProtobufCodec;
+
+/// A protobuf message, which is serialized as bytes.
+#[cfg(feature = "prost")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Protobuf<M>(pub M);
+
+impl Default for Codecs {
+    fn default() -> Codecs {
+        let mut codecs = Codecs {
+            inner: HashMap::new(),
+        };
+        codecs.register(MimeType::APPLICATION_JSON, JsonCodec);
+        codecs.register(MimeType::APPLICATION_CBOR, CborCodec);
+        codecs.register(MimeType::APPLICATION_CLOUDEVENTS_JSON, JsonCodec);
+        #[cfg(feature = "rmp-serde")]
+        codecs.register(APPLICATION_MSGPACK, MsgpackCodec);
+        #[cfg(feature = "bincode")]
+        codecs.register(APPLICATION_BINCODE, BincodeCodec);
+        #[cfg(feature = "prost")]
+        codecs.register(MimeType::APPLICATION_VND_GOOGLE_PROTOBUF, ProtobufCodec);
+        codecs
+    }
+}
+
+impl fmt::Debug for Codecs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.inner.keys()).finish()
+    }
+}
+
+impl Codecs {
+    /// Registers a codec, replacing the one of the same MIME type. Custom formats implement
+    /// `Codec`, which makes them usable as a `SerDe` by typed handlers too.
+    pub fn register<M, C>(&mut self, mime_type: M, codec: C)
+    where
+        M: Into<MimeType>,
+        C: Codec + 'static,
+    {
+        let mime_type: String = mime_type.into().into();
+        self.inner.insert(mime_type, Arc::new(codec));
+    }
+
+    pub fn get(&self, mime_type: &MimeType) -> Option<&Arc<dyn Codec>> {
+        mime_type.as_str().and_then(|it| self.inner.get(it))
+    }
+
+    pub fn mime_types(&self) -> Vec<MimeType> {
+        self.inner
+            .keys()
+            .map(|it| MimeType::from(it.as_str()))
+            .collect()
+    }
+
+    pub fn marshal<T>(&self, mime_type: &MimeType, data: &T) -> Result<Vec<u8>>
+    where
+        T: Serialize,
+    {
+        self.find(mime_type)?.marshal(data)
+    }
+
+    pub fn unmarshal<T>(&self, mime_type: &MimeType, raw: &[u8]) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        unmarshal(self.find(mime_type)?.as_ref(), raw)
+    }
+
+    fn find(&self, mime_type: &MimeType) -> Result<&Arc<dyn Codec>> {
+        self.get(mime_type).ok_or_else(|| {
+            let desc = format!(
+                "unsupported mime type: {}!",
+                mime_type.as_str().unwrap_or("UNKNOWN")
+            );
+            RSocketError::WithDescription(desc).into()
+        })
+    }
+}
+
+/// Built-in codecs, which are used where no registry is configured.
+pub(crate) fn builtin() -> &'static Arc<Codecs> {
+    static BUILTIN: Lazy<Arc<Codecs>> = Lazy::new(Arc::default);
+    &BUILTIN
+}
+
+pub(crate) fn unmarshal<C, T>(codec: &C, raw: &[u8]) -> Result<T>
+where
+    C: Codec + ?Sized,
+    T: DeserializeOwned,
+{
+    let mut res = None;
+    codec.unmarshal(raw, &mut |de| {
+        res = Some(erased_serde::deserialize(de)?);
+        Ok(())
+    })?;
+    res.ok_or_else(|| RSocketError::WithDescription("nothing deserialized".into()).into())
+}
+
+impl Codec for JsonCodec {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&data)?)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let mut de = serde_json::Deserializer::from_slice(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))?;
+        Ok(de.end()?)
+    }
+}
+
+impl Codec for CborCodec {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        Ok(serde_cbor::to_vec(&data)?)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let mut de = serde_cbor::Deserializer::from_slice(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))?;
+        Ok(de.end()?)
+    }
+}
+
+#[cfg(feature = "rmp-serde")]
+impl Codec for MsgpackCodec {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        Ok(rmp_serde::to_vec_named(&data)?)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let mut de = rmp_serde::Deserializer::from_read_ref(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        Ok(bincode::serialize(&data)?)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        use bincode::Options;
+        // same options as `bincode::deserialize`
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes();
+        let mut de = bincode::Deserializer::from_slice(raw, options);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))
+    }
+}
+
+#[cfg(feature = "prost")]
+impl Codec for ProtobufCodec {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        Ok(serde::Serialize::serialize(
+            data,
+            protobuf::BytesSerializer,
+        )?)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let de = serde::de::value::BytesDeserializer::<serde::de::value::Error>::new(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(de))
+    }
+}
+
+#[cfg(feature = "prost")]
+mod protobuf {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{self, Deserialize, Deserializer, Visitor};
+    use serde::ser::{self, Impossible, Serialize, Serializer};
+
+    use super::Protobuf;
+
+    type Error = serde::de::value::Error;
+
+    impl<M> Serialize for Protobuf<M>
+    where
+        M: prost::Message,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0.encode_to_vec())
+        }
+    }
+
+    impl<'de, M> Deserialize<'de> for Protobuf<M>
+    where
+        M: prost::Message + Default,
+    {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_bytes(MessageVisitor(PhantomData))
+        }
+    }
+
+    struct MessageVisitor<M>(PhantomData<M>);
+
+    impl<M> Visitor<'_> for MessageVisitor<M>
+    where
+        M: prost::Message + Default,
+    {
+        type Value = Protobuf<M>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an encoded protobuf message")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            M::decode(v).map(Protobuf).map_err(E::custom)
+        }
+    }
+
+    /// Takes the bytes of a `Protobuf`, rejecting anything else.
+    pub(super) struct BytesSerializer;
+
+    macro_rules! reject {
+        ($($name:ident($($arg:ty),*) -> $ok:ty;)*) => {
+            $(
+                fn $name(self, $(_: $arg),*) -> Result<$ok, Error> {
+                    Err(ser::Error::custom("only protobuf messages can be serialized"))
+                }
+            )*
+        };
+    }
+
+    impl Serializer for BytesSerializer {
+        type Ok = Vec<u8>;
+        type Error = Error;
+        type SerializeSeq = Impossible<Vec<u8>, Error>;
+        type SerializeTuple = Impossible<Vec<u8>, Error>;
+        type SerializeTupleStruct = Impossible<Vec<u8>, Error>;
+        type SerializeTupleVariant = Impossible<Vec<u8>, Error>;
+        type SerializeMap = Impossible<Vec<u8>, Error>;
+        type SerializeStruct = Impossible<Vec<u8>, Error>;
+        type SerializeStructVariant = Impossible<Vec<u8>, Error>;
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>, Error> {
+            Ok(v.to_vec())
+        }
+
+        fn serialize_newtype_struct<T>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<Vec<u8>, Error>
+        where
+            T: ?Sized + Serialize,
+        {
+            value.serialize(self)
+        }
+
+        fn serialize_some<T>(self, _value: &T) -> Result<Vec<u8>, Error>
+        where
+            T: ?Sized + Serialize,
+        {
+            Err(ser::Error::custom(
+                "only protobuf messages can be serialized",
+            ))
+        }
+
+        fn serialize_newtype_variant<T>(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _value: &T,
+        ) -> Result<Vec<u8>, Error>
+        where
+            T: ?Sized + Serialize,
+        {
+            Err(ser::Error::custom(
+                "only protobuf messages can be serialized",
+            ))
+        }
+
+        reject! {
+            serialize_bool(bool) -> Vec<u8>;
+            serialize_i8(i8) -> Vec<u8>;
+            serialize_i16(i16) -> Vec<u8>;
+            serialize_i32(i32) -> Vec<u8>;
+            serialize_i64(i64) -> Vec<u8>;
+            serialize_u8(u8) -> Vec<u8>;
+            serialize_u16(u16) -> Vec<u8>;
+            serialize_u32(u32) -> Vec<u8>;
+            serialize_u64(u64) -> Vec<u8>;
+            serialize_f32(f32) -> Vec<u8>;
+            serialize_f64(f64) -> Vec<u8>;
+            serialize_char(char) -> Vec<u8>;
+            serialize_str(&str) -> Vec<u8>;
+            serialize_none() -> Vec<u8>;
+            serialize_unit() -> Vec<u8>;
+            serialize_unit_struct(&'static str) -> Vec<u8>;
+            serialize_unit_variant(&'static str, u32, &'static str) -> Vec<u8>;
+            serialize_seq(Option<usize>) -> Self::SerializeSeq;
+            serialize_tuple(usize) -> Self::SerializeTuple;
+            serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+            serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+            serialize_map(Option<usize>) -> Self::SerializeMap;
+            serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+            serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+        }
+    }
+}
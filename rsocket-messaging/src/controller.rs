@@ -0,0 +1,424 @@
+//! Runtime support of responders declared with `#[rsocket_controller]`.
+//!
+//! Requests are expected to carry composite metadata, with the route in routing metadata.
+//! Their data is (de)serialized by the MIME type declared in the metadata of the stream,
+//! falling back to the data MIME type of the connection, and responses follow the first
+//! supported MIME type the requester accepts. Handlers failing with an `ErrorBody` send it in
+//! the APPLICATION_ERROR frame, encoded with the MIME type of responses.
+//!
+//! A controller used as an RSocket directly knows neither the connection nor other codecs than
+//! the built-in ones, so it assumes JSON. Wrap it in a `Controller` instead:
+//!
+//! ```no_run,ignore
+//! let controller = Controller::new(StudentController::default()).codecs(codecs);
+//! RSocketFactory::receive()
+//!     .transport(TcpServerTransport::from("127.0.0.1:7878"))
+//!     .acceptor(Box::new(move |setup, _socket| Ok(Box::new(controller.with_setup(&setup)))))
+//!     .serve()
+//!     .await
+//! ```
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::extension::{
+    AcceptMimeTypesMetadata, CompositeMetadata, MimeType, MimeTypeMetadata, RoutingMetadata,
+};
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::{RoutePattern, Variables};
+use rsocket_rust::utils::Writeable;
+use rsocket_rust::{async_trait, error::RSocketError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::codec::{self, Codecs};
+use super::error::encode_error;
+use super::misc;
+
+/// The handlers of a controller, which `#[rsocket_controller]` implements.
+#[async_trait]
+pub trait Dispatch: Send + Sync {
+    async fn dispatch_fire_and_forget(&self, request: Request) -> Result<()>;
+
+    async fn dispatch_request_response(&self, request: Request) -> Result<Option<Payload>>;
+
+    fn dispatch_request_stream(&self, request: Request) -> Result<Flux<Result<Payload>>>;
+
+    /// Handles a channel, whose inputs start with the payload of the request if it has data.
+    fn dispatch_request_channel(
+        &self,
+        request: Request,
+        reqs: Flux<Result<Payload>>,
+    ) -> Result<Flux<Result<Payload>>>;
+}
+
+/// Serves a controller with a registry of codecs, and the data MIME type of a connection.
+pub struct Controller<T> {
+    inner: Arc<T>,
+    context: Context,
+}
+
+/// How the requests of a connection are decoded, and their responses encoded.
+#[derive(Clone)]
+pub struct Context {
+    codecs: Arc<Codecs>,
+    data_mime_type: MimeType,
+}
+
+/// Route patterns of the handlers of an interaction model, indexed in declaration order.
+pub struct Routes {
+    /// Ordered by the precedence of patterns.
+    patterns: Vec<(usize, RoutePattern)>,
+}
+
+/// A request received by a controller.
+pub struct Request {
+    payload: Payload,
+    route: Option<String>,
+    data_mime_type: MimeType,
+    response_mime_type: MimeType,
+    codecs: Arc<Codecs>,
+}
+
+impl<T> Controller<T>
+where
+    T: Dispatch,
+{
+    pub fn new(inner: T) -> Self {
+        Self::from_arc(Arc::new(inner))
+    }
+
+    pub fn from_arc(inner: Arc<T>) -> Self {
+        Controller {
+            inner,
+            context: Context::default(),
+        }
+    }
+
+    /// Replaces the registry of codecs, which has the built-in codecs by default.
+    pub fn codecs(mut self, codecs: Codecs) -> Self {
+        self.context = self.context.codecs(codecs);
+        self
+    }
+
+    /// Returns the controller of a connection, which decodes the requests declaring no MIME
+    /// type with the data MIME type of its SETUP.
+    pub fn with_setup(&self, setup: &SetupPayload) -> Self {
+        let mut context = self.context.clone();
+        if let Some(it) = setup.data_mime_type() {
+            context = context.data_mime_type(MimeType::from(it));
+        }
+        Controller {
+            inner: self.inner.clone(),
+            context,
+        }
+    }
+}
+
+impl<T> Clone for Controller<T> {
+    fn clone(&self) -> Self {
+        Controller {
+            inner: self.inner.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> RSocket for Controller<T>
+where
+    T: Dispatch + 'static,
+{
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        let request = Request::with_context(req, &self.context)?;
+        self.inner.dispatch_fire_and_forget(request).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        let request = Request::with_context(req, &self.context)?;
+        self.inner.dispatch_request_response(request).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        stream(req, &self.context, |request| {
+            self.inner.dispatch_request_stream(request)
+        })
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        let inner = self.inner.clone();
+        channel(reqs, &self.context, move |request, reqs| {
+            inner.dispatch_request_channel(request, reqs)
+        })
+    }
+}
+
+impl Context {
+    pub fn codecs(mut self, codecs: Codecs) -> Self {
+        self.codecs = Arc::new(codecs);
+        self
+    }
+
+    /// Sets the MIME type of requests which declare none.
+    pub fn data_mime_type(mut self, mime_type: MimeType) -> Self {
+        self.data_mime_type = mime_type;
+        self
+    }
+}
+
+impl Default for Context {
+    /// The built-in codecs, and JSON.
+    fn default() -> Context {
+        Context {
+            codecs: codec::builtin().clone(),
+            data_mime_type: MimeType::APPLICATION_JSON,
+        }
+    }
+}
+
+impl Routes {
+    /// Panics if a pattern is invalid, which `#[rsocket_controller]` rules out at compile time.
+    pub fn new(patterns: &[&str]) -> Routes {
+        let mut patterns: Vec<(usize, RoutePattern)> = patterns
+            .iter()
+            .map(|it| RoutePattern::parse(it).unwrap_or_else(|e| panic!("{}", e)))
+            .enumerate()
+            .collect();
+        patterns.sort_by_key(|(_, it)| it.precedence());
+        Routes { patterns }
+    }
+}
+
+impl Request {
+    /// Parses a request with the default `Context`.
+    pub fn new(payload: Payload) -> Result<Request> {
+        Self::with_context(payload, &Context::default())
+    }
+
+    pub fn with_context(payload: Payload, context: &Context) -> Result<Request> {
+        let routing: Option<RoutingMetadata> = invalid(payload.metadata_extension())?;
+        let declared: Option<MimeTypeMetadata> = invalid(payload.metadata_extension())?;
+        let accept: Option<AcceptMimeTypesMetadata> = invalid(payload.metadata_extension())?;
+        let data_mime_type = match declared {
+            Some(it) => it.get_mime_type().clone(),
+            None => context.data_mime_type.clone(),
+        };
+        let offered = context.codecs.mime_types();
+        let response_mime_type = accept
+            .as_ref()
+            .and_then(|it| it.negotiate(&offered))
+            .unwrap_or(&data_mime_type)
+            .clone();
+        Ok(Request {
+            payload,
+            route: routing.and_then(|it| it.get_tags().first().cloned()),
+            data_mime_type,
+            response_mime_type,
+            codecs: context.codecs.clone(),
+        })
+    }
+
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    pub fn data_mime_type(&self) -> &MimeType {
+        &self.data_mime_type
+    }
+
+    /// Selects the most specific handler whose pattern matches the route, returning its index.
+    pub fn select(&self, routes: &Routes) -> Result<(usize, Variables)> {
+        if let Some(route) = &self.route {
+            for (i, pattern) in routes.patterns.iter() {
+                if let Some(vars) = pattern.matches(route) {
+                    return Ok((*i, vars));
+                }
+            }
+        }
+        self.reject()
+    }
+
+    /// Fails with INVALID if the route is missing, otherwise REJECTED.
+    pub fn reject<T>(&self) -> Result<T> {
+        match &self.route {
+            Some(route) => {
+                let desc = format!("no handler for route: {}", route);
+                Err(RSocketError::RequestRejected(desc).into())
+            }
+            None => Err(RSocketError::RequestInvalid("missing route".into()).into()),
+        }
+    }
+
+    /// Parses a variable captured from the route.
+    pub fn variable<T>(&self, vars: &Variables, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let raw = vars.get(name).unwrap_or_default();
+        raw.parse().map_err(|e| {
+            let desc = format!("invalid variable {}: {}", name, e);
+            RSocketError::RequestInvalid(desc).into()
+        })
+    }
+
+    pub fn data<T>(&self) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        decode(&self.codecs, &self.data_mime_type, &self.payload)
+    }
+
+    /// Deserializes the inputs of a channel, which start with the payload of this request
+    /// unless it is marked as the request alone.
+    pub fn data_stream<T>(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<T>>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let codecs = self.codecs.clone();
+        let mime_type = self.data_mime_type.clone();
+        Box::pin(reqs.map(move |it| it.and_then(|req| decode(&codecs, &mime_type, &req))))
+    }
+
+    pub fn encode<T>(&self, value: &T) -> Result<Payload>
+    where
+        T: Serialize,
+    {
+        encode(
+            &self.codecs,
+            &self.data_mime_type,
+            &self.response_mime_type,
+            value,
+        )
+    }
+
+    /// Encodes the result of a handler, see `fail` for its error.
+    pub fn respond<T>(&self, result: Result<T>) -> Result<Payload>
+    where
+        T: Serialize,
+    {
+        match result {
+            Ok(value) => self.encode(&value),
+            Err(e) => Err(self.fail(e)),
+        }
+    }
+
+    /// Turns an `ErrorBody` into an APPLICATION_ERROR carrying it, other errors are kept.
+    pub fn fail(&self, e: anyhow::Error) -> anyhow::Error {
+        fail(&self.codecs, &self.response_mime_type, e)
+    }
+
+    pub fn encode_stream<S, T>(&self, results: S) -> Flux<Result<Payload>>
+    where
+        S: Send + 'static + Stream<Item = Result<T>>,
+        T: Serialize,
+    {
+        let codecs = self.codecs.clone();
+        let data_mime_type = self.data_mime_type.clone();
+        let response_mime_type = self.response_mime_type.clone();
+        Box::pin(results.map(move |it| match it {
+            Ok(value) => encode(&codecs, &data_mime_type, &response_mime_type, &value),
+            Err(e) => Err(fail(&codecs, &response_mime_type, e)),
+        }))
+    }
+}
+
+/// Routes a channel by its first payload, the inputs are handed to the handler. The first
+/// payload is the first input too, unless it is marked as the request alone.
+pub fn channel<F>(
+    mut reqs: Flux<Result<Payload>>,
+    context: &Context,
+    handle: F,
+) -> Flux<Result<Payload>>
+where
+    F: Send + 'static + FnOnce(Request, Flux<Result<Payload>>) -> Result<Flux<Result<Payload>>>,
+{
+    let context = context.clone();
+    Box::pin(rsocket_rust::stream! {
+        let first = match reqs.next().await {
+            Some(Ok(it)) => it,
+            Some(Err(e)) => {
+                yield Err(e);
+                return;
+            }
+            None => return,
+        };
+        let request = match Request::with_context(first.clone(), &context) {
+            Ok(it) => it,
+            Err(e) => {
+                yield Err(e);
+                return;
+            }
+        };
+        let reqs = if misc::is_input(&first) {
+            Box::pin(stream::once(async move { Ok(first) }).chain(reqs))
+        } else {
+            reqs
+        };
+        let mut results = match handle(request, reqs) {
+            Ok(it) => it,
+            Err(e) => {
+                yield Err(e);
+                return;
+            }
+        };
+        while let Some(next) = results.next().await {
+            yield next;
+        }
+    })
+}
+
+/// Routes a stream, the failure of routing is turned into a stream of the error.
+pub fn stream<F>(req: Payload, context: &Context, handle: F) -> Flux<Result<Payload>>
+where
+    F: FnOnce(Request) -> Result<Flux<Result<Payload>>>,
+{
+    match Request::with_context(req, context).and_then(handle) {
+        Ok(it) => it,
+        Err(e) => Box::pin(stream::iter(Some(Err(e)))),
+    }
+}
+
+fn decode<T>(codecs: &Codecs, mime_type: &MimeType, req: &Payload) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let raw = req.data().cloned().unwrap_or_default();
+    invalid(codecs.unmarshal(mime_type, &raw))
+}
+
+/// Blames the requester for failures of decoding.
+fn invalid<T>(result: Result<T>) -> Result<T> {
+    result.map_err(|e| RSocketError::RequestInvalid(e.to_string()).into())
+}
+
+fn fail(codecs: &Codecs, mime_type: &MimeType, e: anyhow::Error) -> anyhow::Error {
+    encode_error(e, |body| codecs.marshal(mime_type, body))
+}
+
+fn encode<T>(
+    codecs: &Codecs,
+    data_mime_type: &MimeType,
+    mime_type: &MimeType,
+    value: &T,
+) -> Result<Payload>
+where
+    T: Serialize,
+{
+    let mut bu = Payload::builder().set_data(codecs.marshal(mime_type, value)?);
+    if mime_type != data_mime_type {
+        let mut composite = CompositeMetadata::default();
+        composite.insert(&MimeTypeMetadata::new(mime_type.clone()));
+        bu = bu.set_metadata(composite.bytes());
+    }
+    Ok(bu.build())
+}
@@ -0,0 +1,108 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use rsocket_rust::{error::RSocketError, Result};
+use serde::{Serialize, Serializer};
+
+/// A structured error body, which responders send in an APPLICATION_ERROR frame.
+///
+/// Controllers and typed handlers encode it with the MIME type of the response when a handler
+/// fails with it, other responders send it as JSON text.
+///
+/// # Example
+/// ```no_run,ignore
+/// #[route("student.{id}")]
+/// async fn get(&self, #[var] id: i64) -> Result<Student> {
+///     self.find(id).ok_or_else(|| ErrorBody::new(Fault::NotFound(id)).into())
+/// }
+/// ```
+pub struct ErrorBody {
+    body: Box<dyn erased_serde::Serialize + Send + Sync>,
+}
+
+/// The error of a request, whose structured body is decoded if the responder sent one.
+#[derive(Debug)]
+pub enum RequestError<E> {
+    Body(E),
+    Other(anyhow::Error),
+}
+
+impl ErrorBody {
+    pub fn new<E>(body: E) -> ErrorBody
+    where
+        E: Serialize + Send + Sync + 'static,
+    {
+        ErrorBody {
+            body: Box::new(body),
+        }
+    }
+}
+
+impl Serialize for ErrorBody {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        erased_serde::serialize(&*self.body, serializer)
+    }
+}
+
+impl fmt::Display for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match serde_json::to_string(self) {
+            Ok(it) => f.write_str(&it),
+            Err(_) => f.write_str("unprintable error body"),
+        }
+    }
+}
+
+impl fmt::Debug for ErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ErrorBody({})", self)
+    }
+}
+
+impl StdError for ErrorBody {}
+
+impl<E> fmt::Display for RequestError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Body(e) => write!(f, "APPLICATION_ERROR: {:?}", e),
+            RequestError::Other(e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl<E> StdError for RequestError<E> where E: fmt::Debug {}
+
+/// Encodes the error body of a failed handler, keeping other errors.
+pub(crate) fn encode_error<F>(e: anyhow::Error, marshal: F) -> anyhow::Error
+where
+    F: FnOnce(&ErrorBody) -> Result<Vec<u8>>,
+{
+    match e.downcast::<ErrorBody>() {
+        Ok(body) => match marshal(&body) {
+            Ok(raw) => RSocketError::ApplicationData(raw.into()).into(),
+            Err(e) => e,
+        },
+        Err(e) => e,
+    }
+}
+
+/// Decodes the error body of an application error, keeping other errors.
+pub(crate) fn decode_error<E, F>(e: anyhow::Error, unmarshal: F) -> RequestError<E>
+where
+    F: FnOnce(&[u8]) -> Result<E>,
+{
+    let body = e
+        .downcast_ref::<RSocketError>()
+        .and_then(|it| it.application_data())
+        .and_then(|raw| unmarshal(raw).ok());
+    match body {
+        Some(body) => RequestError::Body(body),
+        None => RequestError::Other(e),
+    }
+}
@@ -0,0 +1,187 @@
+//! Typed handlers for `Router`, (de)serializing the data of payloads with a `SerDe`.
+//!
+//! # Example
+//! ```no_run,ignore
+//! let router = Router::builder()
+//!     .request_response(
+//!         "student.{id}.courses",
+//!         handler::request_response(json(), |page: Page, vars: Variables| async move {
+//!             find_courses(vars.get("id").unwrap(), page).await
+//!         }),
+//!     )
+//!     .build();
+//! ```
+//!
+//! CloudEvents are ingested by `fire_and_forget(cloudevents(), ..)` with `CloudEvent` data, and
+//! streamed by `event_stream`.
+//!
+//! Handlers failing with an `ErrorBody` send it in the APPLICATION_ERROR frame, encoded with the
+//! `SerDe` of the handler.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use futures::future::{self, BoxFuture};
+use futures::StreamExt;
+use rsocket_rust::extension::{CompositeMetadata, MimeType, MimeTypeMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::Variables;
+use rsocket_rust::utils::Writeable;
+use rsocket_rust::{error::RSocketError, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::cloudevent::CloudEvent;
+use super::error::encode_error;
+use super::misc::{self, cloudevents, SerDe};
+
+pub fn fire_and_forget<S, T, F, Fut>(
+    serde: S,
+    handler: F,
+) -> impl Send + Sync + Fn(Payload, Variables) -> BoxFuture<'static, Result<()>>
+where
+    S: SerDe + Send + Sync + 'static,
+    T: DeserializeOwned,
+    F: Send + Sync + 'static + Fn(T, Variables) -> Fut,
+    Fut: Send + 'static + Future<Output = Result<()>>,
+{
+    move |req, vars| match decode(&serde, &req) {
+        Ok(data) => Box::pin(handler(data, vars)),
+        Err(e) => Box::pin(future::ready(Err(e))),
+    }
+}
+
+pub fn request_response<S, T, R, F, Fut>(
+    serde: S,
+    handler: F,
+) -> impl Send + Sync + Fn(Payload, Variables) -> BoxFuture<'static, Result<Option<Payload>>>
+where
+    S: SerDe + Send + Sync + 'static,
+    T: DeserializeOwned,
+    R: Serialize + 'static,
+    F: Send + Sync + 'static + Fn(T, Variables) -> Fut,
+    Fut: Send + 'static + Future<Output = Result<R>>,
+{
+    let serde = Arc::new(serde);
+    move |req, vars| match decode(&*serde, &req) {
+        Ok(data) => {
+            let serde = serde.clone();
+            let res = handler(data, vars);
+            Box::pin(async move { respond(&*serde, res.await).map(Some) })
+        }
+        Err(e) => Box::pin(future::ready(Err(e))),
+    }
+}
+
+pub fn request_stream<S, T, R, F>(
+    serde: S,
+    handler: F,
+) -> impl Send + Sync + Fn(Payload, Variables) -> Flux<Result<Payload>>
+where
+    S: SerDe + Send + Sync + 'static,
+    T: DeserializeOwned,
+    R: Serialize + 'static,
+    F: Send + Sync + 'static + Fn(T, Variables) -> Flux<Result<R>>,
+{
+    let serde = Arc::new(serde);
+    move |req, vars| match decode(&*serde, &req) {
+        Ok(data) => {
+            let serde = serde.clone();
+            Box::pin(handler(data, vars).map(move |it| respond(&*serde, it)))
+        }
+        Err(e) => Box::pin(futures::stream::iter(Some(Err(e)))),
+    }
+}
+
+/// Adapts a stream handler emitting CloudEvents, whose payloads declare
+/// `application/cloudevents+json` in their metadata. Requests are deserialized by the `SerDe`.
+pub fn event_stream<S, T, R, F>(
+    serde: S,
+    handler: F,
+) -> impl Send + Sync + Fn(Payload, Variables) -> Flux<Result<Payload>>
+where
+    S: SerDe + Send + Sync + 'static,
+    T: DeserializeOwned,
+    R: Serialize + 'static,
+    F: Send + Sync + 'static + Fn(T, Variables) -> Flux<Result<CloudEvent<R>>>,
+{
+    let mut composite = CompositeMetadata::default();
+    composite.insert(&MimeTypeMetadata::new(
+        MimeType::APPLICATION_CLOUDEVENTS_JSON,
+    ));
+    let metadata = composite.bytes();
+    move |req, vars| match decode(&serde, &req) {
+        Ok(data) => {
+            let metadata = metadata.clone();
+            Box::pin(handler(data, vars).map(move |it| {
+                let events = cloudevents();
+                let data = match it {
+                    Ok(event) => events.marshal(&event)?,
+                    Err(e) => return Err(encode_error(e, |body| events.marshal(body))),
+                };
+                Ok(Payload::builder()
+                    .set_data(data)
+                    .set_metadata(metadata.clone())
+                    .build())
+            }))
+        }
+        Err(e) => Box::pin(futures::stream::iter(Some(Err(e)))),
+    }
+}
+
+/// Adapts a channel handler, whose inputs fail with INVALID if they can't be deserialized.
+pub fn request_channel<S, T, R, F>(
+    serde: S,
+    handler: F,
+) -> impl Send + Sync + Fn(Flux<Result<Payload>>, Variables) -> Flux<Result<Payload>>
+where
+    S: SerDe + Send + Sync + 'static,
+    T: DeserializeOwned + 'static,
+    R: Serialize + 'static,
+    F: Send + Sync + 'static + Fn(Flux<Result<T>>, Variables) -> Flux<Result<R>>,
+{
+    let serde = Arc::new(serde);
+    move |reqs, vars| {
+        let de = serde.clone();
+        let ser = serde.clone();
+        let reqs = reqs
+            .enumerate()
+            .filter(|(i, it)| {
+                // the first payload may be marked as the request alone
+                let request = *i == 0 && it.as_ref().is_ok_and(|req| !misc::is_input(req));
+                future::ready(!request)
+            })
+            .map(move |(_, it)| it.and_then(|req| decode(&*de, &req)));
+        Box::pin(handler(Box::pin(reqs), vars).map(move |it| respond(&*ser, it)))
+    }
+}
+
+fn decode<S, T>(serde: &S, req: &Payload) -> Result<T>
+where
+    S: SerDe,
+    T: DeserializeOwned,
+{
+    let raw = req.data().map(|it| &it[..]).unwrap_or_default();
+    serde
+        .unmarshal(raw)
+        .map_err(|e| RSocketError::RequestInvalid(e.to_string()).into())
+}
+
+fn encode<S, R>(serde: &S, res: &R) -> Result<Payload>
+where
+    S: SerDe,
+    R: Serialize,
+{
+    let data = serde.marshal(res)?;
+    Ok(Payload::builder().set_data(data).build())
+}
+
+fn respond<S, R>(serde: &S, res: Result<R>) -> Result<Payload>
+where
+    S: SerDe,
+    R: Serialize,
+{
+    match res {
+        Ok(res) => encode(serde, &res),
+        Err(e) => Err(encode_error(e, |body| serde.marshal(body))),
+    }
+}
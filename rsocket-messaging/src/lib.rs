@@ -1,7 +1,29 @@
 #![allow(clippy::upper_case_acronyms)]
 
+pub mod cloudevent;
+pub mod codec;
+pub mod controller;
+mod error;
+pub mod handler;
 mod misc;
 mod requester;
+#[cfg(feature = "prost")]
+pub mod rpc;
+pub mod transfer;
 
-pub use misc::{cbor, json, SerDe};
-pub use requester::{RequestSpec, Requester, RequesterBuilder};
+pub use cloudevent::CloudEvent;
+pub use controller::Controller;
+pub use error::{ErrorBody, RequestError};
+#[cfg(feature = "bincode")]
+pub use misc::bincode;
+#[cfg(feature = "rmp-serde")]
+pub use misc::msgpack;
+#[cfg(feature = "prost")]
+pub use misc::protobuf;
+pub use misc::{cbor, cloudevents, json, SerDe, REQUEST_ONLY_MIME_TYPE};
+pub use requester::{Message, RequestSpec, Requester, RequesterBuilder, Unpacker, Unpackers};
+pub use rsocket_rust_macros::{
+    fire_and_forget, request_channel, request_response, request_stream, route, rsocket_client,
+    rsocket_controller,
+};
+pub use transfer::Transfer;
@@ -1,6 +1,12 @@
+use bytes::BytesMut;
+use rsocket_rust::extension::{CompositeMetadata, MimeType};
+use rsocket_rust::prelude::Payload;
+use rsocket_rust::utils::Writeable;
 use rsocket_rust::Result;
 use serde::{de::DeserializeOwned, Serialize};
 
+use super::codec::{self, CborCodec, Codec, JsonCodec};
+
 pub trait SerDe {
     fn marshal<T>(&self, data: &T) -> Result<Vec<u8>>
     where
@@ -13,61 +19,77 @@ pub trait SerDe {
         T: Sized + DeserializeOwned;
 }
 
-#[derive(Default)]
-struct JsonSerDe {}
-
-impl SerDe for JsonSerDe {
+impl<C> SerDe for C
+where
+    C: Codec,
+{
     fn marshal<T>(&self, data: &T) -> Result<Vec<u8>>
     where
         T: Sized + Serialize,
     {
-        Ok(serde_json::to_vec(data)?)
+        Codec::marshal(self, data)
     }
 
     fn unmarshal<T>(&self, raw: &[u8]) -> Result<T>
     where
         T: Sized + DeserializeOwned,
     {
-        Ok(serde_json::from_slice(raw)?)
+        codec::unmarshal(self, raw)
     }
 }
 
-pub fn json() -> impl SerDe {
-    JsonSerDe {}
+pub fn json() -> impl SerDe + Codec {
+    JsonCodec
 }
 
-pub fn cbor() -> impl SerDe {
-    CborSerDe {}
+pub fn cbor() -> impl SerDe + Codec {
+    CborCodec
 }
 
-struct CborSerDe {}
+/// Serializes `CloudEvent`s in the structured mode of `application/cloudevents+json`, which is
+/// plain JSON.
+pub fn cloudevents() -> impl SerDe + Codec {
+    JsonCodec
+}
 
-impl SerDe for CborSerDe {
-    fn marshal<T>(&self, data: &T) -> Result<Vec<u8>>
-    where
-        T: Sized + Serialize,
-    {
-        Ok(serde_cbor::to_vec(data)?)
-    }
+#[cfg(feature = "rmp-serde")]
+pub fn msgpack() -> impl SerDe + Codec {
+    codec::MsgpackCodec
+}
 
-    fn unmarshal<T>(&self, raw: &[u8]) -> Result<T>
-    where
-        T: Sized + DeserializeOwned,
-    {
-        Ok(serde_cbor::from_slice(raw)?)
-    }
+#[cfg(feature = "bincode")]
+pub fn bincode() -> impl SerDe + Codec {
+    codec::BincodeCodec
 }
 
-pub(crate) fn marshal<T>(ser: impl SerDe, data: &T) -> Result<Vec<u8>>
-where
-    T: Sized + Serialize,
-{
-    ser.marshal(data)
+/// Serializes `Protobuf` messages only.
+#[cfg(feature = "prost")]
+pub fn protobuf() -> impl SerDe + Codec {
+    codec::ProtobufCodec
 }
 
-pub(crate) fn unmarshal<T>(de: impl SerDe, raw: &[u8]) -> Result<T>
-where
-    T: Sized + DeserializeOwned,
-{
-    de.unmarshal(raw)
+/// Marks the first payload of a channel which carries the request alone, so that a channel can be
+/// requested without inputs. Any other first payload is the first input.
+pub const REQUEST_ONLY_MIME_TYPE: &str = "application/x.rsocket.request-only";
+
+/// Whether a payload of a channel carries an input, unless it is marked as the request alone.
+pub(crate) fn is_input(payload: &Payload) -> bool {
+    let marker = MimeType::from(REQUEST_ONLY_MIME_TYPE);
+    match payload.metadata() {
+        Some(it) => !CompositeMetadata::entries(it.clone())
+            .map_while(|it| it.ok())
+            .any(|it| it.get_mime_type() == &marker),
+        None => true,
+    }
+}
+
+/// Marks the payload, whose metadata is composite, as the request alone.
+pub(crate) fn request_only(payload: Payload) -> Payload {
+    let (data, metadata) = payload.split();
+    let mut metadata = metadata.map(BytesMut::from).unwrap_or_default();
+    CompositeMetadata::builder()
+        .push(MimeType::from(REQUEST_ONLY_MIME_TYPE), b"")
+        .build()
+        .write_to(&mut metadata);
+    Payload::new(data, Some(metadata.freeze()))
 }
@@ -1,48 +1,89 @@
 use std::collections::LinkedList;
-use std::net::SocketAddr;
+use std::fmt::Display;
+use std::future::Future;
 use std::sync::Arc;
 
 use bytes::{Bytes, BytesMut};
-use rsocket_rust::extension::{CompositeMetadata, MimeType, RoutingMetadata};
+use futures::future::{self, BoxFuture};
+use futures::stream;
+use rsocket_rust::extension::{
+    AcceptMimeTypesMetadata, CompositeMetadata, CompositeMetadataEntry, MetadataExtension,
+    MimeType, MimeTypeMetadata, RoutingMetadata,
+};
 use rsocket_rust::prelude::*;
+use rsocket_rust::router::RoutePattern;
+use rsocket_rust::transport::{BoxConnection, BoxTransport, Transports};
 use rsocket_rust::utils::Writeable;
-use rsocket_rust::{error::RSocketError, Result};
-use rsocket_rust_transport_tcp::TcpClientTransport;
-use rsocket_rust_transport_websocket::WebsocketClientTransport;
+use rsocket_rust::{error::RSocketError, ClientBuilder, Result};
 use serde::{de::DeserializeOwned, Serialize};
-use url::Url;
 
-use super::misc::{self, marshal, unmarshal};
+use super::cloudevent::CloudEvent;
+use super::codec::{Codec, Codecs};
+use super::error::{decode_error, RequestError};
+use super::misc;
 
-type FnMetadata = Box<dyn FnMut() -> Result<(MimeType, Vec<u8>)>>;
-type FnData = Box<dyn FnMut(&MimeType) -> Result<Vec<u8>>>;
-type PreflightResult = Result<(Payload, MimeType, Arc<Box<dyn RSocket>>)>;
-type UnpackerResult = Result<(MimeType, Option<Payload>)>;
-type UnpackersResult = Result<(MimeType, Flux<Result<Payload>>)>;
-
-enum TransportKind {
-    TCP(String, u16),
-    WS(String),
-}
+type Metadata = Result<(MimeType, Vec<u8>)>;
+type FnProvider = Arc<dyn Send + Sync + Fn(Arc<Codecs>) -> BoxFuture<'static, Result<Vec<u8>>>>;
+type PreflightResult = Result<(Pending, Decoding, Arc<Box<dyn RSocket>>)>;
+type UnpackersResult = Result<(Decoding, Flux<Result<Payload>>)>;
 
 pub struct Requester {
     rsocket: Arc<Box<dyn RSocket>>,
+    data_mime_type: MimeType,
+    codecs: Arc<Codecs>,
+    defaults: Arc<Vec<DefaultMetadata>>,
 }
 
 pub struct RequestSpec {
     rsocket: Arc<Box<dyn RSocket>>,
     data_mime_type: MimeType,
-    metadatas: LinkedList<FnMetadata>,
-    data: Option<FnData>,
+    codecs: Arc<Codecs>,
+    defaults: Arc<Vec<DefaultMetadata>>,
+    stream_mime_type: Option<MimeType>,
+    accept_mime_types: Vec<MimeType>,
+    metadatas: LinkedList<Metadata>,
+    data: Option<Data>,
+}
+
+/// Data encoded as soon as it is set, along with the MIME type of its encoding if it is typed.
+struct Data {
+    mime_type: Option<MimeType>,
+    raw: Result<Vec<u8>>,
+}
+
+/// Metadata of every request, unless the request has metadata of the same MIME type.
+#[derive(Clone)]
+struct DefaultMetadata {
+    mime_type: MimeType,
+    provider: FnProvider,
+}
+
+/// An encoded request, waiting for its default metadata.
+struct Pending {
+    composite: CompositeMetadata,
+    data: Option<Vec<u8>>,
+    defaults: Vec<DefaultMetadata>,
+    codecs: Arc<Codecs>,
+}
+
+/// Decides the MIME type of response data.
+struct Decoding {
+    /// The first supported MIME type accepted, otherwise the data MIME type.
+    response_mime_type: MimeType,
+    codecs: Arc<Codecs>,
 }
 
 #[derive(Default)]
 pub struct RequesterBuilder {
     data_mime_type: Option<MimeType>,
     route: Option<String>,
-    metadata: LinkedList<FnMetadata>,
-    data: Option<FnData>,
-    tp: Option<TransportKind>,
+    metadata: LinkedList<Metadata>,
+    data: Option<Data>,
+    codecs: Option<Codecs>,
+    defaults: Vec<DefaultMetadata>,
+    uri: Option<String>,
+    transports: Option<Transports>,
+    client: Option<ClientBuilder<BoxTransport, BoxConnection>>,
 }
 
 pub struct Unpackers {
@@ -50,7 +91,15 @@ pub struct Unpackers {
 }
 
 pub struct Unpacker {
-    inner: UnpackerResult,
+    /// Absent if the request fails before it is sent.
+    decoding: Option<Decoding>,
+    inner: Result<Option<Payload>>,
+}
+
+/// A response deserialized by the data MIME type, along with its metadata.
+pub struct Message<T> {
+    data: Option<T>,
+    payload: Payload,
 }
 
 impl RequesterBuilder {
@@ -70,47 +119,167 @@ impl RequesterBuilder {
         self
     }
 
+    /// Encodes the data of the SETUP right away, set the data MIME type and codecs before.
     pub fn setup_data<D>(mut self, data: D) -> Self
     where
         D: Sized + Serialize + 'static,
     {
-        self.data = Some(Box::new(move |mime_type: &MimeType| {
-            do_marshal(mime_type, &data)
-        }));
+        let mime_type = self
+            .data_mime_type
+            .clone()
+            .unwrap_or(MimeType::APPLICATION_JSON);
+        let codecs = self.codecs.get_or_insert_with(Codecs::default);
+        self.data = Some(Data {
+            raw: codecs.marshal(&mime_type, &data),
+            mime_type: Some(mime_type),
+        });
         self
     }
 
+    /// Encodes metadata of the SETUP right away, set the codecs before.
     pub fn setup_metadata<M, T>(mut self, metadata: M, mime_type: T) -> Self
     where
         M: Sized + Serialize + 'static,
         T: Into<MimeType>,
     {
         let mime_type = mime_type.into();
-        self.metadata.push_back(Box::new(move || {
-            let raw = do_marshal(&mime_type, &metadata)?;
-            Ok((mime_type.clone(), raw))
-        }));
+        let codecs = self.codecs.get_or_insert_with(Codecs::default);
+        let raw = codecs.marshal(&mime_type, &metadata);
+        self.metadata.push_back(raw.map(|it| (mime_type, it)));
         self
     }
 
-    pub fn connect_tcp<A>(mut self, host: A, port: u16) -> Self
+    /// Adds metadata to every request, unless the request has metadata of the same MIME type.
+    pub fn default_metadata<T, M>(self, metadata: T, mime_type: M) -> Self
     where
-        A: Into<String>,
+        T: Sized + Send + Sync + Serialize + 'static,
+        M: Into<MimeType>,
     {
-        self.tp = Some(TransportKind::TCP(host.into(), port));
+        let mime_type = mime_type.into();
+        let marshal = mime_type.clone();
+        self.provide(
+            mime_type,
+            Arc::new(move |codecs: Arc<Codecs>| {
+                Box::pin(future::ready(codecs.marshal(&marshal, &metadata)))
+            }),
+        )
+    }
+
+    /// Adds raw metadata to every request, unless the request has metadata of the same MIME
+    /// type.
+    pub fn default_metadata_raw<I, M>(self, metadata: I, mime_type: M) -> Self
+    where
+        I: Into<Vec<u8>>,
+        M: Into<MimeType>,
+    {
+        let metadata = metadata.into();
+        self.provide(
+            mime_type.into(),
+            Arc::new(move |_: Arc<Codecs>| Box::pin(future::ready(Ok(metadata.clone())))),
+        )
+    }
+
+    /// Adds raw metadata to every request from a provider, which is called for each request,
+    /// unless the request has metadata of the same MIME type.
+    ///
+    /// A provider can refresh expired credentials, it should cache them otherwise. Requests fail
+    /// with the errors of the provider.
+    pub fn metadata_provider<M, F, R, I>(self, mime_type: M, provider: F) -> Self
+    where
+        M: Into<MimeType>,
+        F: Fn() -> R + Send + Sync + 'static,
+        R: Future<Output = Result<I>> + Send + 'static,
+        I: Into<Vec<u8>>,
+    {
+        self.provide(
+            mime_type.into(),
+            Arc::new(move |_: Arc<Codecs>| {
+                let metadata = provider();
+                Box::pin(async move { Ok(metadata.await?.into()) })
+            }),
+        )
+    }
+
+    /// Replaces the default metadata of the same MIME type.
+    fn provide(mut self, mime_type: MimeType, provider: FnProvider) -> Self {
+        self.defaults.retain(|it| it.mime_type != mime_type);
+        self.defaults.push(DefaultMetadata {
+            mime_type,
+            provider,
+        });
+        self
+    }
+
+    /// Replaces the registry of codecs, which has JSON and CBOR by default.
+    pub fn codecs(mut self, codecs: Codecs) -> Self {
+        self.codecs = Some(codecs);
+        self
+    }
+
+    /// Registers a codec, replacing the one of the same MIME type.
+    pub fn codec<M, C>(mut self, mime_type: M, codec: C) -> Self
+    where
+        M: Into<MimeType>,
+        C: Codec + 'static,
+    {
+        self.codecs
+            .get_or_insert_with(Codecs::default)
+            .register(mime_type, codec);
+        self
+    }
+
+    /// Replaces the registry of transports, which has `tcp`, `unix`, `ws` and `wss` by default,
+    /// plus `tls` with the feature `tls`.
+    pub fn transports(mut self, transports: Transports) -> Self {
+        self.transports = Some(transports);
+        self
+    }
+
+    /// Registers the transport of a URI scheme, replacing the previous one.
+    pub fn scheme<F>(mut self, scheme: &str, factory: F) -> Self
+    where
+        F: Fn(&str) -> Result<BoxTransport> + Send + Sync + 'static,
+    {
+        self.transports
+            .get_or_insert_with(default_transports)
+            .register(scheme, factory);
         self
     }
 
-    pub fn connect_websocket<I>(mut self, url: I) -> Self
+    /// Connects with a prebuilt client, e.g. to configure keepalive, fragmentation or the
+    /// acceptor. The MIME types and the SETUP payload of the requester override its own.
+    pub fn client(mut self, client: ClientBuilder<BoxTransport, BoxConnection>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Connects to a URI like `tcp://127.0.0.1:7878` or `ws://127.0.0.1:7878`, whose transport
+    /// is created by the registry of transports.
+    pub fn connect<I>(mut self, uri: I) -> Self
     where
         I: Into<String>,
     {
-        self.tp = Some(TransportKind::WS(url.into()));
+        self.uri = Some(uri.into());
         self
     }
 
+    pub fn connect_tcp<A>(self, host: A, port: u16) -> Self
+    where
+        A: Into<String>,
+    {
+        self.connect(format!("tcp://{}:{}", host.into(), port))
+    }
+
+    pub fn connect_websocket<I>(self, url: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.connect(url)
+    }
+
     pub async fn build(self) -> Result<Requester> {
         let data_mime_type = self.data_mime_type.unwrap_or(MimeType::APPLICATION_JSON);
+        let codecs = self.codecs.unwrap_or_default();
 
         let mut added = 0usize;
         let mut composite_builder = CompositeMetadata::builder();
@@ -122,8 +291,8 @@ impl RequesterBuilder {
             added += 1;
         }
 
-        for mut gen in self.metadata.into_iter() {
-            let (mime_type, raw) = gen()?;
+        for it in self.metadata.into_iter() {
+            let (mime_type, raw) = it?;
             composite_builder = composite_builder.push(mime_type, raw);
             added += 1;
         }
@@ -134,88 +303,146 @@ impl RequesterBuilder {
             payload_builder = payload_builder.set_metadata(composite_builder.build());
         }
 
-        if let Some(mut gen) = self.data {
-            payload_builder = payload_builder.set_data(gen(&data_mime_type)?);
+        let has_setup = added > 0 || self.data.is_some();
+        if let Some(data) = self.data {
+            payload_builder = payload_builder.set_data(data.encoded_as(&data_mime_type)?);
         }
 
-        let setup = payload_builder.build();
-
-        match self.tp {
-            Some(TransportKind::TCP(h, p)) => {
-                let addr: SocketAddr = format!("{}:{}", h, p).parse()?;
-                let cli = RSocketFactory::connect()
-                    .data_mime_type(data_mime_type)
-                    .setup(setup)
-                    .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
-                    .transport(TcpClientTransport::from(addr))
-                    .start()
-                    .await?;
-                let rsocket: Box<dyn RSocket> = Box::new(cli);
-                let requester = Requester::from(rsocket);
-                Ok(requester)
-            }
-            Some(TransportKind::WS(u)) => {
-                let url = Url::parse(&u)?;
-                let cli = RSocketFactory::connect()
-                    .data_mime_type(data_mime_type)
-                    .setup(setup)
-                    .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
-                    .transport(WebsocketClientTransport::from(url))
-                    .start()
-                    .await?;
-                let rsocket: Box<dyn RSocket> = Box::new(cli);
-                let requester = Requester::from(rsocket);
-                Ok(requester)
-            }
-            None => Err(RSocketError::WithDescription("Missing transport!".into()).into()),
+        let mut client = self.client.unwrap_or_else(RSocketFactory::connect);
+        if let Some(uri) = self.uri {
+            let transports = self.transports.unwrap_or_else(default_transports);
+            client = client.transport(transports.resolve(&uri)?);
         }
+        if has_setup {
+            client = client.setup(payload_builder.build());
+        }
+        let cli = client
+            .data_mime_type(data_mime_type.clone())
+            .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+            .start()
+            .await?;
+        let rsocket: Box<dyn RSocket> = Box::new(cli);
+        let mut requester = Requester::new(rsocket, data_mime_type).set_codecs(codecs);
+        requester.defaults = Arc::new(self.defaults);
+        Ok(requester)
     }
 }
 
+/// The transports of TCP, TLS, Unix domain sockets and WebSocket.
+fn default_transports() -> Transports {
+    let mut transports = Transports::default();
+    rsocket_rust_transport_tcp::register_schemes(&mut transports);
+    rsocket_rust_transport_websocket::register_schemes(&mut transports);
+    transports
+}
+
 impl From<Box<dyn RSocket>> for Requester {
     fn from(rsocket: Box<dyn RSocket>) -> Requester {
+        Requester::new(rsocket, MimeType::APPLICATION_JSON)
+    }
+}
+
+impl Requester {
+    /// Wraps a connection, whose SETUP declared the data MIME type.
+    pub fn new(rsocket: Box<dyn RSocket>, data_mime_type: MimeType) -> Requester {
         Requester {
             rsocket: Arc::new(rsocket),
+            data_mime_type,
+            codecs: Arc::new(Codecs::default()),
+            defaults: Arc::new(vec![]),
         }
     }
-}
 
-impl Requester {
+    /// Replaces the registry of codecs, which has JSON and CBOR by default.
+    pub fn set_codecs(mut self, codecs: Codecs) -> Self {
+        self.codecs = Arc::new(codecs);
+        self
+    }
+
     pub fn builder() -> RequesterBuilder {
         RequesterBuilder::default()
     }
 
     pub fn route(&self, route: &str) -> RequestSpec {
-        let routing = RoutingMetadata::builder().push_str(route).build();
-        let mut buf = BytesMut::new();
-        routing.write_to(&mut buf);
+        self.routing(Ok(String::from(route)))
+    }
+
+    /// Routes to a template like `user.{id}.orders`, whose variables are substituted with
+    /// the arguments in order.
+    pub fn route_with(&self, template: &str, args: &[&dyn Display]) -> RequestSpec {
+        self.routing(RoutePattern::parse(template).and_then(|it| it.expand(args)))
+    }
+
+    fn routing(&self, route: Result<String>) -> RequestSpec {
+        let route = route.map_err(|e| e.to_string()).and_then(|route| {
+            if route.len() > RoutingMetadata::MAX_TAG_LEN {
+                Err(format!("exceeded maximum routing tag length: {}", route))
+            } else {
+                Ok(route)
+            }
+        });
+        let routing = route.map(|it| RoutingMetadata::builder().push(it).build().bytes());
 
-        let mut metadatas: LinkedList<FnMetadata> = LinkedList::new();
-        metadatas.push_back(Box::new(move || {
-            Ok((MimeType::MESSAGE_X_RSOCKET_ROUTING_V0, buf.to_vec()))
-        }));
+        let mut spec = self.spec();
+        spec.metadatas.push_back(match routing {
+            Ok(raw) => Ok((MimeType::MESSAGE_X_RSOCKET_ROUTING_V0, raw)),
+            Err(e) => Err(RSocketError::WithDescription(e).into()),
+        });
+        spec
+    }
+
+    /// Starts a request without route, e.g. a metadata push.
+    pub fn metadata<T, M>(&self, metadata: T, mime_type: M) -> RequestSpec
+    where
+        T: Sized + Serialize + 'static,
+        M: Into<MimeType>,
+    {
+        self.spec().metadata(metadata, mime_type)
+    }
 
+    fn spec(&self) -> RequestSpec {
         RequestSpec {
             rsocket: self.rsocket.clone(),
-            data_mime_type: MimeType::APPLICATION_JSON,
-            metadatas,
+            data_mime_type: self.data_mime_type.clone(),
+            codecs: self.codecs.clone(),
+            defaults: self.defaults.clone(),
+            stream_mime_type: None,
+            accept_mime_types: vec![],
+            metadatas: LinkedList::new(),
             data: None,
         }
     }
 }
 
 impl RequestSpec {
+    /// Encodes the data of this request with another MIME type than the connection's, which
+    /// must be set before the data.
+    pub fn data_mime_type<M>(mut self, mime_type: M) -> Self
+    where
+        M: Into<MimeType>,
+    {
+        self.stream_mime_type = Some(mime_type.into());
+        self
+    }
+
+    /// Adds a MIME type accepted for the response data, in the order of preference.
+    pub fn accept<M>(mut self, mime_type: M) -> Self
+    where
+        M: Into<MimeType>,
+    {
+        self.accept_mime_types.push(mime_type.into());
+        self
+    }
+
+    /// Encodes the metadata right away.
     pub fn metadata<T, M>(mut self, metadata: T, mime_type: M) -> Self
     where
         T: Sized + Serialize + 'static,
         M: Into<MimeType>,
     {
         let mime_type = mime_type.into();
-        let f: FnMetadata = Box::new(move || {
-            let raw = do_marshal(&mime_type, &metadata)?;
-            Ok((mime_type.clone(), raw))
-        });
-        self.metadatas.push_back(f);
+        let raw = self.codecs.marshal(&mime_type, &metadata);
+        self.metadatas.push_back(raw.map(|it| (mime_type, it)));
         self
     }
 
@@ -224,18 +451,21 @@ impl RequestSpec {
         I: Into<Vec<u8>>,
         M: Into<MimeType>,
     {
-        let mime_type = mime_type.into();
-        let metadata = metadata.into();
         self.metadatas
-            .push_back(Box::new(move || Ok((mime_type.clone(), metadata.clone()))));
+            .push_back(Ok((mime_type.into(), metadata.into())));
         self
     }
 
+    /// Encodes the data right away, with the data MIME type of this request.
     pub fn data<T>(mut self, data: T) -> Self
     where
         T: Sized + Serialize + 'static,
     {
-        self.data = Some(Box::new(move |m| do_marshal(m, &data)));
+        let mime_type = self.mime_type();
+        self.data = Some(Data {
+            raw: self.codecs.marshal(&mime_type, &data),
+            mime_type: Some(mime_type),
+        });
         self
     }
 
@@ -243,77 +473,275 @@ impl RequestSpec {
     where
         I: Into<Vec<u8>>,
     {
-        let data = data.into();
-        self.data = Some(Box::new(move |_m| Ok(data.clone())));
+        self.data = Some(Data {
+            mime_type: None,
+            raw: Ok(data.into()),
+        });
+        self
+    }
+
+    /// Sends the event as the data, in the structured mode of `application/cloudevents+json`.
+    pub fn event<T>(mut self, event: CloudEvent<T>) -> Self
+    where
+        T: Sized + Serialize + 'static,
+    {
+        self.stream_mime_type = Some(MimeType::APPLICATION_CLOUDEVENTS_JSON);
+        self.data = Some(Data {
+            mime_type: self.stream_mime_type.clone(),
+            raw: self
+                .codecs
+                .marshal(&MimeType::APPLICATION_CLOUDEVENTS_JSON, &event),
+        });
         self
     }
 
     pub async fn retrieve(self) -> Result<()> {
-        let (req, _mime_type, rsocket) = self.preflight()?;
-        rsocket.fire_and_forget(req).await
+        let (pending, _decoding, rsocket) = self.preflight()?;
+        rsocket.fire_and_forget(pending.payload().await?).await
     }
 
     pub async fn retrieve_mono(self) -> Unpacker {
         match self.preflight() {
-            Ok((req, mime_type, rsocket)) => {
-                let res = rsocket.request_response(req).await;
-                match res {
-                    Ok(v) => Unpacker {
-                        inner: Ok((mime_type, v)),
-                    },
-                    Err(e) => Unpacker { inner: Err(e) },
+            Ok((pending, decoding, rsocket)) => {
+                let inner = match pending.payload().await {
+                    Ok(req) => rsocket.request_response(req).await,
+                    Err(e) => Err(e),
+                };
+                Unpacker {
+                    decoding: Some(decoding),
+                    inner,
                 }
             }
-            Err(e) => Unpacker { inner: Err(e) },
+            Err(e) => Unpacker {
+                decoding: None,
+                inner: Err(e),
+            },
         }
     }
 
+    /// Starts a stream once the returned results are polled.
     pub fn retrieve_flux(self) -> Unpackers {
         match self.preflight() {
-            Ok((req, mime_type, rsocket)) => {
-                let results = rsocket.request_stream(req);
+            Ok((pending, decoding, rsocket)) => {
+                let results = stream::once(async move {
+                    match pending.payload().await {
+                        Ok(req) => rsocket.request_stream(req),
+                        Err(e) => Box::pin(stream::iter(Some(Err(e)))),
+                    }
+                })
+                .flatten();
                 Unpackers {
-                    inner: Ok((mime_type, results)),
+                    inner: Ok((decoding, Box::pin(results))),
                 }
             }
             Err(e) => Unpackers { inner: Err(e) },
         }
     }
 
+    /// Starts a channel with the inputs, the metadata of this request is sent along with the
+    /// first one, or alone and marked with `REQUEST_ONLY_MIME_TYPE` if there are no inputs. The
+    /// data of this request, if any, is sent before the inputs.
+    pub fn retrieve_channel<S, T>(self, inputs: S) -> Unpackers
+    where
+        S: Stream<Item = T> + Send + 'static,
+        T: Sized + Serialize + Send + 'static,
+    {
+        let codecs = self.codecs.clone();
+        let mime_type = self.mime_type();
+        let inputs = inputs.map(move |it| {
+            let data = codecs.marshal(&mime_type, &it)?;
+            Ok(Payload::builder().set_data(data).build())
+        });
+        self.retrieve_channel_raw(inputs)
+    }
+
+    /// Starts a channel with inputs which are encoded already.
+    pub(crate) fn retrieve_channel_raw<S>(self, inputs: S) -> Unpackers
+    where
+        S: Stream<Item = Result<Payload>> + Send + 'static,
+    {
+        match self.preflight() {
+            Ok((pending, decoding, rsocket)) => {
+                let has_data = pending.data.is_some();
+                let reqs = rsocket_rust::stream! {
+                    let req = match pending.payload().await {
+                        Ok(it) => it,
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    };
+                    let mut request = if has_data {
+                        yield Ok(req);
+                        None
+                    } else {
+                        Some(req)
+                    };
+                    for await input in inputs {
+                        // the metadata of this request goes along with the first input
+                        let input = match (input, request.take()) {
+                            (Ok(input), Some(req)) => {
+                                Ok(Payload::new(input.split().0, req.metadata().cloned()))
+                            }
+                            (input, Some(req)) => {
+                                yield Ok(misc::request_only(req));
+                                input
+                            }
+                            (input, None) => input,
+                        };
+                        yield input;
+                    }
+                    // a channel can't be requested without a payload, send the metadata alone
+                    if let Some(req) = request {
+                        yield Ok(misc::request_only(req));
+                    }
+                };
+                Unpackers {
+                    inner: Ok((decoding, rsocket.request_channel(Box::pin(reqs)))),
+                }
+            }
+            Err(e) => Unpackers { inner: Err(e) },
+        }
+    }
+
+    /// Pushes the metadata of this request, which can't carry data.
+    pub async fn metadata_push(self) -> Result<()> {
+        if self.data.is_some() {
+            let desc = "metadata push can't carry data";
+            return Err(RSocketError::WithDescription(desc.into()).into());
+        }
+        let (pending, _decoding, rsocket) = self.preflight()?;
+        rsocket.metadata_push(pending.payload().await?).await
+    }
+
+    /// The MIME type of the data and inputs of this request.
+    fn mime_type(&self) -> MimeType {
+        self.stream_mime_type
+            .clone()
+            .unwrap_or_else(|| self.data_mime_type.clone())
+    }
+
     #[inline]
     fn preflight(self) -> PreflightResult {
-        let mut b = BytesMut::new();
         let mut c = CompositeMetadata::builder();
+        let mut defaults = self.defaults.to_vec();
 
-        for mut b in self.metadatas.into_iter() {
-            let (mime_type, raw) = b()?;
+        for it in self.metadatas.into_iter() {
+            let (mime_type, raw) = it?;
+            defaults.retain(|it| it.mime_type != mime_type);
             c = c.push(mime_type, raw);
         }
-        c.build().write_to(&mut b);
+        let mut composite = c.build();
+        if let Some(mime_type) = &self.stream_mime_type {
+            MimeTypeMetadata::new(mime_type.clone()).inject(&mut composite);
+        }
+        let accept = if self.accept_mime_types.is_empty() {
+            None
+        } else {
+            let mut bu = AcceptMimeTypesMetadata::builder();
+            for it in self.accept_mime_types.into_iter() {
+                bu = bu.push(it);
+            }
+            let accept = bu.build();
+            accept.inject(&mut composite);
+            Some(accept)
+        };
+
+        let data_mime_type = self.stream_mime_type.unwrap_or(self.data_mime_type);
+        let data = match self.data {
+            Some(data) => Some(data.encoded_as(&data_mime_type)?),
+            None => None,
+        };
+        let offered = self.codecs.mime_types();
+        let response_mime_type = accept
+            .as_ref()
+            .and_then(|it| it.negotiate(&offered))
+            .unwrap_or(&data_mime_type)
+            .clone();
+        let pending = Pending {
+            composite,
+            data,
+            defaults,
+            codecs: self.codecs.clone(),
+        };
+        let decoding = Decoding {
+            response_mime_type,
+            codecs: self.codecs,
+        };
+        Ok((pending, decoding, self.rsocket))
+    }
+}
 
+impl Data {
+    /// Returns the encoded data, unless it was encoded with another MIME type.
+    fn encoded_as(self, mime_type: &MimeType) -> Result<Vec<u8>> {
+        match self.mime_type {
+            Some(it) if &it != mime_type => {
+                let desc = format!(
+                    "data encoded as {} instead of {}, set the MIME type before the data",
+                    it, mime_type
+                );
+                Err(RSocketError::WithDescription(desc).into())
+            }
+            _ => self.raw,
+        }
+    }
+}
+
+impl Pending {
+    /// Completes the composite metadata with the default metadata.
+    async fn payload(self) -> Result<Payload> {
+        let mut composite = self.composite;
+        for it in self.defaults.into_iter() {
+            let raw = (it.provider)(self.codecs.clone()).await?;
+            composite.push(CompositeMetadataEntry::new(it.mime_type, Bytes::from(raw)));
+        }
+        let mut b = BytesMut::new();
+        composite.write_to(&mut b);
         let mut bu = Payload::builder().set_metadata(b.to_vec());
-        if let Some(mut gen) = self.data {
-            let raw = gen(&self.data_mime_type)?;
-            bu = bu.set_data(raw);
+        if let Some(data) = self.data {
+            bu = bu.set_data(data);
         }
-        Ok((bu.build(), self.data_mime_type, self.rsocket))
+        Ok(bu.build())
     }
 }
 
 impl Unpackers {
+    /// Returns the payloads as they are received, without decoding them.
+    pub(crate) fn into_payloads(self) -> Flux<Result<Payload>> {
+        match self.inner {
+            Ok((_, results)) => results,
+            Err(e) => Box::pin(stream::iter(Some(Err(e)))),
+        }
+    }
+
     pub async fn block<T>(self) -> Result<Vec<T>>
     where
         T: Sized + DeserializeOwned,
     {
-        let (mime_type, mut results) = self.inner?;
+        let (decoding, mut results) = self.inner?;
         let mut res = Vec::new();
         while let Some(next) = results.next().await {
             let v = next?;
-            if let Some(data) = v.data() {
-                let t = do_unmarshal::<T>(&mime_type, data)?;
-                if let Some(t) = t {
-                    res.push(t);
-                }
+            if let Some(t) = decoding.decode(&v)? {
+                res.push(t);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Like `block`, but decodes the error body sent by the responder into `E`.
+    pub async fn block_or_err<T, E>(self) -> std::result::Result<Vec<T>, RequestError<E>>
+    where
+        T: Sized + DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let (decoding, mut results) = self.inner.map_err(RequestError::Other)?;
+        let mut res = Vec::new();
+        while let Some(next) = results.next().await {
+            let v = next.map_err(|e| decoding.error(e))?;
+            if let Some(t) = decoding.decode(&v).map_err(RequestError::Other)? {
+                res.push(t);
             }
         }
         Ok(res)
@@ -323,68 +751,213 @@ impl Unpackers {
     where
         T: Sized + DeserializeOwned,
     {
-        let (mime_type, mut results) = self.inner?;
+        let (decoding, mut results) = self.inner?;
         while let Some(next) = results.next().await {
             let v = next?;
-            if let Some(data) = v.data() {
-                let t = do_unmarshal::<T>(&mime_type, data)?;
-                if let Some(t) = t {
-                    callback(t);
-                }
+            if let Some(t) = decoding.decode(&v)? {
+                callback(t);
             }
         }
         Ok(())
     }
+
+    /// Deserializes the results lazily, skipping those without data.
+    pub fn flux<T>(self) -> Flux<Result<T>>
+    where
+        T: Sized + DeserializeOwned + Send + 'static,
+    {
+        let (decoding, results) = match self.inner {
+            Ok(it) => it,
+            Err(e) => return Box::pin(stream::iter(Some(Err(e)))),
+        };
+        Box::pin(results.filter_map(move |next| {
+            let res = next.and_then(|it| decoding.decode(&it));
+            future::ready(res.transpose())
+        }))
+    }
+
+    /// Like `flux`, but decodes the error body sent by the responder into `E`.
+    pub fn flux_or_err<T, E>(self) -> Flux<std::result::Result<T, RequestError<E>>>
+    where
+        T: Sized + DeserializeOwned + Send + 'static,
+        E: DeserializeOwned + Send + 'static,
+    {
+        let (decoding, results) = match self.inner {
+            Ok(it) => it,
+            Err(e) => return Box::pin(stream::iter(Some(Err(RequestError::Other(e))))),
+        };
+        Box::pin(results.filter_map(move |next| {
+            let res = match next {
+                Ok(it) => decoding.decode(&it).map_err(RequestError::Other),
+                Err(e) => Err(decoding.error(e)),
+            };
+            future::ready(res.transpose())
+        }))
+    }
+
+    /// Decodes the results lazily as CloudEvents in the structured mode, whatever MIME type they
+    /// declare. Requests without an event should accept `application/cloudevents+json`.
+    pub fn events<T>(self) -> Flux<Result<CloudEvent<T>>>
+    where
+        T: Sized + DeserializeOwned + Send + 'static,
+    {
+        let (decoding, results) = match self.inner {
+            Ok(it) => it,
+            Err(e) => return Box::pin(stream::iter(Some(Err(e)))),
+        };
+        Box::pin(results.filter_map(move |next| {
+            let res = next.and_then(|it| decoding.event(&it));
+            future::ready(res.transpose())
+        }))
+    }
+
+    /// Deserializes the results lazily, along with their metadata.
+    pub fn messages<T>(self) -> Flux<Result<Message<T>>>
+    where
+        T: Sized + DeserializeOwned + Send + 'static,
+    {
+        let (decoding, results) = match self.inner {
+            Ok(it) => it,
+            Err(e) => return Box::pin(stream::iter(Some(Err(e)))),
+        };
+        Box::pin(results.map(move |next| next.and_then(|it| decoding.message(it))))
+    }
 }
 
 impl Unpacker {
+    /// Deserializes the data of the response, if any.
+    pub fn decode<T>(self) -> Result<Option<T>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        match (self.decoding, self.inner?) {
+            (Some(decoding), Some(it)) => decoding.decode(&it),
+            _ => Ok(None),
+        }
+    }
+
+    /// Like `decode`, but decodes the error body sent by the responder into `E`.
+    pub fn decode_or_err<T, E>(self) -> std::result::Result<Option<T>, RequestError<E>>
+    where
+        T: Sized + DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        match (self.decoding, self.inner) {
+            (Some(decoding), Ok(Some(it))) => decoding.decode(&it).map_err(RequestError::Other),
+            (Some(decoding), Err(e)) => Err(decoding.error(e)),
+            (None, Err(e)) => Err(RequestError::Other(e)),
+            (_, Ok(_)) => Ok(None),
+        }
+    }
+
+    /// Decodes the response as a CloudEvent in the structured mode, whatever MIME type it declares.
+    pub fn event<T>(self) -> Result<Option<CloudEvent<T>>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        match (self.decoding, self.inner?) {
+            (Some(decoding), Some(it)) => decoding.event(&it),
+            _ => Ok(None),
+        }
+    }
+
+    #[deprecated(note = "it doesn't block, use `decode` instead")]
     pub fn block<T>(self) -> Result<Option<T>>
     where
         T: Sized + DeserializeOwned,
     {
-        let (mime_type, inner) = self.inner?;
-        match inner {
-            Some(it) => match it.data() {
-                Some(raw) => do_unmarshal(&mime_type, raw),
-                None => Ok(None),
-            },
-            None => Ok(None),
+        self.decode()
+    }
+
+    /// Deserializes the response along with its metadata.
+    pub fn message<T>(self) -> Result<Option<Message<T>>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        match (self.decoding, self.inner?) {
+            (Some(decoding), Some(it)) => decoding.message(it).map(Some),
+            _ => Ok(None),
         }
     }
 }
 
-fn do_unmarshal<T>(mime_type: &MimeType, raw: &Bytes) -> Result<Option<T>>
-where
-    T: Sized + DeserializeOwned,
-{
-    match mime_type.as_u8() {
-        Some(code) => {
-            if code == MimeType::APPLICATION_JSON.as_u8().unwrap() {
-                Ok(Some(unmarshal(misc::json(), raw.as_ref())?))
-            } else if code == MimeType::APPLICATION_CBOR.as_u8().unwrap() {
-                Ok(Some(unmarshal(misc::cbor(), raw.as_ref())?))
-            } else {
-                Err(RSocketError::WithDescription("unsupported mime type!".into()).into())
-            }
-        }
-        _ => Err(RSocketError::WithDescription("unsupported mime type!".into()).into()),
+impl<T> Message<T> {
+    /// Returns the deserialized data, which is absent if the response has no data.
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_ref()
+    }
+
+    pub fn into_data(self) -> Option<T> {
+        self.data
+    }
+
+    pub fn metadata(&self) -> Option<&Bytes> {
+        self.payload.metadata()
+    }
+
+    /// Finds and decodes the extension in the composite metadata of the response.
+    pub fn metadata_extension<E>(&self) -> Result<Option<E>>
+    where
+        E: MetadataExtension,
+    {
+        self.payload.metadata_extension()
+    }
+
+    /// Returns the raw response.
+    pub fn payload(&self) -> &Payload {
+        &self.payload
     }
 }
 
-fn do_marshal<T>(mime_type: &MimeType, data: &T) -> Result<Vec<u8>>
-where
-    T: Sized + Serialize,
-{
-    match mime_type.as_u8() {
-        Some(code) => {
-            if code == MimeType::APPLICATION_JSON.as_u8().unwrap() {
-                marshal(misc::json(), data)
-            } else if code == MimeType::APPLICATION_CBOR.as_u8().unwrap() {
-                marshal(misc::cbor(), data)
-            } else {
-                Err(RSocketError::WithDescription("unsupported mime type!".into()).into())
-            }
+impl Decoding {
+    fn decode<T>(&self, res: &Payload) -> Result<Option<T>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        match res.data() {
+            Some(raw) => self.codecs.unmarshal(&self.mime_type(res), raw).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn message<T>(&self, res: Payload) -> Result<Message<T>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        Ok(Message {
+            data: self.decode(&res)?,
+            payload: res,
+        })
+    }
+
+    fn event<T>(&self, res: &Payload) -> Result<Option<CloudEvent<T>>>
+    where
+        T: Sized + DeserializeOwned,
+    {
+        match res.data() {
+            Some(raw) => self
+                .codecs
+                .unmarshal(&MimeType::APPLICATION_CLOUDEVENTS_JSON, raw)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Error frames carry no metadata, so their body is always of the response MIME type.
+    fn error<E>(&self, e: anyhow::Error) -> RequestError<E>
+    where
+        E: DeserializeOwned,
+    {
+        decode_error(e, |raw| {
+            self.codecs.unmarshal(&self.response_mime_type, raw)
+        })
+    }
+
+    /// Prefers the MIME type declared by the response.
+    fn mime_type(&self, res: &Payload) -> MimeType {
+        match res.metadata_extension::<MimeTypeMetadata>() {
+            Ok(Some(it)) => it.get_mime_type().clone(),
+            _ => self.response_mime_type.clone(),
         }
-        _ => Err(RSocketError::WithDescription("unsupported mime type!".into()).into()),
     }
 }
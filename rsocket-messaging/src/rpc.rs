@@ -0,0 +1,126 @@
+//! Runtime support of servers and clients generated by `rsocket_rust_rpc_build`.
+//!
+//! Methods are routed by `RoutingMetadata` with the route `{package}.{Service}.{Method}`, named
+//! as in the `.proto` file like rsocket-rpc-java does, and messages are encoded with protobuf.
+
+use std::future::Future;
+
+use futures::{stream, Stream, StreamExt};
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::{error::RSocketError, Result};
+
+use super::codec::Protobuf;
+use super::requester::{RequestSpec, Requester};
+
+/// Decodes the message of a request, failing with INVALID.
+pub fn decode<M>(req: &Payload) -> Result<M>
+where
+    M: prost::Message + Default,
+{
+    let raw = req.data().map(|it| &it[..]).unwrap_or_default();
+    M::decode(raw).map_err(|e| RSocketError::RequestInvalid(e.to_string()).into())
+}
+
+/// Decodes the messages of a channel, which start with the payload of its request.
+pub fn decode_stream<M>(reqs: Flux<Result<Payload>>) -> Flux<Result<M>>
+where
+    M: prost::Message + Default + 'static,
+{
+    Box::pin(reqs.map(|it| it.and_then(|req| decode(&req))))
+}
+
+pub fn encode<M>(message: &M) -> Result<Payload>
+where
+    M: prost::Message,
+{
+    Ok(Payload::builder().set_data(message.encode_to_vec()).build())
+}
+
+pub fn encode_stream<M>(results: Flux<Result<M>>) -> Flux<Result<Payload>>
+where
+    M: prost::Message + 'static,
+{
+    Box::pin(results.map(|it| it.and_then(|message| encode(&message))))
+}
+
+/// Encodes the single response of a client streaming method.
+pub fn encode_once<F, M>(result: F) -> Flux<Result<Payload>>
+where
+    F: Send + 'static + Future<Output = Result<M>>,
+    M: prost::Message,
+{
+    Box::pin(stream::once(async move { encode(&result.await?) }))
+}
+
+/// Calls a unary method, which fails if the server responds nothing.
+pub async fn unary<Req, Res>(requester: &Requester, route: &str, req: Req) -> Result<Res>
+where
+    Req: prost::Message + 'static,
+    Res: prost::Message + Default,
+{
+    let res: Option<Protobuf<Res>> = spec(requester, route)
+        .data(Protobuf(req))
+        .retrieve_mono()
+        .await
+        .decode()?;
+    match res {
+        Some(it) => Ok(it.0),
+        None => Err(RSocketError::WithDescription(format!("empty response: {}", route)).into()),
+    }
+}
+
+pub fn server_streaming<Req, Res>(requester: &Requester, route: &str, req: Req) -> Flux<Result<Res>>
+where
+    Req: prost::Message + 'static,
+    Res: prost::Message + Default + 'static,
+{
+    let results = spec(requester, route)
+        .data(Protobuf(req))
+        .retrieve_flux()
+        .flux::<Protobuf<Res>>();
+    Box::pin(results.map(|it| it.map(|res| res.0)))
+}
+
+/// Calls a client streaming method, taking the first response.
+pub async fn client_streaming<S, Req, Res>(
+    requester: &Requester,
+    route: &str,
+    reqs: S,
+) -> Result<Res>
+where
+    S: Stream<Item = Req> + Send + 'static,
+    Req: prost::Message + 'static,
+    Res: prost::Message + Default + 'static,
+{
+    match streaming(requester, route, reqs).next().await {
+        Some(res) => res,
+        None => Err(RSocketError::WithDescription(format!("empty response: {}", route)).into()),
+    }
+}
+
+/// Calls a bidirectional streaming method.
+pub fn streaming<S, Req, Res>(requester: &Requester, route: &str, reqs: S) -> Flux<Result<Res>>
+where
+    S: Stream<Item = Req> + Send + 'static,
+    Req: prost::Message + 'static,
+    Res: prost::Message + Default + 'static,
+{
+    let results = spec(requester, route)
+        .retrieve_channel(reqs.map(Protobuf))
+        .flux::<Protobuf<Res>>();
+    Box::pin(results.map(|it| it.map(|res| res.0)))
+}
+
+pub async fn fire_and_forget<Req>(requester: &Requester, route: &str, req: Req) -> Result<()>
+where
+    Req: prost::Message + 'static,
+{
+    spec(requester, route).data(Protobuf(req)).retrieve().await
+}
+
+fn spec(requester: &Requester, route: &str) -> RequestSpec {
+    requester
+        .route(route)
+        .data_mime_type(MimeType::APPLICATION_VND_GOOGLE_PROTOBUF)
+}
@@ -0,0 +1,518 @@
+//! Chunked transfer of files and blobs, which never holds a whole blob in memory.
+//!
+//! A transfer is a stream of payloads: a header whose composite metadata carries the `Manifest`,
+//! the data of the blob in chunks, then a trailer whose composite metadata carries the length and
+//! the SHA-256 checksum of the chunks. The receiver writes each chunk before it takes the next
+//! one, and verifies the trailer once all chunks are written.
+//!
+//! Uploads are request-channels. The receiver acknowledges every chunk once it is written, and
+//! the sender doesn't read further than a window of unacknowledged chunks. The receiver responds
+//! a `Receipt` at last. Downloads are request-streams, whose request carries the manifest of the
+//! wanted blob.
+//!
+//! Interrupted transfers are resumed by sending a manifest with the offset which has been
+//! received already, the checksum only covers the chunks after that offset.
+//!
+//! # Example
+//! ```no_run,ignore
+//! let router = Router::builder()
+//!     .request_channel(
+//!         "files.upload",
+//!         Transfer::default().accept(|manifest: Manifest, _| async move {
+//!             let mut file = OpenOptions::new().create(true).write(true).open(manifest.name()).await?;
+//!             file.seek(SeekFrom::Start(manifest.offset())).await?;
+//!             Ok(file)
+//!         }),
+//!     )
+//!     .request_stream(
+//!         "files.download",
+//!         Transfer::default().serve(|manifest: Manifest, _| async move {
+//!             let mut file = File::open(manifest.name()).await?;
+//!             let size = file.metadata().await?.len();
+//!             file.seek(SeekFrom::Start(manifest.offset())).await?;
+//!             Ok((manifest.set_size(size), file))
+//!         }),
+//!     )
+//!     .build();
+//!
+//! let receipt = Transfer::default()
+//!     .upload(requester.route("files.upload"), Manifest::new("a.bin").set_size(size), file)
+//!     .await?;
+//! ```
+
+use std::future::Future;
+use std::sync::Arc;
+
+use bytes::{Bytes, BytesMut};
+use futures::stream;
+use rsocket_rust::extension::{CompositeMetadata, MimeType};
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::Variables;
+use rsocket_rust::utils::Writeable;
+use rsocket_rust::{error::RSocketError, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Semaphore;
+
+use super::requester::RequestSpec;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+pub const DEFAULT_WINDOW: usize = 16;
+
+/// MIME type of the manifest in the composite metadata of the header.
+pub const MANIFEST_MIME_TYPE: &str = "application/x.rsocket.transfer.manifest+json";
+/// MIME type of the length and checksum in the composite metadata of the trailer.
+pub const TRAILER_MIME_TYPE: &str = "application/x.rsocket.transfer.trailer+json";
+
+/// Options of the senders and receivers of transfers.
+#[derive(Debug, Clone, Copy)]
+pub struct Transfer {
+    chunk_size: usize,
+    window: usize,
+}
+
+/// Describes a blob: its name, its size if it is known, and the offset a transfer starts from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(default)]
+    offset: u64,
+}
+
+/// What a receiver has written and verified.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    name: String,
+    offset: u64,
+    length: u64,
+    checksum: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Trailer {
+    length: u64,
+    checksum: String,
+}
+
+enum Part {
+    Chunk(Bytes),
+    Trailer(Trailer),
+}
+
+/// A transfer being received.
+struct Receiving<W> {
+    manifest: Manifest,
+    writer: W,
+    digest: Sha256,
+    length: u64,
+}
+
+impl Default for Transfer {
+    fn default() -> Transfer {
+        Transfer {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+impl Transfer {
+    /// Sets the size of the chunks read by senders, 64 KiB by default.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets how many chunks an upload sends ahead of the acknowledgements, 16 by default.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// Streams the reader as the payloads of a transfer. The reader must be positioned at the
+    /// offset of the manifest.
+    pub fn payloads<R>(&self, manifest: &Manifest, reader: R) -> Flux<Result<Payload>>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let header = header(manifest).map(|it| Payload::new(None, Some(it)));
+        Box::pin(stream::iter(Some(header)).chain(chunks(reader, self.chunk_size, None)))
+    }
+
+    /// Uploads the reader over a request-channel, returning the receipt of the receiver.
+    pub async fn upload<R>(
+        &self,
+        spec: RequestSpec,
+        manifest: Manifest,
+        reader: R,
+    ) -> Result<Receipt>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let credits = Arc::new(Semaphore::new(self.window));
+        let reqs = chunks(reader, self.chunk_size, Some(credits.clone()));
+        let mut results = spec
+            .metadata_raw(serde_json::to_vec(&manifest)?, MANIFEST_MIME_TYPE)
+            .data_raw(vec![])
+            .retrieve_channel_raw(reqs)
+            .into_payloads();
+        let res = async {
+            while let Some(next) = results.next().await {
+                match next?.data() {
+                    Some(data) if !data.is_empty() => return Ok(serde_json::from_slice(data)?),
+                    _ => credits.add_permits(1),
+                }
+            }
+            Err(RSocketError::WithDescription("upload completed without receipt".into()).into())
+        }
+        .await;
+        // stops the sender if the upload failed
+        credits.close();
+        res
+    }
+
+    /// Downloads a blob over a request-stream, writing it to the writer which must be positioned
+    /// at the offset of the manifest.
+    pub async fn download<W>(
+        &self,
+        spec: RequestSpec,
+        manifest: Manifest,
+        writer: W,
+    ) -> Result<Receipt>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let offset = manifest.offset;
+        let payloads = spec
+            .metadata_raw(serde_json::to_vec(&manifest)?, MANIFEST_MIME_TYPE)
+            .retrieve_flux()
+            .into_payloads();
+        receive(payloads, |served| async move {
+            if served.offset != offset {
+                let desc = format!("expect offset {}, served {}", offset, served.offset);
+                return Err(RSocketError::WithDescription(desc).into());
+            }
+            Ok(writer)
+        })
+        .await
+    }
+
+    /// Adapts the receiver of uploads to a channel handler of `Router`. The writer opened for a
+    /// manifest must be positioned at its offset.
+    pub fn accept<F, Fut, W>(
+        &self,
+        open: F,
+    ) -> impl Send + Sync + Fn(Flux<Result<Payload>>, Variables) -> Flux<Result<Payload>>
+    where
+        F: Send + Sync + 'static + Fn(Manifest, Variables) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<W>>,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let open = Arc::new(open);
+        move |mut reqs, vars| {
+            let open = open.clone();
+            Box::pin(rsocket_rust::stream! {
+                let mut receiving = match Receiving::start(&mut reqs, |it| open(it, vars)).await {
+                    Ok(it) => it,
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                };
+                loop {
+                    let res = match receiving.next(&mut reqs).await {
+                        Ok(Part::Chunk(chunk)) => match receiving.write(&chunk).await {
+                            Ok(()) => {
+                                yield Ok(Payload::new(None, None));
+                                continue;
+                            }
+                            Err(e) => Err(e),
+                        },
+                        Ok(Part::Trailer(trailer)) => receiving.finish(trailer).await.and_then(|it| {
+                            Ok(Payload::builder().set_data(serde_json::to_vec(&it)?).build())
+                        }),
+                        Err(e) => Err(e),
+                    };
+                    yield res;
+                    break;
+                }
+            })
+        }
+    }
+
+    /// Adapts the sender of downloads to a stream handler of `Router`. The reader opened for a
+    /// requested manifest must be positioned at its offset, along with the manifest to serve.
+    pub fn serve<F, Fut, R>(
+        &self,
+        open: F,
+    ) -> impl Send + Sync + Fn(Payload, Variables) -> Flux<Result<Payload>>
+    where
+        F: Send + Sync + 'static + Fn(Manifest, Variables) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<(Manifest, R)>>,
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let transfer = *self;
+        move |req, vars| {
+            let opening = manifest(&req).map(|it| open(it, vars));
+            let payloads = stream::once(async move {
+                match opening {
+                    Ok(opening) => match opening.await {
+                        Ok((manifest, reader)) => transfer.payloads(&manifest, reader),
+                        Err(e) => Box::pin(stream::iter(Some(Err(e)))),
+                    },
+                    Err(e) => Box::pin(stream::iter(Some(Err(e)))) as Flux<_>,
+                }
+            });
+            Box::pin(payloads.flatten())
+        }
+    }
+}
+
+/// Receives the payloads of a transfer, opening the writer once the manifest is received.
+///
+/// Chunks are written one by one, the next payload is only taken once the previous chunk is
+/// written. It fails if the transfer is interrupted, or if the trailer doesn't match the chunks.
+pub async fn receive<F, Fut, W>(mut payloads: Flux<Result<Payload>>, open: F) -> Result<Receipt>
+where
+    F: FnOnce(Manifest) -> Fut,
+    Fut: Future<Output = Result<W>>,
+    W: AsyncWrite + Unpin,
+{
+    let mut receiving = Receiving::start(&mut payloads, open).await?;
+    loop {
+        match receiving.next(&mut payloads).await? {
+            Part::Chunk(chunk) => receiving.write(&chunk).await?,
+            Part::Trailer(trailer) => return receiving.finish(trailer).await,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn new<I>(name: I) -> Manifest
+    where
+        I: Into<String>,
+    {
+        Manifest {
+            name: name.into(),
+            size: None,
+            offset: 0,
+        }
+    }
+
+    pub fn set_size(mut self, size: u64) -> Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets the offset to resume from.
+    pub fn set_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl Receipt {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the length written after the offset.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the SHA-256 checksum of the bytes written after the offset, like `sha256:<hex>`.
+    pub fn checksum(&self) -> &str {
+        &self.checksum
+    }
+}
+
+impl<W> Receiving<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    async fn start<F, Fut>(payloads: &mut Flux<Result<Payload>>, open: F) -> Result<Receiving<W>>
+    where
+        F: FnOnce(Manifest) -> Fut,
+        Fut: Future<Output = Result<W>>,
+    {
+        let first = match payloads.next().await {
+            Some(first) => first?,
+            None => return Err(interrupted(0)),
+        };
+        let manifest = manifest(&first)?;
+        let writer = open(manifest.clone()).await?;
+        Ok(Receiving {
+            manifest,
+            writer,
+            digest: Sha256::new(),
+            length: 0,
+        })
+    }
+
+    async fn next(&mut self, payloads: &mut Flux<Result<Payload>>) -> Result<Part> {
+        let next = match payloads.next().await {
+            Some(next) => next?,
+            None => return Err(interrupted(self.manifest.offset + self.length)),
+        };
+        match find::<Trailer>(&next, TRAILER_MIME_TYPE)? {
+            Some(trailer) => Ok(Part::Trailer(trailer)),
+            None => Ok(Part::Chunk(next.split().0.unwrap_or_default())),
+        }
+    }
+
+    async fn write(&mut self, chunk: &[u8]) -> Result<()> {
+        self.length += chunk.len() as u64;
+        if let Some(size) = self.manifest.size {
+            if self.manifest.offset + self.length > size {
+                let desc = format!("transfer exceeds the size of {} bytes", size);
+                return Err(RSocketError::RequestInvalid(desc).into());
+            }
+        }
+        self.digest.update(chunk);
+        self.writer.write_all(chunk).await?;
+        Ok(())
+    }
+
+    async fn finish(mut self, trailer: Trailer) -> Result<Receipt> {
+        let checksum = checksum(self.digest);
+        let end = self.manifest.offset + self.length;
+        if trailer.length != self.length || self.manifest.size.is_some_and(|it| it != end) {
+            let desc = format!("received {} bytes, sent {}", self.length, trailer.length);
+            return Err(RSocketError::RequestInvalid(desc).into());
+        }
+        if trailer.checksum != checksum {
+            let desc = format!("checksum mismatch: {}, sent {}", checksum, trailer.checksum);
+            return Err(RSocketError::RequestInvalid(desc).into());
+        }
+        self.writer.flush().await?;
+        Ok(Receipt {
+            name: self.manifest.name,
+            offset: self.manifest.offset,
+            length: self.length,
+            checksum,
+        })
+    }
+}
+
+/// Reads chunks of the reader, followed by the trailer. If credits are given, each chunk takes
+/// one, and it stops once they are closed.
+fn chunks<R>(
+    mut reader: R,
+    chunk_size: usize,
+    credits: Option<Arc<Semaphore>>,
+) -> Flux<Result<Payload>>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    Box::pin(rsocket_rust::stream! {
+        let mut digest = Sha256::new();
+        let mut length = 0;
+        loop {
+            if let Some(credits) = &credits {
+                match credits.acquire().await {
+                    Ok(permit) => permit.forget(),
+                    Err(_) => return,
+                }
+            }
+            let chunk = match read_chunk(&mut reader, chunk_size).await {
+                Ok(chunk) if chunk.is_empty() => break,
+                Ok(chunk) => chunk.freeze(),
+                Err(e) => {
+                    yield Err(e.into());
+                    return;
+                }
+            };
+            digest.update(&chunk);
+            length += chunk.len() as u64;
+            yield Ok(Payload::new(Some(chunk), None));
+        }
+        let trailer = Trailer {
+            length,
+            checksum: checksum(digest),
+        };
+        yield metadata(&trailer, TRAILER_MIME_TYPE).map(|it| Payload::new(None, Some(it)));
+    })
+}
+
+async fn read_chunk<R>(reader: &mut R, chunk_size: usize) -> std::io::Result<BytesMut>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut chunk = BytesMut::with_capacity(chunk_size);
+    while chunk.len() < chunk_size {
+        if reader.read_buf(&mut chunk).await? == 0 {
+            break;
+        }
+    }
+    Ok(chunk)
+}
+
+fn header(manifest: &Manifest) -> Result<Bytes> {
+    metadata(manifest, MANIFEST_MIME_TYPE)
+}
+
+fn manifest(payload: &Payload) -> Result<Manifest> {
+    match find(payload, MANIFEST_MIME_TYPE)? {
+        Some(it) => Ok(it),
+        None => Err(RSocketError::RequestInvalid("missing transfer manifest".into()).into()),
+    }
+}
+
+fn metadata<T>(value: &T, mime_type: &str) -> Result<Bytes>
+where
+    T: Serialize,
+{
+    let raw = serde_json::to_vec(value)?;
+    let composite = CompositeMetadata::builder()
+        .push(MimeType::from(mime_type), raw)
+        .build();
+    Ok(Bytes::from(composite.bytes()))
+}
+
+fn find<T>(payload: &Payload, mime_type: &str) -> Result<Option<T>>
+where
+    T: DeserializeOwned,
+{
+    if payload.metadata().is_none() {
+        return Ok(None);
+    }
+    let mime_type = MimeType::from(mime_type);
+    for entry in payload.composite_metadata() {
+        let entry = entry?;
+        if entry.get_mime_type() == &mime_type {
+            let value = serde_json::from_slice(entry.get_metadata())
+                .map_err(|e| RSocketError::RequestInvalid(e.to_string()))?;
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+fn checksum(digest: Sha256) -> String {
+    format!("sha256:{}", hex::encode(digest.finalize()))
+}
+
+fn interrupted(received: u64) -> anyhow::Error {
+    let desc = format!("transfer interrupted after {} bytes", received);
+    RSocketError::WithDescription(desc).into()
+}
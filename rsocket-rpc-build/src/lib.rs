@@ -0,0 +1,476 @@
+//! Generates RSocket servers and clients from the services of `.proto` files, as a
+//! `ServiceGenerator` of `prost-build`.
+//!
+//! Every service `Foo` is generated into a trait `Foo`, which is served by `FooServer`, and a
+//! `FooClient` wrapping a `Requester`. Methods are mapped to interaction models by their
+//! streaming:
+//!
+//! - unary: request-response, `async fn(&self, Req) -> Result<Res>`
+//! - server streaming: request-stream, `fn(&self, Req) -> Flux<Result<Res>>`
+//! - client streaming: request-channel, `async fn(&self, Flux<Result<Req>>) -> Result<Res>`
+//! - bidirectional streaming: request-channel, `fn(&self, Flux<Result<Req>>) -> Flux<Result<Res>>`
+//! - fire-and-forget: `async fn(&self, Req) -> Result<()>`, for unary methods returning
+//!   `google.protobuf.Empty` which are marked by `Builder::fire_and_forget`
+//!
+//! Requests are routed by `RoutingMetadata` with the route `{package}.{Service}.{Method}`, named
+//! as in the `.proto` file, which is the naming of rsocket-rpc-java. The generated code depends on
+//! `rsocket_rust` and `rsocket_rust_messaging` with the feature `prost`.
+//!
+//! # Example `build.rs`
+//! ```no_run
+//! fn main() -> std::io::Result<()> {
+//!     rsocket_rust_rpc_build::configure()
+//!         .fire_and_forget("helloworld.Greeter.Notify")
+//!         .compile(&["proto/helloworld.proto"], &["proto"])
+//! }
+//! ```
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use proc_macro2::TokenStream;
+use prost_build::{Comments, Config, Method, Service};
+use quote::{format_ident, quote};
+use syn::{Ident, Type};
+
+/// Configures the generation of services.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    server: bool,
+    client: bool,
+    fire_and_forget: HashSet<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Interaction {
+    FireAndForget,
+    RequestResponse,
+    RequestStream,
+    ClientStreaming,
+    RequestChannel,
+}
+
+struct Generator {
+    builder: Builder,
+}
+
+pub fn configure() -> Builder {
+    Builder {
+        server: true,
+        client: true,
+        fire_and_forget: HashSet::new(),
+    }
+}
+
+/// Compiles a `.proto` file with the default configuration, whose directory is the include path.
+pub fn compile_protos<P>(proto: P) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let proto = proto.as_ref();
+    let include = proto.parent().unwrap_or_else(|| Path::new("."));
+    configure().compile(&[proto], &[include])
+}
+
+impl Builder {
+    pub fn build_server(mut self, enable: bool) -> Self {
+        self.server = enable;
+        self
+    }
+
+    pub fn build_client(mut self, enable: bool) -> Self {
+        self.client = enable;
+        self
+    }
+
+    /// Marks a method as fire-and-forget by its route, e.g. `helloworld.Greeter.Notify`.
+    ///
+    /// It must be a unary method returning `google.protobuf.Empty`. Custom method options, like
+    /// the one of rsocket-rpc-java, are dropped by `prost-build`, so they are marked here.
+    pub fn fire_and_forget<I>(mut self, route: I) -> Self
+    where
+        I: Into<String>,
+    {
+        self.fire_and_forget.insert(route.into());
+        self
+    }
+
+    /// Returns the `ServiceGenerator`, for a `Config` which is compiled by the caller.
+    pub fn service_generator(self) -> Box<dyn prost_build::ServiceGenerator> {
+        Box::new(Generator { builder: self })
+    }
+
+    pub fn compile<P>(self, protos: &[P], includes: &[P]) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        self.compile_with_config(Config::new(), protos, includes)
+    }
+
+    pub fn compile_with_config<P>(
+        self,
+        mut config: Config,
+        protos: &[P],
+        includes: &[P],
+    ) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        config
+            .service_generator(self.service_generator())
+            .compile_protos(protos, includes)
+    }
+
+    /// Compiles the descriptors of `.proto` files, which doesn't need `protoc`.
+    pub fn compile_fds(
+        self,
+        mut config: Config,
+        fds: prost_types::FileDescriptorSet,
+    ) -> io::Result<()> {
+        config
+            .service_generator(self.service_generator())
+            .compile_fds(fds)
+    }
+}
+
+impl prost_build::ServiceGenerator for Generator {
+    fn generate(&mut self, service: Service, buf: &mut String) {
+        let methods: Vec<(String, Interaction, &Method)> = service
+            .methods
+            .iter()
+            .map(|method| {
+                let route = route(&service, method);
+                let interaction = self.interaction(&route, method);
+                (route, interaction, method)
+            })
+            .collect();
+
+        let mut tokens = trait_of(&service, &methods);
+        if self.builder.server {
+            tokens.extend(server(&service, &methods));
+        }
+        if self.builder.client {
+            tokens.extend(client(&service, &methods));
+        }
+        buf.push_str(&tokens.to_string());
+    }
+}
+
+impl Generator {
+    /// Panics if a fire-and-forget method isn't unary or doesn't return `google.protobuf.Empty`,
+    /// which fails the build script.
+    fn interaction(&self, route: &str, method: &Method) -> Interaction {
+        if self.builder.fire_and_forget.contains(route) {
+            if method.client_streaming
+                || method.server_streaming
+                || method.output_proto_type != ".google.protobuf.Empty"
+            {
+                panic!(
+                    "fire-and-forget method {} must be unary and return google.protobuf.Empty",
+                    route
+                );
+            }
+            return Interaction::FireAndForget;
+        }
+        match (method.client_streaming, method.server_streaming) {
+            (false, false) => Interaction::RequestResponse,
+            (false, true) => Interaction::RequestStream,
+            (true, false) => Interaction::ClientStreaming,
+            (true, true) => Interaction::RequestChannel,
+        }
+    }
+}
+
+fn route(service: &Service, method: &Method) -> String {
+    if service.package.is_empty() {
+        format!("{}.{}", service.proto_name, method.proto_name)
+    } else {
+        format!(
+            "{}.{}.{}",
+            service.package, service.proto_name, method.proto_name
+        )
+    }
+}
+
+fn docs_of(comments: &Comments) -> TokenStream {
+    let lines = comments.leading.iter().map(|it| it.trim_end());
+    quote! { #(#[doc = #lines])* }
+}
+
+/// Panics if prost-build gives a type which isn't a Rust type.
+fn type_of(path: &str) -> Type {
+    syn::parse_str(path).unwrap_or_else(|e| panic!("invalid type {}: {}", path, e))
+}
+
+fn trait_of(service: &Service, methods: &[(String, Interaction, &Method)]) -> TokenStream {
+    let ident = format_ident!("{}", service.name);
+    let docs = docs_of(&service.comments);
+    let methods = methods.iter().map(|(_, interaction, method)| {
+        let docs = docs_of(&method.comments);
+        let name = format_ident!("{}", method.name);
+        let req = type_of(&method.input_type);
+        let res = type_of(&method.output_type);
+        let signature = match interaction {
+            Interaction::FireAndForget => quote! {
+                async fn #name(&self, req: #req) -> ::rsocket_rust::Result<()>
+            },
+            Interaction::RequestResponse => quote! {
+                async fn #name(&self, req: #req) -> ::rsocket_rust::Result<#res>
+            },
+            Interaction::RequestStream => quote! {
+                fn #name(&self, req: #req)
+                    -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#res>>
+            },
+            Interaction::ClientStreaming => quote! {
+                async fn #name(
+                    &self,
+                    reqs: ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#req>>,
+                ) -> ::rsocket_rust::Result<#res>
+            },
+            Interaction::RequestChannel => quote! {
+                fn #name(
+                    &self,
+                    reqs: ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#req>>,
+                ) -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#res>>
+            },
+        };
+        quote! {
+            #docs
+            #signature;
+        }
+    });
+    quote! {
+        #docs
+        #[::rsocket_rust::async_trait]
+        pub trait #ident: Send + Sync + 'static {
+            #(#methods)*
+        }
+    }
+}
+
+fn server(service: &Service, methods: &[(String, Interaction, &Method)]) -> TokenStream {
+    let ident = format_ident!("{}", service.name);
+    let name = format_ident!("{}Server", service.name);
+    let doc = format!(" Serves `{}` as an RSocket responder.", service.name);
+    let of = |wanted: &[Interaction]| {
+        methods
+            .iter()
+            .filter(|(_, interaction, _)| wanted.contains(interaction))
+            .collect::<Vec<_>>()
+    };
+
+    let fire_and_forget = dispatch(&of(&[Interaction::FireAndForget]), |method, _| {
+        quote! { self.inner.#method(::rsocket_rust_messaging::rpc::decode(__request.payload())?).await }
+    });
+    let request_response = dispatch(&of(&[Interaction::RequestResponse]), |method, _| {
+        quote! {
+            let __res = self.inner.#method(::rsocket_rust_messaging::rpc::decode(__request.payload())?).await?;
+            ::rsocket_rust_messaging::rpc::encode(&__res).map(Some)
+        }
+    });
+    let request_stream = dispatch(&of(&[Interaction::RequestStream]), |method, _| {
+        quote! {
+            let __req = ::rsocket_rust_messaging::rpc::decode(__request.payload())?;
+            Ok(::rsocket_rust_messaging::rpc::encode_stream(self.inner.#method(__req)))
+        }
+    });
+    let channels = of(&[Interaction::ClientStreaming, Interaction::RequestChannel]);
+    let this = if channels.is_empty() {
+        quote!()
+    } else {
+        quote! { let __inner = ::std::sync::Arc::clone(&self.inner); }
+    };
+    let request_channel = dispatch(&channels, |method, interaction| {
+        if interaction == Interaction::ClientStreaming {
+            quote! {
+                let __reqs = ::rsocket_rust_messaging::rpc::decode_stream(__reqs);
+                Ok(::rsocket_rust_messaging::rpc::encode_once(async move {
+                    __inner.#method(__reqs).await
+                }))
+            }
+        } else {
+            quote! {
+                let __reqs = ::rsocket_rust_messaging::rpc::decode_stream(__reqs);
+                Ok(::rsocket_rust_messaging::rpc::encode_stream(__inner.#method(__reqs)))
+            }
+        }
+    });
+
+    quote! {
+        #[doc = #doc]
+        pub struct #name<T> {
+            inner: ::std::sync::Arc<T>,
+            context: ::rsocket_rust_messaging::controller::Context,
+        }
+
+        impl<T> #name<T>
+        where
+            T: #ident,
+        {
+            pub fn new(inner: T) -> Self {
+                Self::from_arc(::std::sync::Arc::new(inner))
+            }
+
+            pub fn from_arc(inner: ::std::sync::Arc<T>) -> Self {
+                #name {
+                    inner,
+                    context: ::std::default::Default::default(),
+                }
+            }
+
+            /// Replaces the registry of codecs, which encode the `ErrorBody` of failures.
+            pub fn codecs(mut self, codecs: ::rsocket_rust_messaging::codec::Codecs) -> Self {
+                self.context = self.context.codecs(codecs);
+                self
+            }
+        }
+
+        impl<T> ::std::clone::Clone for #name<T> {
+            fn clone(&self) -> Self {
+                #name {
+                    inner: ::std::sync::Arc::clone(&self.inner),
+                    context: ::std::clone::Clone::clone(&self.context),
+                }
+            }
+        }
+
+        #[::rsocket_rust::async_trait]
+        impl<T> ::rsocket_rust::prelude::RSocket for #name<T>
+        where
+            T: #ident,
+        {
+            async fn metadata_push(
+                &self,
+                _req: ::rsocket_rust::prelude::Payload,
+            ) -> ::rsocket_rust::Result<()> {
+                Ok(())
+            }
+
+            async fn fire_and_forget(
+                &self,
+                req: ::rsocket_rust::prelude::Payload,
+            ) -> ::rsocket_rust::Result<()> {
+                let __request =
+                    ::rsocket_rust_messaging::controller::Request::with_context(req, &self.context)?;
+                #fire_and_forget
+            }
+
+            async fn request_response(
+                &self,
+                req: ::rsocket_rust::prelude::Payload,
+            ) -> ::rsocket_rust::Result<Option<::rsocket_rust::prelude::Payload>> {
+                let __request =
+                    ::rsocket_rust_messaging::controller::Request::with_context(req, &self.context)?;
+                #request_response
+            }
+
+            fn request_stream(
+                &self,
+                req: ::rsocket_rust::prelude::Payload,
+            ) -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>> {
+                ::rsocket_rust_messaging::controller::stream(req, &self.context, |__request| {
+                    #request_stream
+                })
+            }
+
+            fn request_channel(
+                &self,
+                reqs: ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>>,
+            ) -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<::rsocket_rust::prelude::Payload>> {
+                #this
+                ::rsocket_rust_messaging::controller::channel(reqs, &self.context, move |__request, __reqs| {
+                    #request_channel
+                })
+            }
+        }
+    }
+}
+
+/// Matches the route of `__request` against the routes of the methods.
+fn dispatch<F>(methods: &[&(String, Interaction, &Method)], call: F) -> TokenStream
+where
+    F: Fn(&Ident, Interaction) -> TokenStream,
+{
+    if methods.is_empty() {
+        return quote! { __request.reject() };
+    }
+    let arms = methods.iter().map(|(route, interaction, method)| {
+        let body = call(&format_ident!("{}", method.name), *interaction);
+        quote! { Some(#route) => { #body } }
+    });
+    quote! {
+        match __request.route() {
+            #(#arms)*
+            _ => __request.reject(),
+        }
+    }
+}
+
+fn client(service: &Service, methods: &[(String, Interaction, &Method)]) -> TokenStream {
+    let name = format_ident!("{}Client", service.name);
+    let doc = format!(
+        " A client of `{}`, sending requests with a `Requester`.",
+        service.name
+    );
+    let methods = methods.iter().map(|(route, interaction, method)| {
+        let docs = docs_of(&method.comments);
+        let name = format_ident!("{}", method.name);
+        let req = type_of(&method.input_type);
+        let res = type_of(&method.output_type);
+        let body = match interaction {
+            Interaction::FireAndForget => quote! {
+                pub async fn #name(&self, req: #req) -> ::rsocket_rust::Result<()> {
+                    ::rsocket_rust_messaging::rpc::fire_and_forget(&self.requester, #route, req).await
+                }
+            },
+            Interaction::RequestResponse => quote! {
+                pub async fn #name(&self, req: #req) -> ::rsocket_rust::Result<#res> {
+                    ::rsocket_rust_messaging::rpc::unary(&self.requester, #route, req).await
+                }
+            },
+            Interaction::RequestStream => quote! {
+                pub fn #name(&self, req: #req)
+                    -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#res>>
+                {
+                    ::rsocket_rust_messaging::rpc::server_streaming(&self.requester, #route, req)
+                }
+            },
+            Interaction::ClientStreaming => quote! {
+                pub async fn #name<S>(&self, reqs: S) -> ::rsocket_rust::Result<#res>
+                where
+                    S: ::rsocket_rust::prelude::Stream<Item = #req> + Send + 'static,
+                {
+                    ::rsocket_rust_messaging::rpc::client_streaming(&self.requester, #route, reqs).await
+                }
+            },
+            Interaction::RequestChannel => quote! {
+                pub fn #name<S>(&self, reqs: S)
+                    -> ::rsocket_rust::prelude::Flux<::rsocket_rust::Result<#res>>
+                where
+                    S: ::rsocket_rust::prelude::Stream<Item = #req> + Send + 'static,
+                {
+                    ::rsocket_rust_messaging::rpc::streaming(&self.requester, #route, reqs)
+                }
+            },
+        };
+        quote! {
+            #docs
+            #body
+        }
+    });
+    quote! {
+        #[doc = #doc]
+        pub struct #name {
+            requester: ::rsocket_rust_messaging::Requester,
+        }
+
+        impl #name {
+            pub fn new(requester: ::rsocket_rust_messaging::Requester) -> Self {
+                #name { requester }
+            }
+
+            #(#methods)*
+        }
+    }
+}
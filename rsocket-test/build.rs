@@ -0,0 +1,62 @@
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    MethodDescriptorProto, ServiceDescriptorProto,
+};
+
+/// Generates the services of `proto/helloworld.proto`, whose descriptors are built here so that
+/// the tests don't need `protoc`.
+fn main() -> std::io::Result<()> {
+    println!("cargo:rerun-if-changed=build.rs");
+    rsocket_rust_rpc_build::configure()
+        .fire_and_forget("helloworld.Greeter.Notify")
+        .compile_fds(prost_build::Config::new(), helloworld())
+}
+
+fn helloworld() -> FileDescriptorSet {
+    let method =
+        |name: &str, input: &str, output: &str, client: bool, server: bool| MethodDescriptorProto {
+            name: Some(name.into()),
+            input_type: Some(input.into()),
+            output_type: Some(output.into()),
+            client_streaming: Some(client),
+            server_streaming: Some(server),
+            ..Default::default()
+        };
+    let message = |name: &str, field: &str| DescriptorProto {
+        name: Some(name.into()),
+        field: vec![FieldDescriptorProto {
+            name: Some(field.into()),
+            json_name: Some(field.into()),
+            number: Some(1),
+            label: Some(Label::Optional as i32),
+            r#type: Some(Type::String as i32),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let (req, res) = (".helloworld.HelloRequest", ".helloworld.HelloReply");
+    let file = FileDescriptorProto {
+        name: Some("helloworld.proto".into()),
+        package: Some("helloworld".into()),
+        dependency: vec!["google/protobuf/empty.proto".into()],
+        message_type: vec![
+            message("HelloRequest", "name"),
+            message("HelloReply", "message"),
+        ],
+        service: vec![ServiceDescriptorProto {
+            name: Some("Greeter".into()),
+            method: vec![
+                method("SayHello", req, res, false, false),
+                method("SayHellos", req, res, false, true),
+                method("CountNames", req, res, true, false),
+                method("Chat", req, res, true, true),
+                method("Notify", req, ".google.protobuf.Empty", false, false),
+            ],
+            ..Default::default()
+        }],
+        syntax: Some("proto3".into()),
+        ..Default::default()
+    };
+    FileDescriptorSet { file: vec![file] }
+}
@@ -0,0 +1,13 @@
+use rsocket_rust::extension::{CompositeMetadata, RoutingMetadata};
+use rsocket_rust::prelude::Payload;
+use rsocket_rust::utils::Writeable;
+
+/// Builds a payload routed by composite metadata.
+pub fn routed(route: &str, data: &str) -> Payload {
+    let mut composite = CompositeMetadata::default();
+    composite.insert(&RoutingMetadata::builder().push_str(route).build());
+    Payload::builder()
+        .set_data_utf8(data)
+        .set_metadata(composite.bytes())
+        .build()
+}
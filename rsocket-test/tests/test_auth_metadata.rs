@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use bytes::BytesMut;
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::{AuthMetadata, AuthType, CompositeMetadata, MimeType};
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::{EchoRSocket, Writeable};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+
+fn decode(hex: &str) -> rsocket_rust::Result<AuthMetadata> {
+    AuthMetadata::decode(&mut BytesMut::from(&hex::decode(hex).unwrap()[..]))
+}
+
+#[test]
+fn auth_metadata_codec() {
+    let simple = AuthMetadata::builder().simple("user", "pass").build();
+    assert_eq!(hex::encode(simple.bytes()), "8000047573657270617373");
+    assert_eq!(simple.len(), simple.bytes().len());
+    let decoded = decode("8000047573657270617373").unwrap();
+    assert_eq!(decoded.get_auth_type(), &AuthType::Simple);
+    assert_eq!(decoded.get_username(), Some("user"));
+    assert_eq!(decoded.get_password(), Some("pass"));
+    assert_eq!(decoded.get_token(), None);
+
+    let bearer = AuthMetadata::builder().bearer("token").build();
+    assert_eq!(hex::encode(bearer.bytes()), "81746f6b656e");
+    let decoded = decode("81746f6b656e").unwrap();
+    assert_eq!(decoded.get_token(), Some("token"));
+    assert_eq!(decoded.get_username(), None);
+
+    let custom = AuthMetadata::builder().custom("x", b"abc").build();
+    assert_eq!(hex::encode(custom.bytes()), "0078616263");
+    let decoded = decode("0078616263").unwrap();
+    assert_eq!(decoded.get_auth_type(), &AuthType::Custom("x".into()));
+    assert_eq!(&decoded.get_payload()[..], b"abc");
+    assert_eq!(decoded, custom);
+
+    // username longer than the payload
+    assert!(decode("800004757365").is_err());
+    // empty password
+    let empty = AuthMetadata::builder().simple("user", "").build();
+    assert_eq!(decode(&hex::encode(empty.bytes())).unwrap(), empty);
+    // unknown well-known type
+    assert!(decode("82746f6b656e").is_err());
+    // truncated custom type
+    assert!(decode("0578").is_err());
+    assert!(decode("").is_err());
+}
+
+#[test]
+fn auth_metadata_composite() {
+    let mut composite = CompositeMetadata::builder()
+        .push(MimeType::TEXT_PLAIN, "hello")
+        .build();
+    assert_eq!(AuthMetadata::extract(&composite).unwrap(), None);
+    let auth = AuthMetadata::builder().bearer("token").build();
+    auth.inject(&mut composite);
+    let composite = CompositeMetadata::decode(&mut BytesMut::from(&composite.bytes()[..])).unwrap();
+    assert_eq!(AuthMetadata::extract(&composite).unwrap(), Some(auth));
+}
+
+fn setup(username: &str, password: &str) -> Payload {
+    let mut composite = CompositeMetadata::default();
+    AuthMetadata::builder()
+        .simple(username, password)
+        .build()
+        .inject(&mut composite);
+    Payload::builder().set_metadata(composite.bytes()).build()
+}
+
+#[tokio::test]
+async fn test_setup_auth() {
+    let addr = "127.0.0.1:7676";
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .acceptor(Box::new(|setup, _socket| {
+                let metadata = setup.metadata().cloned().unwrap_or_default();
+                let composite = CompositeMetadata::decode(&mut BytesMut::from(&metadata[..]))?;
+                match AuthMetadata::extract(&composite)? {
+                    Some(auth) if auth.get_password() == Some("secret") => {
+                        Ok(Box::new(EchoRSocket))
+                    }
+                    _ => Err(RSocketError::RejectedSetup("bad credentials".into()).into()),
+                }
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    for (password, ok) in [("secret", true), ("guess", false)] {
+        let cli = RSocketFactory::connect()
+            .transport(TcpClientTransport::from(addr))
+            .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+            .setup(setup("user", password))
+            .start()
+            .await
+            .unwrap();
+        let res = cli.request_response(Payload::from("hello")).await;
+        assert_eq!(res.is_ok(), ok, "{}", password);
+    }
+}
@@ -0,0 +1,273 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::balancer::{LeastRequests, LoadBalancer, RoundRobin, Weighted};
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust::{async_trait, Result};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+struct Named {
+    name: &'static str,
+    availability: f64,
+}
+
+impl Named {
+    fn boxed(name: &'static str) -> Box<dyn RSocket> {
+        Box::new(Named {
+            name,
+            availability: 1.0,
+        })
+    }
+}
+
+#[async_trait]
+impl RSocket for Named {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, _req: Payload) -> Result<Option<Payload>> {
+        Ok(Some(Payload::builder().set_data_utf8(self.name).build()))
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        // never completes, keeps the request pending
+        Box::pin(stream::pending())
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        reqs
+    }
+
+    fn availability(&self) -> f64 {
+        self.availability
+    }
+}
+
+struct Closed;
+
+#[async_trait]
+impl RSocket for Closed {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Err(RSocketError::ConnectionClosed("closed".into()).into())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Err(RSocketError::ConnectionClosed("closed".into()).into())
+    }
+
+    async fn request_response(&self, _req: Payload) -> Result<Option<Payload>> {
+        Err(RSocketError::ConnectionClosed("closed".into()).into())
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        Box::pin(stream::once(async {
+            Err(RSocketError::ConnectionClosed("closed".into()).into())
+        }))
+    }
+
+    fn request_channel(&self, _reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        Box::pin(stream::once(async {
+            Err(RSocketError::ConnectionClosed("closed".into()).into())
+        }))
+    }
+}
+
+/// Forwards TCP connections to an upstream address, until they are dropped.
+struct Proxy {
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Proxy {
+    async fn start(addr: &str, upstream: &'static str) -> Proxy {
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let connections = Arc::new(Mutex::new(vec![]));
+        let registered = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                let handle = tokio::spawn(async move {
+                    if let Ok(mut outbound) = TcpStream::connect(upstream).await {
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+                registered.lock().unwrap().push(handle);
+            }
+        });
+        Proxy { connections }
+    }
+
+    /// Closes all the forwarded connections, like a server dropping idle ones.
+    fn drop_connections(&self) {
+        for it in self.connections.lock().unwrap().drain(..) {
+            it.abort();
+        }
+    }
+}
+
+async fn call(lb: &LoadBalancer) -> String {
+    lb.request_response(Payload::from("ping"))
+        .await
+        .expect("request failed")
+        .expect("empty response")
+        .data_utf8()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn test_round_robin() {
+    let lb = LoadBalancer::builder()
+        .strategy(RoundRobin::default())
+        .member(Named::boxed("a"))
+        .member(Named::boxed("b"))
+        .member(Named::boxed("c"))
+        .build()
+        .await;
+    let mut got = vec![];
+    for _ in 0..6 {
+        got.push(call(&lb).await);
+    }
+    assert_eq!(got.join(""), "abcabc");
+}
+
+#[tokio::test]
+async fn test_least_requests() {
+    let lb = LoadBalancer::builder()
+        .strategy(LeastRequests::default())
+        .member(Named::boxed("a"))
+        .member(Named::boxed("b"))
+        .build()
+        .await;
+    // occupy one of the members with a stream which never completes
+    let busy = lb.request_stream(Payload::from("stream"));
+    let first = call(&lb).await;
+    for _ in 0..3 {
+        assert_eq!(call(&lb).await, first);
+    }
+    drop(busy);
+    let mut got = vec![];
+    for _ in 0..2 {
+        got.push(call(&lb).await);
+    }
+    got.sort();
+    assert_eq!(got.join(""), "ab");
+}
+
+#[tokio::test]
+async fn test_skip_unavailable() {
+    let drained = Box::new(Named {
+        name: "drained",
+        availability: 0.0,
+    });
+    let lb = LoadBalancer::builder()
+        .strategy(Weighted::default())
+        .member(drained)
+        .weighted_member(Named::boxed("a"), 3)
+        .build()
+        .await;
+    for _ in 0..10 {
+        assert_eq!(call(&lb).await, "a");
+    }
+    assert_eq!(lb.len(), 2);
+}
+
+#[tokio::test]
+async fn test_evict_and_recover() {
+    let connects = Arc::new(AtomicUsize::new(0));
+    let counter = connects.clone();
+    let lb = LoadBalancer::builder()
+        .retry_interval(Duration::from_millis(50))
+        .target(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let rsocket: Box<dyn RSocket> = if n == 0 {
+                    Box::new(Closed)
+                } else {
+                    Named::boxed("recovered")
+                };
+                Ok(rsocket)
+            }
+        })
+        .member(Named::boxed("static"))
+        .build()
+        .await;
+    assert_eq!(lb.len(), 2);
+
+    // the broken member is evicted after its first failure
+    let mut failures = 0;
+    for _ in 0..2 {
+        if lb.request_response(Payload::from("ping")).await.is_err() {
+            failures += 1;
+        }
+    }
+    assert_eq!(failures, 1);
+    assert_eq!(lb.len(), 1);
+    assert_eq!(call(&lb).await, "static");
+
+    // then it is reconnected in background
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(lb.len(), 2);
+    assert_eq!(connects.load(Ordering::SeqCst), 2);
+    let mut got = vec![call(&lb).await, call(&lb).await];
+    got.sort();
+    assert_eq!(got, vec!["recovered", "static"]);
+}
+
+#[tokio::test]
+async fn test_no_available_member() {
+    let lb = LoadBalancer::builder().build().await;
+    assert!(lb.is_empty());
+    assert!(lb.request_response(Payload::from("ping")).await.is_err());
+    let mut results = lb.request_stream(Payload::from("ping"));
+    assert!(results.next().await.unwrap().is_err());
+    assert_eq!(lb.availability(), 0.0);
+}
+
+#[tokio::test]
+async fn test_evict_closed_while_idle() {
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8989"))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let proxy = Proxy::start("127.0.0.1:8990", "127.0.0.1:8989").await;
+
+    let connects = Arc::new(AtomicUsize::new(0));
+    let counter = connects.clone();
+    let lb = LoadBalancer::builder()
+        .retry_interval(Duration::from_millis(50))
+        .target(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {
+                let client = RSocketFactory::connect()
+                    .transport(TcpClientTransport::from("127.0.0.1:8990"))
+                    .start()
+                    .await?;
+                Ok(Box::new(client) as Box<dyn RSocket>)
+            }
+        })
+        .build()
+        .await;
+    assert_eq!(lb.len(), 1);
+    assert_eq!(call(&lb).await, "ping");
+
+    // the connection is closed by the peer while no request is in flight
+    proxy.drop_connections();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(connects.load(Ordering::SeqCst), 2);
+    assert_eq!(lb.len(), 1);
+    assert_eq!(call(&lb).await, "ping");
+}
@@ -0,0 +1,166 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::prelude::*;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::*;
+use rsocket_rust_transport_tcp::TcpServerTransport;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Student {
+    id: i64,
+    name: String,
+}
+
+#[rsocket_client]
+trait Students {
+    #[route("student.{id}")]
+    async fn get(&self, #[var] id: i64) -> Result<Student>;
+
+    #[route("student.{id}.nickname")]
+    async fn nickname(&self, #[var] id: i64) -> Result<Option<String>>;
+
+    #[route("student.{id}.rename")]
+    async fn rename(&self, name: String, #[var("id")] sid: i64) -> Result<Student>;
+
+    #[route("student.fire")]
+    async fn fire(&self, n: usize) -> Result<()>;
+
+    #[route("students")]
+    fn list(&self, n: i64) -> Flux<Result<Student>>;
+
+    #[route("students.{prefix}")]
+    fn rename_all(&self, #[var] prefix: &str, students: Flux<Student>) -> Flux<Result<Student>>;
+
+    async fn first(&self) -> Result<Student> {
+        self.get(0).await
+    }
+}
+
+#[derive(Clone, Default)]
+struct StudentController {
+    fired: Arc<AtomicUsize>,
+}
+
+#[rsocket_controller]
+impl StudentController {
+    #[route("student.{id}")]
+    async fn get(&self, #[var] id: i64) -> Result<Student> {
+        Ok(Student {
+            id,
+            name: format!("student-{}", id),
+        })
+    }
+
+    #[route("student.{id}.rename")]
+    async fn rename(&self, #[var] id: i64, name: String) -> Result<Student> {
+        Ok(Student { id, name })
+    }
+
+    #[route("student.fire")]
+    #[fire_and_forget]
+    async fn fire(&self, n: usize) -> Result<()> {
+        self.fired.fetch_add(n, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[route("students")]
+    #[request_stream]
+    fn list(&self, n: i64) -> Flux<Result<Student>> {
+        let students: Vec<Result<Student>> = (0..n)
+            .map(|id| {
+                Ok(Student {
+                    id,
+                    name: format!("student-{}", id),
+                })
+            })
+            .collect();
+        Box::pin(stream::iter(students))
+    }
+
+    #[route("students.{prefix}")]
+    #[request_channel]
+    fn rename_all(
+        &self,
+        #[var] prefix: String,
+        students: Flux<Result<Student>>,
+    ) -> Flux<Result<Student>> {
+        Box::pin(students.map(move |it| {
+            it.map(|student| Student {
+                name: format!("{}-{}", prefix, student.name),
+                ..student
+            })
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_client() {
+    let controller = StudentController::default();
+    let responder = controller.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8383"))
+            .acceptor(Box::new(move |_setup, _socket| {
+                Ok(Box::new(responder.clone()))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let requester = Requester::builder()
+        .connect_tcp("127.0.0.1", 8383)
+        .build()
+        .await
+        .unwrap();
+    let client = StudentsClient::new(requester);
+
+    let student = client.get(7).await.unwrap();
+    assert_eq!("student-7", student.name);
+    assert_eq!(0, client.first().await.unwrap().id);
+
+    let student = client.rename("tom".to_owned(), 3).await.unwrap();
+    assert_eq!(
+        Student {
+            id: 3,
+            name: "tom".to_owned()
+        },
+        student
+    );
+
+    let students: Vec<Student> = client.list(3).map(|it| it.unwrap()).collect().await;
+    assert_eq!(3, students.len());
+    assert_eq!("student-2", students[2].name);
+
+    let inputs: Flux<Student> = Box::pin(stream::iter(vec![
+        Student {
+            id: 1,
+            name: "tom".to_owned(),
+        },
+        Student {
+            id: 2,
+            name: "jerry".to_owned(),
+        },
+    ]));
+    let students: Vec<Student> = client
+        .rename_all("new", inputs)
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    assert_eq!(2, students.len());
+    assert_eq!("new-tom", students[0].name);
+    assert_eq!("new-jerry", students[1].name);
+
+    client.fire(2).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(2, controller.fired.load(Ordering::SeqCst));
+
+    // no handler for the route
+    assert!(client.nickname(1).await.is_err());
+}
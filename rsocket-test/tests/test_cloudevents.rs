@@ -0,0 +1,163 @@
+#[macro_use]
+extern crate serde_derive;
+
+use futures::stream;
+use rsocket_rust::extension::{MimeType, MimeTypeMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::Router;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::{cloudevents, handler, json, CloudEvent, Requester};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Order {
+    id: i64,
+    amount: u32,
+}
+
+fn created(id: i64) -> CloudEvent<Order> {
+    CloudEvent::builder(id.to_string(), "/orders", "com.example.order.created")
+        .subject(format!("order-{}", id))
+        .datacontenttype("application/json")
+        .extension(
+            "traceparent",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        )
+        .data(Order { id, amount: 42 })
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_structured_json() {
+    let event = created(1);
+    let value = serde_json::to_value(&event).unwrap();
+    assert_eq!(
+        json!({
+            "specversion": "1.0",
+            "id": "1",
+            "source": "/orders",
+            "type": "com.example.order.created",
+            "datacontenttype": "application/json",
+            "subject": "order-1",
+            "data": {"id": 1, "amount": 42},
+            "traceparent": "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        }),
+        value
+    );
+
+    let decoded: CloudEvent<Order> = serde_json::from_value(value).unwrap();
+    assert_eq!(event, decoded);
+    assert_eq!("com.example.order.created", decoded.ty());
+    assert_eq!(Some(&Order { id: 1, amount: 42 }), decoded.data());
+    assert!(decoded.extension("traceparent").is_some());
+
+    // events without data
+    let decoded: CloudEvent<Order> =
+        serde_json::from_str(r#"{"specversion":"1.0","id":"2","source":"/orders","type":"ping"}"#)
+            .unwrap();
+    assert_eq!(None, decoded.data());
+
+    for invalid in [
+        r#"{"specversion":"0.3","id":"1","source":"/orders","type":"ping"}"#,
+        r#"{"specversion":"1.0","id":"","source":"/orders","type":"ping"}"#,
+        r#"{"specversion":"1.0","id":"1","source":"/orders"}"#,
+    ] {
+        assert!(serde_json::from_str::<CloudEvent<Order>>(invalid).is_err());
+    }
+    for (id, source, ty) in [
+        ("", "/orders", "ping"),
+        ("1", "", "ping"),
+        ("1", "/orders", ""),
+    ] {
+        assert!(CloudEvent::<Order>::builder(id, source, ty)
+            .build()
+            .is_err());
+    }
+}
+
+fn router(ingested: mpsc::UnboundedSender<CloudEvent<Order>>) -> Router {
+    Router::builder()
+        .fire_and_forget(
+            "orders.ingest",
+            handler::fire_and_forget(cloudevents(), move |event: CloudEvent<Order>, _| {
+                let ingested = ingested.clone();
+                async move {
+                    ingested.send(event).unwrap();
+                    Ok(())
+                }
+            }),
+        )
+        .request_response(
+            "orders.echo",
+            handler::request_response(cloudevents(), |event: CloudEvent<Order>, _| async move {
+                Ok(event)
+            }),
+        )
+        .request_stream(
+            "orders.events",
+            handler::event_stream(json(), |n: i64, _| -> Flux<Result<CloudEvent<Order>>> {
+                Box::pin(stream::iter((1..=n).map(|id| Ok(created(id)))))
+            }),
+        )
+        .build()
+}
+
+#[tokio::test]
+async fn test_send_and_receive() {
+    let (ingested, mut received) = mpsc::unbounded_channel();
+    let requester = Requester::new(Box::new(router(ingested)), MimeType::APPLICATION_JSON);
+
+    requester
+        .route("orders.ingest")
+        .event(created(1))
+        .retrieve()
+        .await
+        .unwrap();
+    assert_eq!(Some(created(1)), received.recv().await);
+
+    let res = requester
+        .route("orders.echo")
+        .event(created(2))
+        .retrieve_mono()
+        .await
+        .event::<Order>()
+        .unwrap();
+    assert_eq!(Some(created(2)), res);
+
+    let res: Vec<CloudEvent<Order>> = requester
+        .route("orders.events")
+        .data(3)
+        .retrieve_flux()
+        .events()
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    assert_eq!(vec![created(1), created(2), created(3)], res);
+
+    // payloads of event streams declare their MIME type
+    let res: Vec<_> = requester
+        .route("orders.events")
+        .data(2)
+        .retrieve_flux()
+        .messages::<CloudEvent<Order>>()
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    assert_eq!(Some(&created(2)), res[1].data());
+    let declared: MimeTypeMetadata = res[1].metadata_extension().unwrap().unwrap();
+    assert_eq!(
+        &MimeType::APPLICATION_CLOUDEVENTS_JSON,
+        declared.get_mime_type()
+    );
+
+    // events are validated when they are ingested
+    let res = requester
+        .route("orders.echo")
+        .data(json!({"specversion": "0.3", "id": "1", "source": "/", "type": "ping"}))
+        .retrieve_mono()
+        .await
+        .event::<Order>();
+    assert!(res.is_err());
+}
@@ -0,0 +1,169 @@
+#[macro_use]
+extern crate serde_derive;
+
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::codec::{
+    Codec, Codecs, Protobuf, Visitor, APPLICATION_BINCODE, APPLICATION_MSGPACK,
+};
+use rsocket_rust_messaging::*;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Student {
+    id: i64,
+    name: String,
+    tags: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct Point {
+    #[prost(int64, tag = "1")]
+    x: i64,
+    #[prost(int64, tag = "2")]
+    y: i64,
+}
+
+/// Writes data as JSON with a prefix.
+struct Prefixed;
+
+impl Codec for Prefixed {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        let mut raw = b"prefixed:".to_vec();
+        raw.extend(serde_json::to_vec(data)?);
+        Ok(raw)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let raw = raw.strip_prefix(b"prefixed:").ok_or_else(|| {
+            rsocket_rust::error::RSocketError::WithDescription("missing prefix".into())
+        })?;
+        let mut de = serde_json::Deserializer::from_slice(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))
+    }
+}
+
+struct Echo;
+
+#[rsocket_rust::async_trait]
+impl RSocket for Echo {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        Ok(Some(req))
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        unimplemented!()
+    }
+
+    fn request_channel(&self, _reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        unimplemented!()
+    }
+}
+
+fn student() -> Student {
+    Student {
+        id: 1,
+        name: "tom".to_owned(),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+    }
+}
+
+#[test]
+fn test_builtin_codecs() {
+    let codecs = Codecs::default();
+    for mime_type in [
+        MimeType::APPLICATION_JSON,
+        MimeType::APPLICATION_CBOR,
+        MimeType::from(APPLICATION_MSGPACK),
+        MimeType::from(APPLICATION_BINCODE),
+    ] {
+        let raw = codecs.marshal(&mime_type, &student()).unwrap();
+        let res: Student = codecs.unmarshal(&mime_type, &raw).unwrap();
+        assert_eq!(student(), res);
+    }
+
+    let protobuf = MimeType::APPLICATION_VND_GOOGLE_PROTOBUF;
+    let point = Point { x: 1, y: -2 };
+    let raw = codecs.marshal(&protobuf, &Protobuf(point.clone())).unwrap();
+    assert_eq!(prost::Message::encode_to_vec(&point), raw);
+    let res: Protobuf<Point> = codecs.unmarshal(&protobuf, &raw).unwrap();
+    assert_eq!(point, res.0);
+    // only protobuf messages
+    assert!(codecs.marshal(&protobuf, &student()).is_err());
+
+    let raw = SerDe::marshal(&msgpack(), &student()).unwrap();
+    let res: Student = SerDe::unmarshal(&msgpack(), &raw).unwrap();
+    assert_eq!(student(), res);
+
+    let unknown = MimeType::from("application/x-unknown");
+    let e = codecs.marshal(&unknown, &student()).unwrap_err();
+    assert_eq!(
+        "unsupported mime type: application/x-unknown!",
+        e.to_string()
+    );
+}
+
+#[test]
+fn test_custom_codec() {
+    let mut codecs = Codecs::default();
+    codecs.register("application/x-prefixed", Prefixed);
+    let mime_type = MimeType::from("application/x-prefixed");
+    assert!(codecs.mime_types().contains(&mime_type));
+
+    let raw = codecs.marshal(&mime_type, &42).unwrap();
+    assert_eq!(b"prefixed:42", &raw[..]);
+    assert_eq!(42, codecs.unmarshal::<i64>(&mime_type, &raw).unwrap());
+    assert!(codecs.unmarshal::<i64>(&mime_type, b"42").is_err());
+
+    // replaces the codec of the same MIME type
+    codecs.register(MimeType::APPLICATION_JSON, Prefixed);
+    let raw = codecs.marshal(&MimeType::APPLICATION_JSON, &"foo").unwrap();
+    assert_eq!(b"prefixed:\"foo\"", &raw[..]);
+}
+
+#[tokio::test]
+async fn test_requester_codecs() {
+    let mut codecs = Codecs::default();
+    codecs.register("application/x-prefixed", Prefixed);
+    let requester =
+        Requester::new(Box::new(Echo), MimeType::from("application/x-prefixed")).set_codecs(codecs);
+
+    let res: Message<Student> = requester
+        .route("echo")
+        .data(student())
+        .retrieve_mono()
+        .await
+        .message()
+        .unwrap()
+        .unwrap();
+    assert!(res.payload().data().unwrap().starts_with(b"prefixed:"));
+    assert_eq!(Some(&student()), res.data());
+
+    let requester = Requester::new(Box::new(Echo), MimeType::from(APPLICATION_MSGPACK));
+    let res: Option<Student> = requester
+        .route("echo")
+        .data(student())
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(Some(student()), res);
+
+    // the default codecs don't know it
+    let requester = Requester::new(Box::new(Echo), MimeType::from("application/x-prefixed"));
+    assert!(requester
+        .route("echo")
+        .data(student())
+        .retrieve_mono()
+        .await
+        .decode::<Student>()
+        .is_err());
+}
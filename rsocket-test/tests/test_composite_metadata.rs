@@ -1,5 +1,9 @@
-use bytes::{BufMut, BytesMut};
-use rsocket_rust::extension::{self, CompositeMetadata, CompositeMetadataEntry, MimeType};
+use bytes::{BufMut, Bytes, BytesMut};
+use rsocket_rust::extension::{
+    self, AuthMetadata, CompositeMetadata, CompositeMetadataEntry, MetadataExtension, MimeType,
+    RoutingMetadata,
+};
+use rsocket_rust::prelude::*;
 use rsocket_rust::utils::Writeable;
 
 #[test]
@@ -39,3 +43,102 @@ fn test_bad() {
         "should be error"
     )
 }
+
+/// A made-up extension, carrying a single tenant name.
+#[derive(Debug, PartialEq)]
+struct Tenant(String);
+
+impl Writeable for Tenant {
+    fn write_to(&self, bf: &mut BytesMut) {
+        bf.put_slice(self.0.as_bytes());
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl MetadataExtension for Tenant {
+    fn mime_type() -> MimeType {
+        MimeType::from("application/x.tenant")
+    }
+
+    fn decode(bf: &mut BytesMut) -> rsocket_rust::Result<Self> {
+        Ok(Tenant(String::from_utf8(bf.split().to_vec())?))
+    }
+}
+
+#[test]
+fn test_get_and_insert() {
+    let mut cm = CompositeMetadata::default();
+    assert!(cm.get::<RoutingMetadata>().unwrap().is_none());
+
+    cm.insert(&RoutingMetadata::builder().push_str("foo").build());
+    cm.insert(&Tenant("acme".into()));
+    cm.insert(&AuthMetadata::builder().bearer("token").build());
+    // replaces the former entry
+    cm.insert(&RoutingMetadata::builder().push_str("bar").build());
+    assert_eq!(3, cm.iter().count());
+
+    let cm = CompositeMetadata::decode(&mut BytesMut::from(&cm.bytes()[..])).unwrap();
+    let routing: RoutingMetadata = cm.get().unwrap().unwrap();
+    assert_eq!(&vec!["bar".to_owned()], routing.get_tags());
+    assert_eq!(Some(Tenant("acme".into())), cm.get().unwrap());
+    let auth: AuthMetadata = cm.get().unwrap().unwrap();
+    assert_eq!(Some("token"), auth.get_token());
+
+    // well-known MIME types match whether or not they are encoded as well-known
+    let cm = CompositeMetadata::builder()
+        .push(MimeType::from("message/x.rsocket.routing.v0"), b"\x03baz")
+        .build();
+    let routing: RoutingMetadata = cm.get().unwrap().unwrap();
+    assert_eq!(&vec!["baz".to_owned()], routing.get_tags());
+}
+
+#[test]
+fn test_payload_accessors() {
+    let mut cm = CompositeMetadata::default();
+    cm.insert(&Tenant("acme".into()));
+    cm.insert(&RoutingMetadata::builder().push_str("foo").build());
+    let req = Payload::builder().set_metadata(cm.bytes()).build();
+
+    assert_eq!(2, req.composite_metadata().count());
+    assert_eq!(
+        Some(Tenant("acme".into())),
+        req.metadata_extension().unwrap()
+    );
+    let routing: RoutingMetadata = req.metadata_extension().unwrap().unwrap();
+    assert_eq!("foo", routing.get_tags()[0]);
+    assert!(req.metadata_extension::<AuthMetadata>().unwrap().is_none());
+    assert!(Payload::from("data")
+        .metadata_extension::<Tenant>()
+        .unwrap()
+        .is_none());
+
+    // entries before a broken one are still decoded
+    let mut bf = BytesMut::from(&cm.bytes()[..]);
+    bf.put_slice(b"broken");
+    let req = Payload::builder().set_metadata(bf.to_vec()).build();
+    let entries: Vec<_> = req.composite_metadata().collect();
+    assert_eq!(3, entries.len());
+    assert!(entries[0].is_ok() && entries[1].is_ok() && entries[2].is_err());
+    assert_eq!(
+        Some(Tenant("acme".into())),
+        req.metadata_extension().unwrap()
+    );
+    assert!(req.metadata_extension::<AuthMetadata>().is_err());
+}
+
+#[test]
+fn test_entries_share_bytes() {
+    let cm = CompositeMetadata::builder()
+        .push(MimeType::TEXT_PLAIN, b"Hello World!")
+        .build();
+    let bs = Bytes::from(cm.bytes());
+    let entry = CompositeMetadata::entries(bs.clone())
+        .next()
+        .unwrap()
+        .unwrap();
+    let range = bs.as_ptr_range();
+    assert!(range.contains(&entry.get_metadata().as_ptr()));
+}
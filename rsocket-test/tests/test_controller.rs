@@ -0,0 +1,234 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::codec::{Codec, Codecs, Visitor};
+use rsocket_rust_messaging::*;
+
+mod common;
+
+use common::routed;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Student {
+    id: i64,
+    name: String,
+}
+
+#[derive(Clone, Default)]
+struct StudentController {
+    fired: Arc<AtomicUsize>,
+}
+
+#[rsocket_controller]
+impl StudentController {
+    #[route("student.{id}")]
+    async fn get(&self, #[var] id: i64) -> Result<Student> {
+        Ok(Student {
+            id,
+            name: format!("student-{}", id),
+        })
+    }
+
+    #[route("student.{id}.rename")]
+    async fn rename(&self, #[var("id")] sid: i64, name: String) -> Result<Student> {
+        Ok(Student { id: sid, name })
+    }
+
+    #[route("student.fire")]
+    #[fire_and_forget]
+    async fn fire(&self, n: usize) -> Result<()> {
+        self.fired.fetch_add(n, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[route("students")]
+    #[request_stream]
+    fn list(&self, n: i64) -> Flux<Result<Student>> {
+        let students = (0..n).map(|id| {
+            Ok(Student {
+                id,
+                name: format!("student-{}", id),
+            })
+        });
+        Box::pin(stream::iter(students.collect::<Vec<_>>()))
+    }
+
+    #[route("students.{prefix}")]
+    #[request_channel]
+    fn rename_all(
+        &self,
+        students: Flux<Result<Student>>,
+        #[var] prefix: String,
+    ) -> Flux<Result<Student>> {
+        Box::pin(students.map(move |it| {
+            it.map(|student| Student {
+                name: format!("{}-{}", prefix, student.name),
+                ..student
+            })
+        }))
+    }
+
+    fn helper(&self) -> usize {
+        self.fired.load(Ordering::SeqCst)
+    }
+}
+
+/// Writes data as JSON with a prefix.
+struct Prefixed;
+
+impl Codec for Prefixed {
+    fn marshal(&self, data: &dyn erased_serde::Serialize) -> Result<Vec<u8>> {
+        let mut raw = b"prefixed:".to_vec();
+        raw.extend(serde_json::to_vec(data)?);
+        Ok(raw)
+    }
+
+    fn unmarshal(&self, raw: &[u8], visitor: &mut Visitor) -> Result<()> {
+        let raw = raw.strip_prefix(b"prefixed:").ok_or_else(|| {
+            rsocket_rust::error::RSocketError::WithDescription("missing prefix".into())
+        })?;
+        let mut de = serde_json::Deserializer::from_slice(raw);
+        visitor(&mut <dyn erased_serde::Deserializer>::erase(&mut de))
+    }
+}
+
+#[tokio::test]
+async fn test_controller() {
+    let controller = StudentController::default();
+    let requester = Requester::new(Box::new(controller.clone()), MimeType::APPLICATION_JSON);
+
+    let res: Option<Student> = requester
+        .route("student.7")
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(
+        Some(Student {
+            id: 7,
+            name: "student-7".to_owned()
+        }),
+        res
+    );
+
+    let res: Option<Student> = requester
+        .route_with("student.{id}.rename", &[&3])
+        .data_mime_type(MimeType::APPLICATION_CBOR)
+        .data("tom")
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(
+        Some(Student {
+            id: 3,
+            name: "tom".to_owned()
+        }),
+        res
+    );
+
+    let res: Vec<Student> = requester
+        .route("students")
+        .data(3)
+        .accept(MimeType::APPLICATION_CBOR)
+        .retrieve_flux()
+        .block()
+        .await
+        .unwrap();
+    assert_eq!(3, res.len());
+    assert_eq!("student-2", res[2].name);
+
+    requester
+        .route("student.fire")
+        .data(2)
+        .retrieve()
+        .await
+        .unwrap();
+    assert_eq!(2, controller.helper());
+
+    // errors of routing and decoding
+    let res: Result<Option<Student>> = requester
+        .route("student.abc")
+        .retrieve_mono()
+        .await
+        .decode();
+    assert!(res.is_err());
+    let res: Result<Option<Student>> = requester.route("teacher.1").retrieve_mono().await.decode();
+    assert!(res.is_err());
+    let res = requester.route("students").data(1).retrieve().await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_controller_channel() {
+    let controller = StudentController::default();
+    let reqs: Flux<Result<Payload>> = Box::pin(stream::iter(vec![
+        Ok(routed("students.new", r#"{"id":1,"name":"tom"}"#)),
+        Ok(Payload::from(r#"{"id":2,"name":"jerry"}"#)),
+    ]));
+    let res: Vec<Result<Payload>> = controller.request_channel(reqs).collect().await;
+    assert_eq!(2, res.len());
+    let names: Vec<String> = res
+        .into_iter()
+        .map(|it| it.unwrap().data_utf8().unwrap().to_owned())
+        .collect();
+    assert_eq!(r#"{"id":1,"name":"new-tom"}"#, names[0]);
+    assert_eq!(r#"{"id":2,"name":"new-jerry"}"#, names[1]);
+
+    let reqs: Flux<Result<Payload>> = Box::pin(stream::iter(vec![Ok(routed("student.1", ""))]));
+    let res: Vec<Result<Payload>> = controller.request_channel(reqs).collect().await;
+    assert_eq!(1, res.len());
+    assert!(res[0].is_err());
+}
+
+#[tokio::test]
+async fn test_controller_context() {
+    let mut codecs = Codecs::default();
+    codecs.register("application/x-prefixed", Prefixed);
+    let setup = SetupPayload::builder()
+        .set_data_mime_type("application/x-prefixed")
+        .build();
+    let controller = Controller::new(StudentController::default())
+        .codecs(codecs)
+        .with_setup(&setup);
+
+    // requests declaring no MIME type follow the SETUP of the connection
+    let res = controller
+        .request_response(routed("student.3.rename", r#"prefixed:"tom""#))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some(r#"prefixed:{"id":3,"name":"tom"}"#), res.data_utf8());
+
+    let res: Vec<Result<Payload>> = controller
+        .request_stream(routed("students", "prefixed:1"))
+        .collect()
+        .await;
+    assert_eq!(
+        Some(r#"prefixed:{"id":0,"name":"student-0"}"#),
+        res[0].as_ref().unwrap().data_utf8()
+    );
+
+    let reqs: Flux<Result<Payload>> = Box::pin(stream::iter(vec![
+        Ok(routed("students.new", r#"prefixed:{"id":1,"name":"tom"}"#)),
+        Ok(Payload::from(r#"prefixed:{"id":2,"name":"jerry"}"#)),
+    ]));
+    let res: Vec<Result<Payload>> = controller.request_channel(reqs).collect().await;
+    assert_eq!(
+        Some(r#"prefixed:{"id":2,"name":"new-jerry"}"#),
+        res[1].as_ref().unwrap().data_utf8()
+    );
+
+    // used as an RSocket directly, a controller only knows JSON
+    let res = StudentController::default()
+        .request_response(routed("student.3.rename", r#"prefixed:"tom""#))
+        .await;
+    assert!(res.is_err());
+}
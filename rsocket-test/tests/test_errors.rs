@@ -0,0 +1,153 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::time::Duration;
+
+use futures::stream;
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::Variables;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::*;
+use rsocket_rust_transport_tcp::TcpServerTransport;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Fault {
+    NotFound { id: i64 },
+    Conflict(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Student {
+    id: i64,
+    name: String,
+}
+
+#[derive(Clone)]
+struct StudentController;
+
+#[rsocket_controller]
+impl StudentController {
+    #[route("student.{id}")]
+    async fn get(&self, #[var] id: i64) -> Result<Student> {
+        match id {
+            0 => Err(RSocketError::WithDescription("boom".into()).into()),
+            id if id < 0 => Err(ErrorBody::new(Fault::NotFound { id }).into()),
+            id => Ok(Student {
+                id,
+                name: format!("student-{}", id),
+            }),
+        }
+    }
+
+    #[route("students")]
+    #[request_stream]
+    fn list(&self, n: i64) -> Flux<Result<Student>> {
+        let mut students: Vec<Result<Student>> = (0..n)
+            .map(|id| {
+                Ok(Student {
+                    id,
+                    name: format!("student-{}", id),
+                })
+            })
+            .collect();
+        students.push(Err(ErrorBody::new(Fault::Conflict("full".into())).into()));
+        Box::pin(stream::iter(students))
+    }
+}
+
+#[tokio::test]
+async fn test_error_body() {
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8686"))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(StudentController))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let requester = Requester::builder()
+        .connect_tcp("127.0.0.1", 8686)
+        .build()
+        .await
+        .unwrap();
+
+    let res = requester
+        .route("student.1")
+        .retrieve_mono()
+        .await
+        .decode_or_err::<Student, Fault>()
+        .unwrap();
+    assert_eq!(Some(1), res.map(|it| it.id));
+
+    // the body is encoded with the MIME type of the response, JSON and CBOR
+    for mime_type in [MimeType::APPLICATION_JSON, MimeType::APPLICATION_CBOR] {
+        let res = requester
+            .route("student.-3")
+            .data_mime_type(mime_type)
+            .retrieve_mono()
+            .await
+            .decode_or_err::<Student, Fault>();
+        match res {
+            Err(RequestError::Body(e)) => assert_eq!(Fault::NotFound { id: -3 }, e),
+            _ => panic!("unexpected result: {:?}", res.map(|_| ())),
+        }
+    }
+
+    // plain errors are kept
+    let res = requester
+        .route("student.0")
+        .retrieve_mono()
+        .await
+        .decode_or_err::<Student, Fault>();
+    match res {
+        Err(RequestError::Other(e)) => assert!(e.to_string().contains("boom")),
+        _ => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+    let res: Result<Option<Student>> = requester.route("student.-3").retrieve_mono().await.decode();
+    assert!(res.unwrap_err().to_string().contains("NotFound"));
+
+    let res = requester
+        .route("students")
+        .data(2)
+        .accept(MimeType::APPLICATION_CBOR)
+        .retrieve_flux()
+        .block_or_err::<Student, Fault>()
+        .await;
+    match res {
+        Err(RequestError::Body(e)) => assert_eq!(Fault::Conflict("full".into()), e),
+        _ => panic!("unexpected result: {:?}", res.map(|_| ())),
+    }
+
+    let res: Vec<_> = requester
+        .route("students")
+        .data(2)
+        .retrieve_flux()
+        .flux_or_err::<Student, Fault>()
+        .collect()
+        .await;
+    assert_eq!(3, res.len());
+    assert_eq!(1, res[1].as_ref().unwrap().id);
+    assert!(matches!(
+        &res[2],
+        Err(RequestError::Body(Fault::Conflict(_)))
+    ));
+}
+
+#[tokio::test]
+async fn test_handler_error_body() {
+    let handle = handler::request_response(json(), |id: i64, _| async move {
+        if id < 0 {
+            Err(ErrorBody::new(Fault::NotFound { id }).into())
+        } else {
+            Ok(id)
+        }
+    });
+    let e = handle(Payload::from("-1"), Variables::default())
+        .await
+        .unwrap_err();
+    let raw = e.downcast_ref::<RSocketError>().unwrap().application_data();
+    assert_eq!(Some(&br#"{"NotFound":{"id":-1}}"#[..]), raw);
+}
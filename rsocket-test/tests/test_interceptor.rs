@@ -0,0 +1,180 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::interceptor::{ConnectionInfo, Interceptor};
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust::{async_trait, Client, Result};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+
+#[derive(Clone)]
+struct Peer(SocketAddr);
+
+/// Rejects the setup "bad" and prefixes responses with the peer address.
+struct Guard;
+
+#[async_trait]
+impl Interceptor for Guard {
+    async fn on_setup(&self, info: &ConnectionInfo) -> Result<()> {
+        if info.setup().data().map(|it| it.as_ref()) == Some(b"bad".as_ref()) {
+            return Err(RSocketError::RejectedSetup("bad setup".into()).into());
+        }
+        info.insert(Peer(info.peer_addr().expect("missing peer address")));
+        Ok(())
+    }
+
+    fn responder(&self, info: &ConnectionInfo, responder: Box<dyn RSocket>) -> Box<dyn RSocket> {
+        let peer = info.get::<Peer>().unwrap();
+        Box::new(Prefix {
+            inner: responder,
+            prefix: format!("{}|", peer.0.ip()),
+        })
+    }
+}
+
+struct Prefix {
+    inner: Box<dyn RSocket>,
+    prefix: String,
+}
+
+#[async_trait]
+impl RSocket for Prefix {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        self.inner.metadata_push(req).await
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        self.inner.fire_and_forget(req).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        let res = self.inner.request_response(req).await?;
+        Ok(res.map(|it| {
+            let data = format!("{}{}", self.prefix, it.data_utf8().unwrap_or_default());
+            let mut bu = Payload::builder().set_data_utf8(&data);
+            if let Some(m) = it.metadata_utf8() {
+                bu = bu.set_metadata_utf8(m);
+            }
+            bu.build()
+        }))
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        self.inner.request_stream(req)
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        self.inner.request_channel(reqs)
+    }
+}
+
+/// Adds a token as metadata and refuses to send "forbidden".
+struct Token;
+
+impl Interceptor for Token {
+    fn requester(&self, _info: &ConnectionInfo, requester: Box<dyn RSocket>) -> Box<dyn RSocket> {
+        Box::new(WithToken(requester))
+    }
+}
+
+struct WithToken(Box<dyn RSocket>);
+
+#[async_trait]
+impl RSocket for WithToken {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        self.0.metadata_push(req).await
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        self.0.fire_and_forget(req).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        if req.data_utf8() == Some("forbidden") {
+            return Err(RSocketError::RequestRejected("forbidden".into()).into());
+        }
+        let req = Payload::builder()
+            .set_data_utf8(req.data_utf8().unwrap_or_default())
+            .set_metadata_utf8("token")
+            .build();
+        self.0.request_response(req).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        self.0.request_stream(req)
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        self.0.request_channel(reqs)
+    }
+
+    fn availability(&self) -> f64 {
+        self.0.availability()
+    }
+}
+
+struct Deny;
+
+#[async_trait]
+impl Interceptor for Deny {
+    async fn on_setup(&self, _info: &ConnectionInfo) -> Result<()> {
+        Err(RSocketError::WithDescription("denied".into()).into())
+    }
+}
+
+async fn connect(addr: &str, setup: &'static str) -> Result<Client> {
+    RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .setup(Payload::from(setup))
+        .interceptor(Token)
+        .start()
+        .await
+}
+
+#[tokio::test]
+async fn test_interceptors() {
+    let addr = "127.0.0.1:7272";
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .interceptor(Guard)
+            .acceptor(Box::new(move |_setup, _socket| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(EchoRSocket))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let cli = connect(addr, "good").await.unwrap();
+    let res = cli
+        .request_response(Payload::from("hello"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(res.data_utf8(), Some("127.0.0.1|hello"));
+    assert_eq!(res.metadata_utf8(), Some("token"));
+    assert!(cli
+        .request_response(Payload::from("forbidden"))
+        .await
+        .is_err());
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    // the acceptor is never called for a rejected setup
+    let cli = connect(addr, "bad").await.unwrap();
+    assert!(cli.request_response(Payload::from("hello")).await.is_err());
+    assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+    let res = RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .interceptor(Deny)
+        .start()
+        .await;
+    assert!(res.is_err());
+}
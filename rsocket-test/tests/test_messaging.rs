@@ -3,8 +3,20 @@ extern crate log;
 #[macro_use]
 extern crate serde_derive;
 
-use rsocket_rust::extension::MimeType;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::{MimeType, RoutingMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust::Result;
 use rsocket_rust_messaging::*;
+use rsocket_rust_transport_tcp::TcpServerTransport;
+
+const BEARER: &str = "message/x.rsocket.authentication.bearer.v0";
 
 fn init() {
     let _ = env_logger::builder()
@@ -39,6 +51,343 @@ pub struct Response<T> {
     data: T,
 }
 
+/// Records the metadata of requests and channels, echoes other requests.
+#[derive(Clone, Default)]
+struct Recorder {
+    metadatas: Arc<Mutex<Vec<Option<bytes::Bytes>>>>,
+}
+
+#[rsocket_rust::async_trait]
+impl RSocket for Recorder {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        self.metadatas.lock().unwrap().push(req.metadata().cloned());
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        unimplemented!()
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        Ok(Some(req))
+    }
+
+    /// Echoes the request, then ends with a payload without data.
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        let metadata = req.metadata().cloned().unwrap_or_default();
+        let last = Payload::builder().set_metadata(metadata).build();
+        Box::pin(stream::iter(vec![Ok(req), Ok(last)]))
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        let metadatas = self.metadatas.clone();
+        Box::pin(reqs.map(move |it| {
+            let req = it?;
+            metadatas.lock().unwrap().push(req.metadata().cloned());
+            Ok(Payload::builder()
+                .set_data(req.data().cloned().unwrap_or_default())
+                .build())
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_channel() {
+    let recorder = Recorder::default();
+    let requester = Requester::new(Box::new(recorder.clone()), MimeType::APPLICATION_JSON);
+
+    let inputs = stream::iter(vec![1, 2, 3]);
+    let res: Vec<i64> = requester
+        .route("numbers")
+        .retrieve_channel(inputs)
+        .block()
+        .await
+        .unwrap();
+    assert_eq!(vec![1, 2, 3], res);
+    {
+        let metadatas = recorder.metadatas.lock().unwrap();
+        assert_eq!(3, metadatas.len());
+        let routing: Option<RoutingMetadata> = Payload::builder()
+            .set_metadata(metadatas[0].clone().unwrap())
+            .build()
+            .metadata_extension()
+            .unwrap();
+        assert_eq!("numbers", routing.unwrap().get_tags()[0]);
+        assert!(metadatas[1..].iter().all(|it| it.is_none()));
+    }
+
+    // the data of the request goes first
+    let inputs = stream::iter(vec!["b".to_owned(), "c".to_owned()]);
+    let res: Vec<Result<String>> = requester
+        .route("letters")
+        .data("a")
+        .retrieve_channel(inputs)
+        .flux()
+        .collect()
+        .await;
+    let res: Vec<String> = res.into_iter().map(|it| it.unwrap()).collect();
+    assert_eq!(vec!["a", "b", "c"], res);
+
+    // without inputs, the metadata is sent alone so that the channel is requested
+    recorder.metadatas.lock().unwrap().clear();
+    let _: Vec<Result<i64>> = requester
+        .route("nothing")
+        .retrieve_channel(stream::empty::<i64>())
+        .flux()
+        .collect()
+        .await;
+    let metadatas = recorder.metadatas.lock().unwrap();
+    assert_eq!(1, metadatas.len());
+    let request = Payload::builder()
+        .set_metadata(metadatas[0].clone().unwrap())
+        .build();
+    let routing: Option<RoutingMetadata> = request.metadata_extension().unwrap();
+    assert_eq!("nothing", routing.unwrap().get_tags()[0]);
+    assert_eq!(1, entries_of(&request, REQUEST_ONLY_MIME_TYPE).len());
+}
+
+#[tokio::test]
+async fn test_metadata_push() {
+    let recorder = Recorder::default();
+    let requester = Requester::new(Box::new(recorder.clone()), MimeType::APPLICATION_JSON);
+
+    requester
+        .metadata(Token::default(), MimeType::APPLICATION_JSON)
+        .metadata_raw("foobar", "message/x.rsocket.authentication.bearer.v0")
+        .metadata_push()
+        .await
+        .unwrap();
+    requester.route("refresh").metadata_push().await.unwrap();
+    assert!(requester
+        .route("refresh")
+        .data(1)
+        .metadata_push()
+        .await
+        .is_err());
+
+    let metadatas = recorder.metadatas.lock().unwrap();
+    assert_eq!(2, metadatas.len());
+    let pushed = Payload::builder()
+        .set_metadata(metadatas[0].clone().unwrap())
+        .build();
+    let entries: Vec<_> = pushed.composite_metadata().map(|it| it.unwrap()).collect();
+    assert_eq!(2, entries.len());
+    assert_eq!(&MimeType::APPLICATION_JSON, entries[0].get_mime_type());
+    assert_eq!(b"foobar", &entries[1].get_metadata()[..]);
+    let routing: Option<RoutingMetadata> = Payload::builder()
+        .set_metadata(metadatas[1].clone().unwrap())
+        .build()
+        .metadata_extension()
+        .unwrap();
+    assert_eq!("refresh", routing.unwrap().get_tags()[0]);
+}
+
+#[tokio::test]
+async fn test_unpack() {
+    let requester = Requester::new(Box::new(Recorder::default()), MimeType::APPLICATION_JSON);
+
+    let res: Option<i64> = requester
+        .route("echo")
+        .data(42)
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(Some(42), res);
+
+    let res: Message<i64> = requester
+        .route("echo")
+        .data(42)
+        .retrieve_mono()
+        .await
+        .message()
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some(&42), res.data());
+    let routing: RoutingMetadata = res.metadata_extension().unwrap().unwrap();
+    assert_eq!("echo", routing.get_tags()[0]);
+
+    // payloads without data are skipped
+    let mut results = requester
+        .route("echo")
+        .data(7)
+        .retrieve_flux()
+        .flux::<i64>();
+    assert_eq!(7, results.next().await.unwrap().unwrap());
+    assert!(results.next().await.is_none());
+
+    let messages: Vec<Message<i64>> = requester
+        .route("echo")
+        .data(7)
+        .retrieve_flux()
+        .messages()
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    assert_eq!(2, messages.len());
+    assert_eq!(Some(&7), messages[0].data());
+    assert_eq!(None, messages[1].data());
+    let routing: RoutingMetadata = messages[1].metadata_extension().unwrap().unwrap();
+    assert_eq!("echo", routing.get_tags()[0]);
+
+    // errors of decoding are results of the stream
+    let mut results = requester
+        .route("echo")
+        .data("x")
+        .retrieve_flux()
+        .flux::<i64>();
+    assert!(results.next().await.unwrap().is_err());
+}
+
+/// Data which can't be sent across threads.
+#[derive(Serialize)]
+struct Local {
+    value: i64,
+    #[serde(skip)]
+    _local: std::marker::PhantomData<std::rc::Rc<()>>,
+}
+
+#[tokio::test]
+async fn test_encode_eagerly() {
+    let requester = Requester::new(Box::new(Recorder::default()), MimeType::APPLICATION_JSON);
+    let local = || Local {
+        value: 3,
+        _local: Default::default(),
+    };
+
+    let res: Option<serde_json::Value> = requester
+        .route("echo")
+        .metadata(local(), MimeType::APPLICATION_JSON)
+        .data(local())
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(Some(serde_json::json!({"value": 3})), res);
+
+    // the MIME type of the data is set after the data
+    assert!(requester
+        .route("echo")
+        .data(1)
+        .data_mime_type(MimeType::APPLICATION_CBOR)
+        .retrieve()
+        .await
+        .is_err());
+}
+
+/// Returns the metadata entries of a payload by MIME type.
+fn entries_of(payload: &Payload, mime_type: &str) -> Vec<String> {
+    let mime_type = MimeType::from(mime_type);
+    payload
+        .composite_metadata()
+        .map(|it| it.unwrap())
+        .filter(|it| it.get_mime_type() == &mime_type)
+        .map(|it| it.get_metadata_utf8().unwrap().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_default_metadata() {
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8585"))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let refreshed = Arc::new(AtomicUsize::new(0));
+    let counter = refreshed.clone();
+    let requester = Requester::builder()
+        .default_metadata(
+            Tracing {
+                id: "t1".to_owned(),
+                ts: 1,
+            },
+            MimeType::APPLICATION_JSON,
+        )
+        .metadata_provider(BEARER, move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move { Ok(format!("token-{}", n)) }
+        })
+        .connect_tcp("127.0.0.1", 8585)
+        .build()
+        .await
+        .unwrap();
+
+    let echo = || async {
+        let res: Message<i64> = requester
+            .route("echo")
+            .data(1)
+            .retrieve_mono()
+            .await
+            .message()
+            .unwrap()
+            .unwrap();
+        res.payload().clone()
+    };
+    let res = echo().await;
+    assert_eq!(vec!["token-1"], entries_of(&res, BEARER));
+    assert_eq!(
+        vec![r#"{"id":"t1","ts":1}"#],
+        entries_of(&res, "application/json")
+    );
+    let routing: RoutingMetadata = res.metadata_extension().unwrap().unwrap();
+    assert_eq!("echo", routing.get_tags()[0]);
+    // the provider is called for every request
+    assert_eq!(vec!["token-2"], entries_of(&echo().await, BEARER));
+
+    // metadata of the request overrides the default one
+    let res: Message<i64> = requester
+        .route("echo")
+        .metadata_raw("mine", BEARER)
+        .data(1)
+        .retrieve_mono()
+        .await
+        .message()
+        .unwrap()
+        .unwrap();
+    assert_eq!(vec!["mine"], entries_of(res.payload(), BEARER));
+    assert_eq!(2, refreshed.load(Ordering::SeqCst));
+
+    let res: Vec<Message<i64>> = requester
+        .route("echo")
+        .data(1)
+        .retrieve_flux()
+        .messages()
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    assert_eq!(vec!["token-3"], entries_of(res[0].payload(), BEARER));
+
+    let mut res = requester
+        .route("echo")
+        .retrieve_channel(stream::iter(vec![1, 2]))
+        .messages::<i64>();
+    let first = res.next().await.unwrap().unwrap();
+    assert_eq!(vec!["token-4"], entries_of(first.payload(), BEARER));
+    assert_eq!(Some(&2), res.next().await.unwrap().unwrap().data());
+
+    // errors of providers fail requests
+    let requester = Requester::builder()
+        .metadata_provider(BEARER, || async {
+            Err::<String, _>(RSocketError::WithDescription("expired".into()).into())
+        })
+        .connect_tcp("127.0.0.1", 8585)
+        .build()
+        .await
+        .unwrap();
+    let res = requester.route("echo").data(1).retrieve_mono().await;
+    assert!(res.decode::<i64>().is_err());
+    let mut res = requester
+        .route("echo")
+        .data(1)
+        .retrieve_flux()
+        .flux::<i64>();
+    assert!(res.next().await.unwrap().is_err());
+}
+
 #[tokio::main]
 #[test]
 #[ignore]
@@ -64,7 +413,7 @@ async fn test_messaging() {
         .data(next_post())
         .retrieve_mono()
         .await
-        .block()
+        .decode()
         .expect("Retrieve failed!")
         .expect("Empty result!");
     info!("------> RESPONSE: {:?}", res);
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use metrics::{SharedString, Unit};
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use metrics_util::CompositeKey;
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+
+mod common;
+
+use common::routed;
+
+type Snapshot = Vec<(CompositeKey, Option<Unit>, Option<SharedString>, DebugValue)>;
+
+fn counter(snapshot: &Snapshot, name: &str, labels: &[(&str, &str)]) -> u64 {
+    snapshot
+        .iter()
+        .filter(|(key, _, _, _)| {
+            let key = key.key();
+            key.name() == name
+                && labels
+                    .iter()
+                    .all(|(k, v)| key.labels().any(|it| it.key() == *k && it.value() == *v))
+        })
+        .map(|(_, _, _, value)| match value {
+            DebugValue::Counter(n) => *n,
+            _ => 0,
+        })
+        .sum()
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    recorder.install().unwrap();
+
+    let addr = "127.0.0.1:7474";
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let cli = RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+        .start()
+        .await
+        .unwrap();
+
+    for _ in 0..3 {
+        let res = cli.request_response(routed("echo", "hello")).await.unwrap();
+        assert!(res.is_some());
+    }
+    let results: Vec<_> = cli.request_stream(routed("feed", "hello")).collect().await;
+    assert_eq!(results.len(), 1);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // counters are reset by taking a snapshot
+    let snapshot = snapshotter.snapshot().into_vec();
+    let echo = [("interaction", "request_response"), ("route", "echo")];
+    for role in ["requester", "responder"] {
+        let labels = [echo[0], echo[1], ("role", role)];
+        assert_eq!(
+            counter(&snapshot, "rsocket_requests_started_total", &labels),
+            3
+        );
+        assert_eq!(
+            counter(&snapshot, "rsocket_requests_completed_total", &labels),
+            3
+        );
+        assert_eq!(
+            counter(&snapshot, "rsocket_requests_errored_total", &labels),
+            0
+        );
+    }
+    let feed = [
+        ("interaction", "request_stream"),
+        ("route", "feed"),
+        ("role", "requester"),
+    ];
+    assert_eq!(
+        counter(&snapshot, "rsocket_requests_completed_total", &feed),
+        1
+    );
+
+    let sent = [("frame_type", "REQUEST_RESPONSE")];
+    assert_eq!(counter(&snapshot, "rsocket_frames_sent_total", &sent), 3);
+    assert_eq!(
+        counter(&snapshot, "rsocket_frames_received_total", &sent),
+        3
+    );
+    assert!(counter(&snapshot, "rsocket_bytes_sent_total", &sent) > 0);
+    assert_eq!(
+        counter(
+            &snapshot,
+            "rsocket_frames_received_total",
+            &[("frame_type", "SETUP")]
+        ),
+        1
+    );
+
+    for side in ["client", "server"] {
+        let labels = [("side", side)];
+        assert_eq!(
+            counter(&snapshot, "rsocket_connections_opened_total", &labels),
+            1
+        );
+    }
+}
@@ -0,0 +1,175 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::time::Duration;
+
+use bytes::BytesMut;
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::{
+    AcceptMimeTypesMetadata, CompositeMetadata, MimeType, MimeTypeMetadata,
+};
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::Writeable;
+use rsocket_rust::{async_trait, Result};
+use rsocket_rust_messaging::Requester;
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+
+#[test]
+fn mime_type_codec() {
+    let json = MimeTypeMetadata::new(MimeType::APPLICATION_JSON);
+    assert_eq!(json.bytes(), vec![0x85]);
+    let custom = MimeTypeMetadata::from(MimeType::from("application/x-custom"));
+    let mut bf = BytesMut::from(&custom.bytes()[..]);
+    assert_eq!(bf[0] as usize, "application/x-custom".len() - 1);
+    assert_eq!(bf.len(), custom.len());
+    assert_eq!(MimeTypeMetadata::decode(&mut bf).unwrap(), custom);
+    assert!(MimeTypeMetadata::decode(&mut BytesMut::new()).is_err());
+    assert!(MimeTypeMetadata::decode(&mut BytesMut::from(&[0x05u8, b'a'][..])).is_err());
+}
+
+#[test]
+fn accept_mime_types_codec() {
+    let accept = AcceptMimeTypesMetadata::builder()
+        .push(MimeType::from("application/x-custom"))
+        .push(MimeType::APPLICATION_CBOR)
+        .push(MimeType::APPLICATION_JSON)
+        .build();
+    let mut bf = BytesMut::from(&accept.bytes()[..]);
+    assert_eq!(bf.len(), accept.len());
+    let decoded = AcceptMimeTypesMetadata::decode(&mut bf).unwrap();
+    assert_eq!(decoded, accept);
+    assert_eq!(decoded.get_mime_types().len(), 3);
+
+    let offered = [MimeType::APPLICATION_JSON, MimeType::APPLICATION_CBOR];
+    assert_eq!(
+        decoded.negotiate(&offered),
+        Some(&MimeType::APPLICATION_CBOR)
+    );
+    assert_eq!(decoded.negotiate(&[MimeType::TEXT_PLAIN]), None);
+
+    let mut composite = CompositeMetadata::default();
+    assert_eq!(AcceptMimeTypesMetadata::extract(&composite).unwrap(), None);
+    accept.inject(&mut composite);
+    MimeTypeMetadata::new(MimeType::APPLICATION_CBOR).inject(&mut composite);
+    let composite = CompositeMetadata::decode(&mut BytesMut::from(&composite.bytes()[..])).unwrap();
+    assert_eq!(
+        AcceptMimeTypesMetadata::extract(&composite).unwrap(),
+        Some(accept)
+    );
+    assert_eq!(
+        MimeTypeMetadata::extract(&composite)
+            .unwrap()
+            .unwrap()
+            .get_mime_type(),
+        &MimeType::APPLICATION_CBOR
+    );
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Student {
+    id: i64,
+    name: String,
+}
+
+/// Echoes the data, declaring its MIME type if the requester accepts it.
+struct Negotiator;
+
+#[async_trait]
+impl RSocket for Negotiator {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        let metadata = req.metadata().cloned().unwrap_or_default();
+        let composite = CompositeMetadata::decode(&mut BytesMut::from(&metadata[..]))?;
+        let mime_type = match MimeTypeMetadata::extract(&composite)? {
+            Some(it) => it.get_mime_type().clone(),
+            None => MimeType::APPLICATION_JSON,
+        };
+        let accept = AcceptMimeTypesMetadata::extract(&composite)?
+            .ok_or_else(|| RSocketError::RequestInvalid("missing accept".into()))?;
+        let mut bu = Payload::builder();
+        if let Some(data) = req.data() {
+            bu = bu.set_data(data.clone());
+        }
+        if accept.get_mime_types()[0] == mime_type {
+            let mut declared = CompositeMetadata::default();
+            MimeTypeMetadata::new(mime_type).inject(&mut declared);
+            bu = bu.set_metadata(declared.bytes());
+        }
+        Ok(Some(bu.build()))
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        unimplemented!()
+    }
+
+    fn request_channel(&self, _reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn test_negotiation() {
+    let addr = "127.0.0.1:8181";
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(Negotiator))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let cli = RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .data_mime_type(MimeType::APPLICATION_JSON)
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+        .start()
+        .await
+        .unwrap();
+    let requester = Requester::new(Box::new(cli), MimeType::APPLICATION_JSON);
+    let student = || Student {
+        id: 1,
+        name: "Jeffsky".to_owned(),
+    };
+
+    // encoded and declared as CBOR, which fails to be decoded as JSON
+    let res: Option<Student> = requester
+        .route("students.echo")
+        .data_mime_type(MimeType::APPLICATION_CBOR)
+        .accept(MimeType::APPLICATION_CBOR)
+        .data(student())
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(res, Some(student()));
+
+    // undeclared, the first supported accepted MIME type is used
+    let res: Option<Student> = requester
+        .route("students.echo")
+        .accept("application/xml")
+        .accept("application/json")
+        .data(student())
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(res, Some(student()));
+
+    let res: Result<Option<Student>> = requester
+        .route("students.echo")
+        .data_mime_type("application/xml")
+        .accept("application/xml")
+        .data(student())
+        .retrieve_mono()
+        .await
+        .decode();
+    assert!(res.is_err());
+}
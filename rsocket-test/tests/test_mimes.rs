@@ -20,6 +20,15 @@ fn test_wellknown() {
     assert_eq!(MimeType::Normal("application/custom".to_owned()), custom);
 }
 
+#[test]
+fn test_cbor() {
+    // the well-known id 0x01 is CBOR, not a second AVRO
+    assert_eq!(Some("application/cbor"), MimeType::APPLICATION_CBOR.as_str());
+    assert_eq!(MimeType::APPLICATION_CBOR, MimeType::from("application/cbor"));
+    assert_eq!(Some(MimeType::APPLICATION_CBOR), MimeType::parse(0x01));
+    assert_eq!(MimeType::APPLICATION_AVRO, MimeType::from("application/avro"));
+}
+
 #[test]
 fn test_parse() {
     assert!(MimeType::parse(0xFF).is_none(), "should be none");
@@ -0,0 +1,222 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::balancer::Pool;
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust::{async_trait, Result};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+struct Conn(usize);
+
+#[async_trait]
+impl RSocket for Conn {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, _req: Payload) -> Result<Option<Payload>> {
+        Ok(Some(
+            Payload::builder()
+                .set_data_utf8(&self.0.to_string())
+                .build(),
+        ))
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        // never completes, keeps the stream outstanding
+        Box::pin(stream::pending())
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        reqs
+    }
+}
+
+/// Forwards TCP connections to an upstream address, until they are dropped.
+struct Proxy {
+    connections: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Proxy {
+    async fn start(addr: &str, upstream: &'static str) -> Proxy {
+        let listener = TcpListener::bind(addr).await.unwrap();
+        let connections = Arc::new(Mutex::new(vec![]));
+        let registered = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                let handle = tokio::spawn(async move {
+                    if let Ok(mut outbound) = TcpStream::connect(upstream).await {
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+                registered.lock().unwrap().push(handle);
+            }
+        });
+        Proxy { connections }
+    }
+
+    /// Closes all the forwarded connections, like a server dropping idle ones.
+    fn drop_connections(&self) {
+        for it in self.connections.lock().unwrap().drain(..) {
+            it.abort();
+        }
+    }
+}
+
+async fn call(pool: &Pool) -> String {
+    pool.request_response(Payload::from("ping"))
+        .await
+        .expect("request failed")
+        .expect("empty response")
+        .data_utf8()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn test_least_outstanding() {
+    let connects = Arc::new(AtomicUsize::new(0));
+    let counter = connects.clone();
+    let pool = Pool::builder()
+        .size(3)
+        .connect(move || {
+            let id = counter.fetch_add(1, Ordering::SeqCst);
+            async move { Ok(Conn(id)) }
+        })
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(connects.load(Ordering::SeqCst), 3);
+    assert_eq!(pool.connections(), 3);
+
+    // each stream occupies another connection
+    let first = pool.request_stream(Payload::from("a"));
+    let second = pool.request_stream(Payload::from("b"));
+    let free = call(&pool).await;
+    for _ in 0..3 {
+        assert_eq!(call(&pool).await, free);
+    }
+    drop(first);
+    drop(second);
+
+    let mut got = vec![];
+    for _ in 0..3 {
+        got.push(call(&pool).await);
+    }
+    got.sort();
+    assert_eq!(got, vec!["0", "1", "2"]);
+}
+
+#[tokio::test]
+async fn test_connect_failed() {
+    let res = Pool::builder()
+        .size(2)
+        .connect(|| async {
+            RSocketFactory::connect()
+                .transport(TcpClientTransport::from("127.0.0.1:6789"))
+                .start()
+                .await
+        })
+        .build()
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_tcp_pool() {
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:7171"))
+            .acceptor(Box::new(move |_setup, _socket| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(EchoRSocket))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let pool = Pool::builder()
+        .size(4)
+        .connect(|| async {
+            RSocketFactory::connect()
+                .transport(TcpClientTransport::from("127.0.0.1:7171"))
+                .start()
+                .await
+        })
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(pool.connections(), 4);
+
+    let requests = (0..100).map(|i| {
+        let pool = pool.clone();
+        async move {
+            let msg = format!("hello {}", i);
+            let res = pool
+                .request_response(Payload::builder().set_data_utf8(&msg).build())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(res.data_utf8(), Some(msg.as_str()));
+        }
+    });
+    futures::future::join_all(requests).await;
+
+    let results: Vec<_> = pool.request_stream(Payload::from("stream")).collect().await;
+    assert!(!results.is_empty());
+    assert_eq!(accepted.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn test_reconnect_idle_connections() {
+    let accepted = Arc::new(AtomicUsize::new(0));
+    let counter = accepted.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:9090"))
+            .acceptor(Box::new(move |_setup, _socket| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(Box::new(EchoRSocket))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let proxy = Proxy::start("127.0.0.1:9091", "127.0.0.1:9090").await;
+
+    let pool = Pool::builder()
+        .size(3)
+        .retry_interval(Duration::from_millis(50))
+        .connect(|| async {
+            RSocketFactory::connect()
+                .transport(TcpClientTransport::from("127.0.0.1:9091"))
+                .start()
+                .await
+        })
+        .build()
+        .await
+        .unwrap();
+    assert_eq!(pool.connections(), 3);
+    assert_eq!(call(&pool).await, "ping");
+
+    // the server drops all the connections while they are idle
+    proxy.drop_connections();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(pool.connections(), 3);
+    assert_eq!(accepted.load(Ordering::SeqCst), 6);
+    for _ in 0..6 {
+        assert_eq!(call(&pool).await, "ping");
+    }
+}
@@ -0,0 +1,198 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use hickory_proto::op::{Message, MessageType};
+use hickory_proto::rr::rdata::{A, SRV};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::serialize::binary::BinEncodable;
+use rsocket_rust::balancer::{Endpoint, LoadBalancer, Resolver, RoundRobin, StaticResolver};
+use rsocket_rust::prelude::*;
+use rsocket_rust::{async_trait, Result};
+use rsocket_rust_transport_tcp::{DnsResolver, FileResolver};
+use tokio::net::UdpSocket;
+
+struct Echo(String);
+
+#[async_trait]
+impl RSocket for Echo {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, _req: Payload) -> Result<Option<Payload>> {
+        Ok(Some(Payload::builder().set_data_utf8(&self.0).build()))
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        Box::pin(futures::stream::once(async move { Ok(req) }))
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        reqs
+    }
+}
+
+/// Resolves once then never again, like resolvers which poll forever.
+struct Forever(Arc<AtomicBool>);
+
+/// Flags when the stream of a resolver is dropped.
+struct Dropped(Arc<AtomicBool>);
+
+impl Drop for Dropped {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Resolver for Forever {
+    fn resolve(&self) -> Flux<Result<Vec<Endpoint>>> {
+        let dropped = Dropped(self.0.clone());
+        let endpoints = stream::once(async { Ok(vec![Endpoint::new("a:1")]) });
+        Box::pin(endpoints.chain(stream::pending()).map(move |it| {
+            let _ = &dropped;
+            it
+        }))
+    }
+}
+
+async fn dial(addr: String) -> Result<Echo> {
+    Ok(Echo(addr))
+}
+
+async fn call(lb: &LoadBalancer) -> String {
+    lb.request_response(Payload::from("ping"))
+        .await
+        .expect("request failed")
+        .expect("empty response")
+        .data_utf8()
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn test_static_resolver() {
+    let lb = LoadBalancer::builder()
+        .strategy(RoundRobin::default())
+        .resolver(StaticResolver::new(vec!["a:1", "b:2"]), dial)
+        .build()
+        .await;
+    assert_eq!(lb.len(), 2);
+    let mut got = vec![call(&lb).await, call(&lb).await];
+    got.sort();
+    assert_eq!(got, vec!["a:1", "b:2"]);
+}
+
+#[tokio::test]
+async fn test_file_resolver() {
+    let path = std::env::temp_dir().join(format!("rsocket-endpoints-{}", std::process::id()));
+    std::fs::write(&path, "# backends\n127.0.0.1:7001 3\n127.0.0.1:7002\n").unwrap();
+
+    let resolver = FileResolver::new(&path).interval(Duration::from_millis(50));
+    let mut results = resolver.resolve();
+    let endpoints = results.next().await.unwrap().unwrap();
+    assert_eq!(
+        endpoints,
+        vec![
+            Endpoint::new("127.0.0.1:7001").with_weight(3),
+            Endpoint::new("127.0.0.1:7002"),
+        ]
+    );
+
+    let lb = LoadBalancer::builder()
+        .resolver(
+            FileResolver::new(&path).interval(Duration::from_millis(50)),
+            dial,
+        )
+        .build()
+        .await;
+    assert_eq!(lb.len(), 2);
+
+    // endpoints which are gone get dropped
+    std::fs::write(&path, "127.0.0.1:7002\n").unwrap();
+    let endpoints = results.next().await.unwrap().unwrap();
+    assert_eq!(endpoints, vec![Endpoint::new("127.0.0.1:7002")]);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(lb.len(), 1);
+    assert_eq!(call(&lb).await, "127.0.0.1:7002");
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(results.next().await.unwrap().is_err());
+}
+
+#[tokio::test]
+async fn test_stop_resolving_on_drop() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let lb = LoadBalancer::builder()
+        .resolver(Forever(dropped.clone()), dial)
+        .build()
+        .await;
+    assert_eq!(call(&lb).await, "a:1");
+    assert!(!dropped.load(Ordering::SeqCst));
+
+    drop(lb);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+// Answers SRV queries for `_rsocket._tcp.example.test.` and A queries for its target.
+async fn serve_dns(socket: UdpSocket) {
+    let mut buf = vec![0u8; 512];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
+        let request = Message::from_vec(&buf[..n]).unwrap();
+        let mut response = Message::new();
+        response
+            .set_id(request.id())
+            .set_message_type(MessageType::Response)
+            .set_op_code(request.op_code())
+            .set_recursion_desired(request.recursion_desired())
+            .set_recursion_available(true);
+        for query in request.queries() {
+            response.add_query(query.clone());
+            let name = query.name().clone();
+            let target = Name::from_ascii("node1.example.test.").unwrap();
+            match query.query_type() {
+                RecordType::SRV
+                    if name == Name::from_ascii("_rsocket._tcp.example.test.").unwrap() =>
+                {
+                    let srv = SRV::new(10, 5, 7878, target);
+                    response.add_answer(Record::from_rdata(name, 60, RData::SRV(srv)));
+                }
+                RecordType::A if name == target => {
+                    let a = A(Ipv4Addr::LOCALHOST);
+                    response.add_answer(Record::from_rdata(name, 60, RData::A(a)));
+                }
+                _ => (),
+            }
+        }
+        socket
+            .send_to(&response.to_bytes().unwrap(), peer)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_dns_resolver() {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let nameserver: SocketAddr = socket.local_addr().unwrap();
+    tokio::spawn(serve_dns(socket));
+
+    let resolver = DnsResolver::srv("_rsocket._tcp.example.test.").nameserver(nameserver);
+    let endpoints = resolver.resolve().next().await.unwrap().unwrap();
+    assert_eq!(
+        endpoints,
+        vec![Endpoint::new("127.0.0.1:7878").with_weight(5)]
+    );
+
+    let resolver = DnsResolver::host("node1.example.test.", 7000).nameserver(nameserver);
+    let endpoints = resolver.resolve().next().await.unwrap().unwrap();
+    assert_eq!(endpoints, vec![Endpoint::new("127.0.0.1:7000")]);
+}
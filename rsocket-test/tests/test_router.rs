@@ -0,0 +1,369 @@
+#[macro_use]
+extern crate serde_derive;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::{MimeType, RoutingMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::{RoutePattern, Router, Variables};
+use rsocket_rust::utils::{EchoRSocket, Writeable};
+use rsocket_rust::Result;
+use rsocket_rust_messaging::{handler, json, Requester};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+
+mod common;
+
+use common::routed;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Student {
+    id: i64,
+    name: String,
+}
+
+fn router(fired: Arc<AtomicUsize>) -> Router {
+    Router::builder()
+        .request_response("echo", |req: Payload, _| async move { Ok(Some(req)) })
+        .fire_and_forget("fire", move |_req: Payload, _| {
+            let fired = fired.clone();
+            async move {
+                fired.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+        .request_stream("echo", |req: Payload, _| -> Flux<Result<Payload>> {
+            Box::pin(stream::iter(vec![Ok(req.clone()), Ok(req)]))
+        })
+        .request_response(
+            "student.get",
+            handler::request_response(json(), |id: i64, _| async move {
+                Ok(Student {
+                    id,
+                    name: format!("student-{}", id),
+                })
+            }),
+        )
+        .request_response(
+            "student.{id}.name",
+            handler::request_response(json(), |prefix: String, vars: Variables| async move {
+                Ok(format!("{}-{}", prefix, vars.get("id").unwrap()))
+            }),
+        )
+        .request_stream(
+            "students",
+            handler::request_stream(json(), |n: i64, _| -> Flux<Result<Student>> {
+                Box::pin(stream::iter((0..n).map(|id| {
+                    Ok(Student {
+                        id,
+                        name: format!("student-{}", id),
+                    })
+                })))
+            }),
+        )
+        .request_channel(
+            "students.rename",
+            handler::request_channel(
+                json(),
+                |students: Flux<Result<Student>>, _| -> Flux<Result<Student>> {
+                    Box::pin(students.map(|it| {
+                        it.map(|mut student| {
+                            student.name = student.name.to_uppercase();
+                            student
+                        })
+                    }))
+                },
+            ),
+        )
+        .build()
+}
+
+fn assert_error<T>(res: Result<T>, expected: fn(&RSocketError) -> bool) {
+    match res {
+        Ok(_) => panic!("should fail"),
+        Err(e) => {
+            let e = e
+                .downcast_ref::<RSocketError>()
+                .expect("not an RSocketError");
+            assert!(expected(e), "unexpected error: {:?}", e);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch() {
+    let fired = Arc::new(AtomicUsize::new(0));
+    let router = router(fired.clone());
+
+    let res = router.request_response(routed("echo", "hello")).await;
+    assert_eq!(Some("hello"), res.unwrap().unwrap().data_utf8());
+
+    let results: Vec<_> = router
+        .request_stream(routed("echo", "hello"))
+        .collect()
+        .await;
+    assert_eq!(2, results.len());
+    assert_eq!(Some("hello"), results[1].as_ref().unwrap().data_utf8());
+
+    router.fire_and_forget(routed("fire", "")).await.unwrap();
+    assert_eq!(1, fired.load(Ordering::SeqCst));
+
+    // no request-response handler for the route
+    assert_error(router.request_response(routed("fire", "")).await, |e| {
+        matches!(e, RSocketError::RequestRejected(_))
+    });
+    assert_error(router.request_response(routed("unknown", "")).await, |e| {
+        matches!(e, RSocketError::RequestRejected(_))
+    });
+    assert_error(router.request_response(Payload::from("hello")).await, |e| {
+        matches!(e, RSocketError::RequestInvalid(_))
+    });
+    let broken = Payload::builder()
+        .set_data_utf8("hello")
+        .set_metadata(b"broken".to_vec())
+        .build();
+    assert_error(router.request_response(broken).await, |e| {
+        matches!(e, RSocketError::RequestInvalid(_))
+    });
+    let mut results = router.request_stream(routed("unknown", ""));
+    assert_error(results.next().await.unwrap(), |e| {
+        matches!(e, RSocketError::RequestRejected(_))
+    });
+    assert!(results.next().await.is_none());
+    let mut results =
+        router.request_channel(Box::pin(stream::iter(vec![Ok(routed("echo", "hello"))])));
+    assert_error(results.next().await.unwrap(), |e| {
+        matches!(e, RSocketError::RequestRejected(_))
+    });
+
+    // typed handlers reject undecodable data
+    assert_error(
+        router.request_response(routed("student.get", "x")).await,
+        |e| matches!(e, RSocketError::RequestInvalid(_)),
+    );
+}
+
+#[tokio::test]
+async fn test_routing_metadata_only() {
+    let router = Router::builder()
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_ROUTING_V0)
+        .request_response("echo", |req: Payload, _| async move { Ok(Some(req)) })
+        .build();
+    let req = Payload::builder()
+        .set_data_utf8("hello")
+        .set_metadata(RoutingMetadata::builder().push_str("echo").build().bytes())
+        .build();
+    let res = router.request_response(req).await.unwrap().unwrap();
+    assert_eq!(Some("hello"), res.data_utf8());
+}
+
+#[tokio::test]
+async fn test_fallback() {
+    let router = Router::builder()
+        .request_response("nothing", |_req: Payload, _| async move { Ok(None) })
+        .fallback(EchoRSocket)
+        .build();
+    let res = router.request_response(routed("nothing", "hello")).await;
+    assert!(res.unwrap().is_none());
+    let res = router.request_response(routed("unknown", "hello")).await;
+    assert_eq!(Some("hello"), res.unwrap().unwrap().data_utf8());
+    let res = router.request_response(Payload::from("hello")).await;
+    assert_eq!(Some("hello"), res.unwrap().unwrap().data_utf8());
+}
+
+#[tokio::test]
+async fn test_serve() {
+    let addr = "127.0.0.1:8282";
+    let fired = Arc::new(AtomicUsize::new(0));
+    let acceptor = router(fired.clone()).acceptor();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .acceptor(acceptor)
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let requester = Requester::builder()
+        .connect_tcp("127.0.0.1", 8282)
+        .build()
+        .await
+        .unwrap();
+
+    let res: Option<Student> = requester
+        .route("student.get")
+        .data(7)
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(
+        Some(Student {
+            id: 7,
+            name: "student-7".to_owned()
+        }),
+        res
+    );
+
+    let res: Vec<Student> = requester
+        .route("students")
+        .data(3)
+        .retrieve_flux()
+        .block()
+        .await
+        .unwrap();
+    assert_eq!(3, res.len());
+    assert_eq!("student-2", res[2].name);
+
+    requester.route("fire").retrieve().await.unwrap();
+
+    let res: Option<String> = requester
+        .route_with("student.{id}.name", &[&42])
+        .data("student")
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap();
+    assert_eq!(Some("student-42".to_owned()), res);
+
+    let res: Result<Option<String>> = requester
+        .route_with("student.{id}.name", &[])
+        .retrieve_mono()
+        .await
+        .decode();
+    assert!(res.is_err());
+    let long = "x".repeat(256);
+    let res: Result<Option<String>> = requester
+        .route_with("student.{id}.name", &[&long])
+        .retrieve_mono()
+        .await
+        .decode();
+    assert!(res.is_err());
+
+    let res: Result<Option<Student>> = requester
+        .route("unknown")
+        .data(7)
+        .retrieve_mono()
+        .await
+        .decode();
+    assert_error(res, |e| matches!(e, RSocketError::RequestRejected(_)));
+
+    // channels are not supported by the requester
+    let cli = RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+        .start()
+        .await
+        .unwrap();
+    let student = |name: &str| Ok(routed("students.rename", name));
+    let reqs = vec![
+        student(r#"{"id":1,"name":"foo"}"#),
+        student(r#"{"id":2,"name":"bar"}"#),
+    ];
+    let results: Vec<_> = cli
+        .request_channel(Box::pin(stream::iter(reqs)))
+        .collect()
+        .await;
+    assert_eq!(2, results.len());
+    assert_eq!(
+        Some(r#"{"id":2,"name":"BAR"}"#),
+        results[1].as_ref().unwrap().data_utf8()
+    );
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(1, fired.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_route_pattern() {
+    let pattern = RoutePattern::parse("user.{id}.orders").unwrap();
+    let vars = pattern.matches("user.42.orders").unwrap();
+    assert_eq!(Some("42"), vars.get("id"));
+    assert_eq!(1, vars.len());
+    assert!(pattern.matches("user.42").is_none());
+    assert!(pattern.matches("user.42.orders.1").is_none());
+    assert!(pattern.matches("user..orders").is_none());
+    assert_eq!("user.7.orders", pattern.expand(&[&7]).unwrap());
+    assert!(pattern.expand(&[]).is_err());
+    assert!(pattern.expand(&[&1, &2]).is_err());
+    // arguments must fill exactly one segment
+    assert!(pattern.expand(&[&""]).is_err());
+    assert!(pattern.expand(&[&"7.cancel"]).is_err());
+
+    let pattern = RoutePattern::parse("metrics.*").unwrap();
+    assert!(pattern.matches("metrics.cpu").unwrap().is_empty());
+    assert!(pattern.matches("metrics").is_none());
+    assert!(pattern.matches("metrics.cpu.load").is_none());
+    assert!(pattern.expand(&[]).is_err());
+
+    let pattern = RoutePattern::parse("metrics.**.{name}").unwrap();
+    assert_eq!(
+        Some("cpu"),
+        pattern.matches("metrics.cpu").unwrap().get("name")
+    );
+    let vars = pattern.matches("metrics.host.a.load").unwrap();
+    assert_eq!(vec![("name", "load")], vars.iter().collect::<Vec<_>>());
+    assert!(pattern.matches("metrics").is_none());
+
+    let pattern = RoutePattern::parse("a.{x}.**.{y}.b").unwrap();
+    let vars = pattern.matches("a.1.2.3.4.b").unwrap();
+    assert_eq!(Some("1"), vars.get("x"));
+    assert_eq!(Some("4"), vars.get("y"));
+    assert_eq!(vec!["x", "y"], pattern.variables().collect::<Vec<_>>());
+
+    assert!(RoutePattern::parse("echo").unwrap().is_literal());
+    for bad in &[
+        "",
+        "a..b",
+        "a.{}",
+        "a.{x}.{x}",
+        "a.b*",
+        "a.{x",
+        "a.x}",
+        "**.**",
+    ] {
+        assert!(RoutePattern::parse(bad).is_err(), "should reject {}", bad);
+    }
+}
+
+#[tokio::test]
+async fn test_precedence() {
+    let label = |label: &'static str| {
+        move |_req: Payload, vars: Variables| async move {
+            let data = format!("{}{:?}", label, vars.iter().collect::<Vec<_>>());
+            Ok(Some(Payload::builder().set_data_utf8(&data).build()))
+        }
+    };
+    let router = Router::builder()
+        .request_response("**", label("any"))
+        .request_response("user.**", label("user.**"))
+        .request_response("user.{id}", label("user.{id}"))
+        .request_response("user.admin", label("user.admin"))
+        .request_response("user.*", label("user.*"))
+        .request_stream("user.**", |req: Payload, _| -> Flux<Result<Payload>> {
+            Box::pin(stream::iter(Some(Ok(req))))
+        })
+        .build();
+    let dispatch = |route: &'static str| {
+        let router = router.clone();
+        async move {
+            let res = router.request_response(routed(route, "")).await.unwrap();
+            res.unwrap().data_utf8().unwrap().to_owned()
+        }
+    };
+    assert_eq!("user.admin[]", dispatch("user.admin").await);
+    // equally specific patterns keep the order of registration
+    assert_eq!(r#"user.{id}[("id", "1")]"#, dispatch("user.1").await);
+    assert_eq!("user.**[]", dispatch("user.1.orders").await);
+    assert_eq!("user.**[]", dispatch("user").await);
+    assert_eq!("any[]", dispatch("order.1").await);
+
+    // the most specific pattern with a handler of the interaction model
+    let mut results = router.request_stream(routed("user.1", "hello"));
+    let res = results.next().await.unwrap().unwrap();
+    assert_eq!(Some("hello"), res.data_utf8());
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream;
+use rsocket_rust::extension::{CompositeMetadata, RoutingMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::Writeable;
+use rsocket_rust::Result;
+use rsocket_rust_messaging::codec::Codecs;
+use rsocket_rust_messaging::Requester;
+use rsocket_rust_transport_tcp::TcpServerTransport;
+use tokio::sync::mpsc;
+
+mod helloworld {
+    include!(concat!(env!("OUT_DIR"), "/helloworld.rs"));
+}
+
+use helloworld::{Greeter, GreeterClient, GreeterServer, HelloReply, HelloRequest};
+
+struct HelloGreeter {
+    notified: mpsc::UnboundedSender<String>,
+}
+
+#[rsocket_rust::async_trait]
+impl Greeter for HelloGreeter {
+    async fn say_hello(&self, req: HelloRequest) -> Result<HelloReply> {
+        Ok(reply(&req.name))
+    }
+
+    fn say_hellos(&self, req: HelloRequest) -> Flux<Result<HelloReply>> {
+        let replies: Vec<_> = (0..3)
+            .map(|i| Ok(reply(&format!("{}-{}", req.name, i))))
+            .collect();
+        Box::pin(stream::iter(replies))
+    }
+
+    async fn count_names(&self, mut reqs: Flux<Result<HelloRequest>>) -> Result<HelloReply> {
+        let mut n = 0;
+        while let Some(req) = reqs.next().await {
+            req?;
+            n += 1;
+        }
+        Ok(reply(&n.to_string()))
+    }
+
+    fn chat(&self, reqs: Flux<Result<HelloRequest>>) -> Flux<Result<HelloReply>> {
+        Box::pin(reqs.map(|it| it.map(|req| reply(&req.name))))
+    }
+
+    async fn notify(&self, req: HelloRequest) -> Result<()> {
+        self.notified.send(req.name).unwrap();
+        Ok(())
+    }
+}
+
+fn reply(name: &str) -> HelloReply {
+    HelloReply {
+        message: format!("hello {}", name),
+    }
+}
+
+fn request(name: &str) -> HelloRequest {
+    HelloRequest { name: name.into() }
+}
+
+#[tokio::test]
+async fn test_rpc() {
+    let (notified, mut notifications) = mpsc::unbounded_channel();
+    let server = GreeterServer::new(HelloGreeter { notified }).codecs(Codecs::default());
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8787"))
+            .acceptor(Box::new(move |_setup, _socket| {
+                Ok(Box::new(server.clone()))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let client = GreeterClient::new(
+        Requester::builder()
+            .connect_tcp("127.0.0.1", 8787)
+            .build()
+            .await
+            .unwrap(),
+    );
+
+    let res = client.say_hello(request("tom")).await.unwrap();
+    assert_eq!("hello tom", res.message);
+
+    let res: Vec<String> = client
+        .say_hellos(request("tom"))
+        .map(|it| it.unwrap().message)
+        .collect()
+        .await;
+    assert_eq!(vec!["hello tom-0", "hello tom-1", "hello tom-2"], res);
+
+    let names = stream::iter(vec![request("tom"), request("jerry")]);
+    let res = client.count_names(names).await.unwrap();
+    assert_eq!("hello 2", res.message);
+    let res = client.count_names(stream::empty()).await.unwrap();
+    assert_eq!("hello 0", res.message);
+    // an empty message has no data, but isn't taken for the request alone
+    let names = stream::iter(vec![HelloRequest::default()]);
+    let res = client.count_names(names).await.unwrap();
+    assert_eq!("hello 1", res.message);
+
+    let names = stream::iter(vec![request("tom"), request("jerry")]);
+    let res: Vec<String> = client
+        .chat(names)
+        .map(|it| it.unwrap().message)
+        .collect()
+        .await;
+    assert_eq!(vec!["hello tom", "hello jerry"], res);
+
+    client.notify(request("tom")).await.unwrap();
+    let res = tokio::time::timeout(Duration::from_secs(3), notifications.recv()).await;
+    assert_eq!(Some("tom".to_owned()), res.unwrap());
+}
+
+#[tokio::test]
+async fn test_rpc_routes() {
+    let (notified, _notifications) = mpsc::unbounded_channel();
+    let server = GreeterServer::from_arc(Arc::new(HelloGreeter { notified }));
+
+    // routes of rsocket-rpc-java: {package}.{Service}.{Method}
+    let routed = |route: &str| {
+        let mut composite = CompositeMetadata::default();
+        composite.insert(&RoutingMetadata::builder().push_str(route).build());
+        Payload::builder()
+            .set_data(prost::Message::encode_to_vec(&request("tom")))
+            .set_metadata(composite.bytes())
+            .build()
+    };
+    let res = server
+        .request_response(routed("helloworld.Greeter.SayHello"))
+        .await
+        .unwrap()
+        .unwrap();
+    let res: HelloReply = prost::Message::decode(res.data().unwrap().clone()).unwrap();
+    assert_eq!("hello tom", res.message);
+
+    // methods are routed within their interaction models
+    assert!(server
+        .request_response(routed("helloworld.Greeter.SayHellos"))
+        .await
+        .is_err());
+    assert!(server
+        .request_response(routed("helloworld.Greeter.Missing"))
+        .await
+        .is_err());
+    let res: Vec<_> = server
+        .request_stream(routed("helloworld.Greeter.SayHello"))
+        .collect()
+        .await;
+    assert!(res[0].is_err());
+
+    // like other implementations, a first input without data is still an input
+    let first = Payload::new(None, routed("helloworld.Greeter.CountNames").split().1);
+    let res: Vec<_> = server
+        .request_channel(Box::pin(stream::iter(vec![Ok(first)])))
+        .collect()
+        .await;
+    let res: HelloReply =
+        prost::Message::decode(res[0].as_ref().unwrap().data().unwrap().clone()).unwrap();
+    assert_eq!("hello 1", res.message);
+}
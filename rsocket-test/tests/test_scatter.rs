@@ -0,0 +1,194 @@
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::balancer::{Policy, ScatterGather};
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::prelude::*;
+use rsocket_rust::{async_trait, Result};
+
+/// Responds its name after a delay, or fails if it has no name.
+struct Backend {
+    name: Option<&'static str>,
+    delay: Duration,
+}
+
+impl Backend {
+    fn boxed(name: &'static str, delay_millis: u64) -> Box<dyn RSocket> {
+        Box::new(Backend {
+            name: Some(name),
+            delay: Duration::from_millis(delay_millis),
+        })
+    }
+
+    fn failing(delay_millis: u64) -> Box<dyn RSocket> {
+        Box::new(Backend {
+            name: None,
+            delay: Duration::from_millis(delay_millis),
+        })
+    }
+
+    fn respond(&self) -> Result<Payload> {
+        match self.name {
+            Some(name) => Ok(Payload::builder().set_data_utf8(name).build()),
+            None => Err(RSocketError::ApplicationException("unavailable".into()).into()),
+        }
+    }
+}
+
+#[async_trait]
+impl RSocket for Backend {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, _req: Payload) -> Result<Option<Payload>> {
+        tokio::time::sleep(self.delay).await;
+        self.respond().map(Some)
+    }
+
+    fn request_stream(&self, _req: Payload) -> Flux<Result<Payload>> {
+        // emits its name right away, then again after the delay
+        let first = self.name.map(|name| Ok(Payload::from(name)));
+        let delay = self.delay;
+        let last = self.respond();
+        let tail = stream::once(async move {
+            tokio::time::sleep(delay).await;
+            last
+        });
+        Box::pin(stream::iter(first).chain(tail))
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        reqs
+    }
+}
+
+fn names(payloads: &[Payload]) -> Vec<&str> {
+    payloads.iter().map(|it| it.data_utf8().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn test_request_response() {
+    let sg = ScatterGather::builder()
+        .member(Backend::boxed("a", 50))
+        .member(Backend::failing(10))
+        .member(Backend::boxed("c", 500))
+        .timeout(Duration::from_millis(200))
+        .build();
+    assert_eq!(3, sg.len());
+
+    // all members are awaited, until the first failure
+    let gathered = sg.request_response(Payload::from("ping")).await;
+    assert!(!gathered.is_satisfied());
+    assert!(gathered.successes().is_empty());
+    assert_eq!(1, gathered.failures()[0].member());
+    assert!(!gathered.failures()[0].is_timeout());
+    assert_eq!(&[0, 2], gathered.cancelled());
+    let e = gathered.into_result().unwrap_err().to_string();
+    assert!(e.contains("member 1"), "{}", e);
+
+    let sg = ScatterGather::builder()
+        .member(Backend::boxed("a", 50))
+        .member(Backend::failing(10))
+        .member(Backend::boxed("c", 500))
+        .timeout(Duration::from_millis(200))
+        .policy(Policy::Quorum(2))
+        .build();
+    // the slow member times out, so the quorum can't be reached
+    let gathered = sg.request_response(Payload::from("ping")).await;
+    assert!(!gathered.is_satisfied());
+    assert_eq!(0, gathered.successes()[0].0);
+    assert_eq!(2, gathered.failures().len());
+    assert!(gathered.failures()[1].is_timeout());
+    assert!(gathered.cancelled().is_empty());
+}
+
+#[tokio::test]
+async fn test_policies() {
+    let members = || {
+        vec![
+            Backend::boxed("a", 150),
+            Backend::failing(10),
+            Backend::boxed("c", 50),
+            Backend::boxed("d", 100),
+        ]
+    };
+    let build = |policy| {
+        members()
+            .into_iter()
+            .fold(ScatterGather::builder(), |sg, it| sg.member(it))
+            .policy(policy)
+            .build()
+    };
+
+    let gathered = build(Policy::FirstSuccess)
+        .request_response(Payload::from("ping"))
+        .await;
+    assert!(gathered.is_satisfied());
+    assert_eq!(1, gathered.failures().len());
+    assert_eq!(&[0, 3], gathered.cancelled());
+    let res = gathered.into_result().unwrap();
+    assert_eq!(Some("c"), res[0].as_ref().unwrap().data_utf8());
+
+    let gathered = build(Policy::Quorum(2))
+        .request_response(Payload::from("ping"))
+        .await;
+    let res: Vec<_> = gathered
+        .into_successes()
+        .into_iter()
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(vec![2, 3], res);
+
+    // quorums larger than the members are never satisfied
+    let gathered = build(Policy::Quorum(5))
+        .request_response(Payload::from("ping"))
+        .await;
+    assert!(!gathered.is_satisfied());
+    assert_eq!(4, gathered.cancelled().len());
+}
+
+#[tokio::test]
+async fn test_request_stream() {
+    let sg = ScatterGather::builder()
+        .member(Backend::boxed("a", 50))
+        .member(Backend::boxed("b", 500))
+        .member(Backend::failing(10))
+        .timeout(Duration::from_millis(200))
+        .policy(Policy::FirstSuccess)
+        .build();
+
+    let gathered = sg.request_stream(Payload::from("ping")).await;
+    assert!(gathered.is_satisfied());
+    assert_eq!(2, gathered.failures()[0].member());
+    assert_eq!(&[1], gathered.cancelled());
+    let res = gathered.into_result().unwrap();
+    assert_eq!(vec!["a", "a"], names(&res[0]));
+
+    // payloads are merged until the first member completes
+    let res: Vec<_> = sg
+        .request_stream_merged(Payload::from("ping"))
+        .map(|it| it.unwrap())
+        .collect()
+        .await;
+    let mut res = names(&res);
+    res.sort_unstable();
+    assert_eq!(vec!["a", "a", "b"], res);
+
+    let sg = ScatterGather::builder()
+        .member(Backend::boxed("a", 50))
+        .member(Backend::boxed("b", 500))
+        .timeout(Duration::from_millis(200))
+        .build();
+    let res: Vec<_> = sg
+        .request_stream_merged(Payload::from("ping"))
+        .collect()
+        .await;
+    assert_eq!(4, res.len());
+    let e = res[3].as_ref().unwrap_err().to_string();
+    assert!(e.contains("member 1: timeout"), "{}", e);
+}
@@ -0,0 +1,159 @@
+use std::time::Duration;
+
+use futures::{stream, StreamExt};
+use rsocket_rust::error::RSocketError;
+use rsocket_rust::extension::{AuthMetadata, CompositeMetadata, MimeType, RoutingMetadata};
+use rsocket_rust::prelude::*;
+use rsocket_rust::security::{Principal, Security};
+use rsocket_rust::utils::{EchoRSocket, Writeable};
+use rsocket_rust::{Client, Result};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+use tokio::sync::mpsc;
+
+fn metadata(route: Option<&str>, auth: Option<(&str, &str)>) -> Vec<u8> {
+    let mut composite = CompositeMetadata::default();
+    if let Some(route) = route {
+        let routing = RoutingMetadata::builder().push_str(route).build();
+        composite = CompositeMetadata::builder()
+            .push(MimeType::MESSAGE_X_RSOCKET_ROUTING_V0, routing.bytes())
+            .build();
+    }
+    if let Some((username, password)) = auth {
+        AuthMetadata::builder()
+            .simple(username, password)
+            .build()
+            .inject(&mut composite);
+    }
+    composite.bytes()
+}
+
+fn request(route: &str, auth: Option<(&str, &str)>) -> Payload {
+    Payload::builder()
+        .set_data_utf8("hello")
+        .set_metadata(metadata(Some(route), auth))
+        .build()
+}
+
+async fn connect(auth: Option<(&str, &str)>) -> Result<Client> {
+    RSocketFactory::connect()
+        .transport(TcpClientTransport::from("127.0.0.1:7979"))
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+        .setup(
+            Payload::builder()
+                .set_metadata(metadata(None, auth))
+                .build(),
+        )
+        .start()
+        .await
+}
+
+fn is_rejected(res: Result<Option<Payload>>) -> bool {
+    matches!(
+        res.map_err(|e| e.downcast::<RSocketError>()),
+        Err(Ok(RSocketError::RequestRejected(_)))
+    )
+}
+
+#[tokio::test]
+async fn test_security() {
+    let security = Security::builder()
+        .authenticator(|auth: AuthMetadata| async move {
+            match (auth.get_username(), auth.get_password()) {
+                (Some("alice"), Some("secret")) => Ok(Principal::new("alice", ["user"])),
+                (Some("root"), Some("toor")) => Ok(Principal::new("root", ["user", "admin"])),
+                _ => Err(RSocketError::WithDescription("bad credentials".into()).into()),
+            }
+        })
+        .rule("admin.**", "admin")
+        .rule("*.write", "user")
+        .build();
+    let (accepted, mut principals) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:7979"))
+            .interceptor(security)
+            .acceptor(Box::new(move |setup, _socket| {
+                let principal = setup.connection().and_then(|it| it.get::<Principal>());
+                accepted.send(principal).unwrap();
+                Ok(Box::new(EchoRSocket))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    // rejected setups
+    for auth in [None, Some(("alice", "guess"))] {
+        let cli = connect(auth).await.unwrap();
+        let res = cli.request_response(request("public.echo", None)).await;
+        assert!(res.is_err());
+    }
+
+    let cli = connect(Some(("alice", "secret"))).await.unwrap();
+    cli.request_response(request("public.echo", None))
+        .await
+        .unwrap();
+    // the acceptor knows the principal of the connection
+    let principal = principals.recv().await.unwrap().unwrap();
+    assert_eq!("alice", principal.name());
+    assert!(principal.has_role("user"));
+    for route in ["public.echo", "orders.write"] {
+        let res = cli.request_response(request(route, None)).await;
+        assert!(res.unwrap().is_some(), "{}", route);
+    }
+    // rules are route patterns, like the ones of routers
+    for route in ["admin.stats", "admin.users.list"] {
+        let res = cli.request_response(request(route, None)).await;
+        assert!(is_rejected(res), "{}", route);
+    }
+    let res = cli.request_response(request("orders.x.write", None)).await;
+    assert!(res.unwrap().is_some());
+
+    // requests without a route can't bypass the rules
+    let unrouted = [
+        Payload::builder()
+            .set_data_utf8("hello")
+            .set_metadata(metadata(None, None))
+            .build(),
+        Payload::from("hello"),
+    ];
+    for req in unrouted {
+        assert!(is_rejected(cli.request_response(req).await));
+    }
+
+    // credentials of a request take precedence
+    let root = Some(("root", "toor"));
+    let res = cli.request_response(request("admin.stats", root)).await;
+    assert!(res.unwrap().is_some());
+    let res = cli
+        .request_response(request("public.echo", Some(("root", "guess"))))
+        .await;
+    assert!(is_rejected(res));
+
+    let results: Vec<_> = cli
+        .request_stream(request("admin.stats", None))
+        .collect()
+        .await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+    let results: Vec<_> = cli
+        .request_stream(request("admin.stats", root))
+        .collect()
+        .await;
+    assert!(results.iter().all(|it| it.is_ok()));
+
+    let reqs = stream::iter(vec![
+        Ok(request("admin.feed", None)),
+        Ok(Payload::from("next")),
+    ]);
+    let results: Vec<_> = cli.request_channel(Box::pin(reqs)).collect().await;
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_err());
+    let reqs = stream::iter(vec![
+        Ok(request("admin.feed", root)),
+        Ok(Payload::from("next")),
+    ]);
+    let results: Vec<_> = cli.request_channel(Box::pin(reqs)).collect().await;
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|it| it.is_ok()));
+}
@@ -0,0 +1,222 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::StreamExt;
+use rsocket_rust::extension::MimeType;
+use rsocket_rust::prelude::*;
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust::{async_trait, Result};
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+
+mod common;
+
+use common::routed;
+
+#[derive(Debug, Clone, Default)]
+struct Fields(Vec<(String, String)>);
+
+impl Fields {
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_owned(), value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .push((field.name().to_owned(), format!("{:?}", value)));
+    }
+}
+
+type Scope = Vec<(&'static str, Fields)>;
+
+/// Records every span and the scope of every event logged by this test.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<(&'static str, Id)>>>,
+    closed: Arc<Mutex<Vec<(&'static str, Fields)>>>,
+    events: Arc<Mutex<Vec<(String, Scope)>>>,
+}
+
+impl<S> Layer<S> for Recorder
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        let span = ctx.span(id).unwrap();
+        span.extensions_mut().insert(fields);
+        self.spans.lock().unwrap().push((span.name(), id.clone()));
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        values.record(extensions.get_mut::<Fields>().unwrap());
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let fields = span.extensions().get::<Fields>().unwrap().clone();
+        self.closed.lock().unwrap().push((span.name(), fields));
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != module_path!() {
+            return;
+        }
+        let mut message = Fields::default();
+        event.record(&mut message);
+        let scope = ctx
+            .event_scope(event)
+            .map(|scope| {
+                scope
+                    .map(|span| {
+                        (
+                            span.name(),
+                            span.extensions().get::<Fields>().unwrap().clone(),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let message = message.get("message").unwrap_or_default().to_owned();
+        self.events.lock().unwrap().push((message, scope));
+    }
+}
+
+/// Logs in its handlers, which should nest into the spans of the stream and the connection.
+struct Traced;
+
+#[async_trait]
+impl RSocket for Traced {
+    async fn metadata_push(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn fire_and_forget(&self, _req: Payload) -> Result<()> {
+        Ok(())
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        tokio::task::yield_now().await;
+        tracing::info!("handle request_response");
+        EchoRSocket.request_response(req).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        Box::pin(async_stream::stream! {
+            tracing::info!("handle request_stream");
+            yield Ok(req);
+        })
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        reqs
+    }
+}
+
+fn find<'a>(scope: &'a Scope, name: &str) -> &'a Fields {
+    &scope
+        .iter()
+        .find(|(it, _)| *it == name)
+        .unwrap_or_else(|| panic!("missing span {}", name))
+        .1
+}
+
+#[tokio::test]
+async fn test_tracing() {
+    let recorder = Recorder::default();
+    tracing::subscriber::set_global_default(tracing_subscriber::registry().with(recorder.clone()))
+        .unwrap();
+
+    let addr = "127.0.0.1:7575";
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(addr))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(Traced))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let cli = RSocketFactory::connect()
+        .transport(TcpClientTransport::from(addr))
+        .data_mime_type("text/plain")
+        .metadata_mime_type(MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0)
+        .start()
+        .await
+        .unwrap();
+
+    let res = cli.request_response(routed("echo", "hello")).await.unwrap();
+    assert!(res.is_some());
+    let results: Vec<_> = cli.request_stream(routed("feed", "hello")).collect().await;
+    assert_eq!(results.len(), 1);
+    let reqs = futures::stream::iter(vec![
+        Ok(routed("chat", "hello")),
+        Ok(routed("ignored", "hello")),
+    ]);
+    let results: Vec<_> = cli.request_channel(Box::pin(reqs)).collect().await;
+    assert_eq!(results.len(), 2);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let events = recorder.events.lock().unwrap().clone();
+    assert_eq!(events.len(), 2);
+    for ((message, scope), (interaction, route)) in events
+        .iter()
+        .zip([("request_response", "echo"), ("request_stream", "feed")])
+    {
+        assert_eq!(message, &format!("handle {}", interaction));
+
+        let stream = find(scope, "rsocket.stream");
+        assert_eq!(stream.get("interaction"), Some(interaction));
+        assert_eq!(stream.get("role"), Some("responder"));
+        assert_eq!(stream.get("route"), Some(route));
+        assert!(stream.get("stream_id").is_some());
+
+        let connection = find(scope, "rsocket.connection");
+        assert_eq!(connection.get("side"), Some("server"));
+        assert!(connection
+            .get("peer_addr")
+            .unwrap()
+            .starts_with("127.0.0.1:"));
+        assert_eq!(connection.get("data_mime_type"), Some("text/plain"));
+        assert_eq!(
+            connection.get("metadata_mime_type"),
+            Some("message/x.rsocket.composite-metadata.v0")
+        );
+    }
+
+    // the requester side gets a span per stream as well
+    let spans = recorder.spans.lock().unwrap();
+    let streams = spans
+        .iter()
+        .filter(|(name, _)| *name == "rsocket.stream")
+        .count();
+    assert_eq!(streams, 6);
+
+    // the route of a channel is recorded once its first payload is sent
+    let closed = recorder.closed.lock().unwrap();
+    let channel = closed
+        .iter()
+        .map(|(_, fields)| fields)
+        .find(|it| {
+            it.get("interaction") == Some("request_channel") && it.get("role") == Some("requester")
+        })
+        .unwrap();
+    assert_eq!(channel.get("route"), Some("chat"));
+}
@@ -0,0 +1,166 @@
+use bytes::BytesMut;
+use rsocket_rust::extension::{CompositeMetadata, MimeType, TraceContext, TracingMetadata};
+use rsocket_rust::utils::Writeable;
+
+fn roundtrip(composite: &CompositeMetadata) -> CompositeMetadata {
+    CompositeMetadata::decode(&mut BytesMut::from(&composite.bytes()[..])).unwrap()
+}
+
+#[test]
+fn zipkin_codec() {
+    let m = TracingMetadata::builder()
+        .trace_id(0x0102030405060708)
+        .span_id(0x1112131415161718)
+        .parent_id(0x2122232425262728)
+        .sampled(true)
+        .build();
+    let mut bf = BytesMut::new();
+    m.write_to(&mut bf);
+    assert_eq!(
+        hex::encode(&bf),
+        "a4010203040506070811121314151617182122232425262728"
+    );
+    assert_eq!(bf.len(), m.len());
+    assert_eq!(TracingMetadata::decode(&mut bf).unwrap(), m);
+
+    // 128 bit trace id
+    let m = TracingMetadata::builder()
+        .trace_id_high(0xAA)
+        .trace_id(1)
+        .span_id(2)
+        .debug(true)
+        .build();
+    let mut bf = BytesMut::new();
+    m.write_to(&mut bf);
+    assert_eq!(bf[0], 0b1100_1000);
+    assert_eq!(bf.len(), 25);
+    let decoded = TracingMetadata::decode(&mut bf).unwrap();
+    assert_eq!(decoded.get_trace_id_high(), Some(0xAA));
+    assert_eq!(decoded.get_parent_id(), None);
+    assert_eq!(decoded.is_sampled(), None);
+    assert!(decoded.is_debug());
+
+    // 128 bit trace id whose low bits are zero
+    let m = TracingMetadata::builder()
+        .trace_id_high(0xAA)
+        .span_id(2)
+        .build();
+    assert!(m.has_ids());
+    let mut bf = BytesMut::new();
+    m.write_to(&mut bf);
+    assert_eq!(bf.len(), 25);
+    assert_eq!(TracingMetadata::decode(&mut bf).unwrap(), m);
+
+    // sampling decision only
+    let m = TracingMetadata::builder().sampled(false).build();
+    assert_eq!(m.bytes(), vec![0b0001_0000]);
+    let decoded = TracingMetadata::decode(&mut BytesMut::from(&m.bytes()[..])).unwrap();
+    assert!(!decoded.has_ids());
+    assert_eq!(decoded.is_sampled(), Some(false));
+
+    let mut truncated = BytesMut::from(&[0x80u8, 0x01, 0x02][..]);
+    assert!(TracingMetadata::decode(&mut truncated).is_err());
+}
+
+#[test]
+fn zipkin_composite() {
+    let m = TracingMetadata::builder()
+        .trace_id(7)
+        .span_id(8)
+        .sampled(true)
+        .build();
+    let mut composite = CompositeMetadata::builder()
+        .push(MimeType::TEXT_PLAIN, "hello")
+        .build();
+    assert_eq!(TracingMetadata::extract(&composite).unwrap(), None);
+    m.inject(&mut composite);
+    let composite = roundtrip(&composite);
+    assert_eq!(TracingMetadata::extract(&composite).unwrap(), Some(m));
+}
+
+#[test]
+fn trace_context() {
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let ctx = TraceContext::parse(traceparent, Some("congo=t61rcWkgMzE")).unwrap();
+    assert_eq!(ctx.get_version(), 0);
+    assert_eq!(ctx.get_trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+    assert_eq!(ctx.get_parent_id(), 0x00f067aa0ba902b7);
+    assert!(ctx.is_sampled());
+    assert_eq!(ctx.get_state(), Some("congo=t61rcWkgMzE"));
+    assert_eq!(ctx.to_string(), traceparent);
+
+    let built = TraceContext::builder()
+        .trace_id(0x4bf92f3577b34da6a3ce929d0e0e4736)
+        .parent_id(0x00f067aa0ba902b7)
+        .sampled(true)
+        .state("congo=t61rcWkgMzE")
+        .build();
+    assert_eq!(built, ctx);
+
+    for invalid in [
+        "",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        "00-4bf92f3577b34da6a3ce929d0e0e473g-00f067aa0ba902b7-01",
+    ] {
+        assert!(TraceContext::parse(invalid, None).is_err(), "{}", invalid);
+    }
+    // later versions may carry more fields
+    assert!(TraceContext::parse(
+        "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        None
+    )
+    .is_ok());
+}
+
+#[test]
+fn trace_context_composite() {
+    let ctx = TraceContext::builder()
+        .trace_id(1)
+        .parent_id(2)
+        .state("rojo=00f067aa0ba902b7")
+        .build();
+    let mut composite = CompositeMetadata::default();
+    assert_eq!(TraceContext::extract(&composite).unwrap(), None);
+    ctx.inject(&mut composite);
+    let composite = roundtrip(&composite);
+    let mimes: Vec<_> = composite
+        .iter()
+        .map(|it| it.get_mime_type().to_string())
+        .collect();
+    assert_eq!(mimes, vec!["traceparent", "tracestate"]);
+    assert_eq!(
+        composite.iter().next().unwrap().get_metadata_utf8(),
+        Some("00-00000000000000000000000000000001-0000000000000002-00")
+    );
+    let extracted = TraceContext::extract(&composite).unwrap().unwrap();
+    assert_eq!(extracted, ctx);
+    assert!(!extracted.is_sampled());
+}
+
+#[test]
+fn trace_context_reinject() {
+    let upstream = TraceContext::builder()
+        .trace_id(1)
+        .parent_id(2)
+        .state("rojo=00f067aa0ba902b7")
+        .build();
+    let mut composite = CompositeMetadata::builder()
+        .push(MimeType::TEXT_PLAIN, "hello")
+        .build();
+    upstream.inject(&mut composite);
+    // forwards the request in a new span without state
+    let downstream = TraceContext::builder().trace_id(1).parent_id(3).build();
+    downstream.inject(&mut composite);
+    let composite = roundtrip(&composite);
+    let mimes: Vec<_> = composite
+        .iter()
+        .map(|it| it.get_mime_type().to_string())
+        .collect();
+    assert_eq!(mimes, vec!["text/plain", "traceparent"]);
+    assert_eq!(TraceContext::extract(&composite).unwrap(), Some(downstream));
+}
@@ -0,0 +1,177 @@
+use std::io::{Cursor, SeekFrom};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use futures::StreamExt;
+use rsocket_rust::prelude::*;
+use rsocket_rust::router::Router;
+use rsocket_rust_messaging::transfer::{self, Manifest};
+use rsocket_rust_messaging::{Requester, Transfer};
+use rsocket_rust_transport_tcp::TcpServerTransport;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncSeekExt;
+
+fn blob(size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+fn router(dir: PathBuf) -> Router {
+    let uploads = dir.clone();
+    let transfer = Transfer::default().chunk_size(4096);
+    Router::builder()
+        .request_channel(
+            "files.upload",
+            transfer.accept(move |manifest: Manifest, _| {
+                let path = uploads.join(manifest.name());
+                async move {
+                    let mut file = OpenOptions::new()
+                        .create(true)
+                        .truncate(false)
+                        .write(true)
+                        .open(path)
+                        .await?;
+                    file.seek(SeekFrom::Start(manifest.offset())).await?;
+                    Ok(file)
+                }
+            }),
+        )
+        .request_stream(
+            "files.download",
+            transfer.serve(move |manifest: Manifest, _| {
+                let path = dir.join(manifest.name());
+                async move {
+                    let mut file = File::open(path).await?;
+                    let size = file.metadata().await?.len();
+                    file.seek(SeekFrom::Start(manifest.offset())).await?;
+                    Ok((manifest.set_size(size), file))
+                }
+            }),
+        )
+        .build()
+}
+
+#[tokio::test]
+async fn test_upload_and_download() {
+    let dir = std::env::temp_dir().join(format!("rsocket-transfer-{}", std::process::id()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let served = dir.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from("127.0.0.1:8888"))
+            .acceptor(Box::new(move |_setup, _socket| {
+                Ok(Box::new(router(served.clone())))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let requester = Requester::builder()
+        .connect_tcp("127.0.0.1", 8888)
+        .build()
+        .await
+        .unwrap();
+    let data = blob(300_000);
+    let manifest = Manifest::new("a.bin").set_size(data.len() as u64);
+
+    let receipt = Transfer::default()
+        .chunk_size(8192)
+        .window(4)
+        .upload(
+            requester.route("files.upload"),
+            manifest.clone(),
+            Cursor::new(data.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!("a.bin", receipt.name());
+    assert_eq!(300_000, receipt.length());
+    assert!(receipt.checksum().starts_with("sha256:"));
+    assert_eq!(data, tokio::fs::read(dir.join("a.bin")).await.unwrap());
+
+    let mut downloaded = vec![];
+    let receipt = Transfer::default()
+        .download(
+            requester.route("files.download"),
+            Manifest::new("a.bin"),
+            &mut downloaded,
+        )
+        .await
+        .unwrap();
+    assert_eq!(300_000, receipt.length());
+    assert_eq!(data, downloaded);
+
+    // resumes both ways from an offset
+    let receipt = Transfer::default()
+        .upload(
+            requester.route("files.upload"),
+            Manifest::new("b.bin").set_size(1000).set_offset(600),
+            Cursor::new(data[600..1000].to_vec()),
+        )
+        .await
+        .unwrap();
+    assert_eq!((600, 400), (receipt.offset(), receipt.length()));
+
+    let mut tail = vec![];
+    let receipt = Transfer::default()
+        .download(
+            requester.route("files.download"),
+            Manifest::new("a.bin").set_offset(299_000),
+            &mut tail,
+        )
+        .await
+        .unwrap();
+    assert_eq!(1000, receipt.length());
+    assert_eq!(&data[299_000..], &tail[..]);
+
+    // blobs larger than their declared size are rejected
+    let res = Transfer::default()
+        .upload(
+            requester.route("files.upload"),
+            Manifest::new("c.bin").set_size(10),
+            Cursor::new(blob(100)),
+        )
+        .await;
+    assert!(res.unwrap_err().to_string().contains("size"));
+
+    let res = Transfer::default()
+        .download(
+            requester.route("files.download"),
+            Manifest::new("missing.bin"),
+            &mut vec![],
+        )
+        .await;
+    assert!(res.is_err());
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_integrity() {
+    let transfer = Transfer::default().chunk_size(10);
+    let manifest = Manifest::new("blob");
+    let payloads = || transfer.payloads(&manifest, Cursor::new(blob(95)));
+
+    let mut received = vec![];
+    let receipt = transfer::receive(payloads(), |it| {
+        assert_eq!("blob", it.name());
+        async { Ok(&mut received) }
+    })
+    .await
+    .unwrap();
+    assert_eq!(95, receipt.length());
+    assert_eq!(blob(95), received);
+
+    // a corrupted chunk fails the checksum
+    let corrupted = payloads().enumerate().map(|(i, it)| match i {
+        3 => Ok(Payload::builder().set_data(vec![0u8; 10]).build()),
+        _ => it,
+    });
+    let res = transfer::receive(Box::pin(corrupted), |_| async { Ok(vec![]) }).await;
+    assert!(res.unwrap_err().to_string().contains("checksum"));
+
+    // a missing trailer means the transfer was interrupted
+    let interrupted = payloads().take(5);
+    let res = transfer::receive(Box::pin(interrupted), |_| async { Ok(vec![]) }).await;
+    assert!(res.unwrap_err().to_string().contains("after 40 bytes"));
+}
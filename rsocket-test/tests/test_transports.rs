@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rsocket_rust::prelude::*;
+use rsocket_rust::transport::{BoxTransport, Transports};
+use rsocket_rust::utils::EchoRSocket;
+use rsocket_rust_messaging::*;
+use rsocket_rust_transport_tcp::{TcpClientTransport, TcpServerTransport, UnixServerTransport};
+
+type Setups = Arc<Mutex<Vec<SetupPayload>>>;
+
+/// Starts an echo server on the port, recording the SETUP payloads.
+async fn serve_tcp(port: u16) -> Setups {
+    let setups = Setups::default();
+    let recorded = setups.clone();
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(TcpServerTransport::from(format!("127.0.0.1:{}", port)))
+            .acceptor(Box::new(move |setup, _socket| {
+                recorded.lock().unwrap().push(setup);
+                Ok(Box::new(EchoRSocket))
+            }))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    setups
+}
+
+async fn echo(requester: &Requester) -> String {
+    requester
+        .route("echo")
+        .data("hello")
+        .retrieve_mono()
+        .await
+        .decode()
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn test_registry() {
+    let mut transports = Transports::default();
+    let e = transports.resolve("tcp://127.0.0.1:8484").err().unwrap();
+    assert_eq!("unsupported transport scheme: tcp", e.to_string());
+
+    rsocket_rust_transport_tcp::register_schemes(&mut transports);
+    rsocket_rust_transport_websocket::register_schemes(&mut transports);
+    for scheme in ["tcp", "unix", "ws", "wss"] {
+        assert!(transports.contains(scheme));
+    }
+    assert!(transports.resolve("TCP://127.0.0.1:8484").is_ok());
+    assert!(transports.resolve("127.0.0.1:8484").is_err());
+    assert!(transports.resolve("ws://[invalid").is_err());
+}
+
+#[tokio::test]
+async fn test_requester_uri() {
+    let setups = serve_tcp(8484).await;
+
+    let requester = Requester::builder()
+        .connect("tcp://localhost:8484")
+        .build()
+        .await
+        .unwrap();
+    assert_eq!("hello", echo(&requester).await);
+
+    // settings of the client are kept, except the MIME types
+    let requester = Requester::builder()
+        .client(
+            RSocketFactory::connect()
+                .keepalive(Duration::from_secs(7), Duration::from_secs(30), 3)
+                .data_mime_type("text/plain"),
+        )
+        .connect_tcp("127.0.0.1", 8484)
+        .build()
+        .await
+        .unwrap();
+    assert_eq!("hello", echo(&requester).await);
+    {
+        let setups = setups.lock().unwrap();
+        assert_eq!(2, setups.len());
+        assert_eq!(Duration::from_secs(7), setups[1].keepalive_interval());
+        assert_eq!(Some("application/json"), setups[1].data_mime_type());
+        assert_eq!(
+            Some("message/x.rsocket.composite-metadata.v0"),
+            setups[1].metadata_mime_type()
+        );
+    }
+
+    // the transport of a prebuilt client
+    let mut transports = Transports::default();
+    rsocket_rust_transport_tcp::register_schemes(&mut transports);
+    let requester = Requester::builder()
+        .client(
+            RSocketFactory::connect()
+                .transport(transports.resolve("tcp://127.0.0.1:8484").unwrap()),
+        )
+        .build()
+        .await
+        .unwrap();
+    assert_eq!("hello", echo(&requester).await);
+
+    // a custom scheme
+    let requester = Requester::builder()
+        .scheme("local", |uri| {
+            let port = uri.trim_start_matches("local://");
+            let addr = format!("127.0.0.1:{}", port);
+            Ok(BoxTransport::new(TcpClientTransport::from(addr)))
+        })
+        .connect("local://8484")
+        .build()
+        .await
+        .unwrap();
+    assert_eq!("hello", echo(&requester).await);
+
+    assert!(Requester::builder().build().await.is_err());
+    assert!(Requester::builder()
+        .connect("foo://127.0.0.1:8484")
+        .build()
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_requester_unix() {
+    let addr = "/tmp/rsocket-transports.sock";
+    let _ = std::fs::remove_file(addr);
+    tokio::spawn(async move {
+        RSocketFactory::receive()
+            .transport(UnixServerTransport::from(addr))
+            .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+            .serve()
+            .await
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let requester = Requester::builder()
+        .connect(format!("unix://{}", addr))
+        .build()
+        .await
+        .unwrap();
+    assert_eq!("hello", echo(&requester).await);
+}
@@ -1,3 +1,5 @@
+use rsocket_rust::transport::{BoxTransport, Transports};
+
 mod tcp;
 mod uds;
 
@@ -10,3 +12,44 @@ cfg_if! {
         pub use tls::TlsClientTransport;
     }
 }
+
+/// Registers the schemes `tcp://host:port` and `unix:///path`, and `tls://host:port` if the
+/// feature `tls` is enabled.
+///
+/// TLS connections trust the root certificates of the system, build a `TlsClientTransport`
+/// for anything else.
+pub fn register_schemes(transports: &mut Transports) {
+    transports.register("tcp", |uri| {
+        Ok(BoxTransport::new(TcpClientTransport::host(authority(uri))))
+    });
+    transports.register("unix", |uri| {
+        Ok(BoxTransport::new(UnixClientTransport::from(authority(uri))))
+    });
+    #[cfg(feature = "tls")]
+    transports.register("tls", |uri| {
+        use rsocket_rust::error::RSocketError;
+        use tokio_native_tls::{native_tls, TlsConnector};
+
+        let (domain, port) = match authority(uri)
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+        {
+            Some(it) => it,
+            None => {
+                let desc = format!("invalid transport uri: {}", uri);
+                return Err(RSocketError::WithDescription(desc).into());
+            }
+        };
+        let cx = TlsConnector::from(native_tls::TlsConnector::new()?);
+        Ok(BoxTransport::new(TlsClientTransport::host(
+            domain.to_owned(),
+            port,
+            cx,
+        )))
+    });
+}
+
+/// Returns the part of a URI after the scheme.
+fn authority(uri: &str) -> &str {
+    uri.split_once("://").map(|(_, it)| it).unwrap_or(uri)
+}
@@ -10,6 +10,8 @@ use crate::{connection::TcpConnection, misc::parse_tcp_addr};
 enum Connector {
     Direct(TcpStream),
     Lazy(SocketAddr),
+    /// A `host:port`, which is resolved when connecting.
+    Host(String),
 }
 
 #[derive(Debug)]
@@ -28,6 +30,19 @@ impl Transport for TcpClientTransport {
                 Ok(stream) => Ok(TcpConnection::from(stream)),
                 Err(e) => Err(RSocketError::IO(e).into()),
             },
+            Connector::Host(host) => match TcpStream::connect(host).await {
+                Ok(stream) => Ok(TcpConnection::from(stream)),
+                Err(e) => Err(RSocketError::IO(e).into()),
+            },
+        }
+    }
+}
+
+impl TcpClientTransport {
+    /// Connects to a `host:port`, resolving the host when connecting.
+    pub fn host(host: impl Into<String>) -> TcpClientTransport {
+        TcpClientTransport {
+            connector: Connector::Host(host.into()),
         }
     }
 }
@@ -10,7 +10,7 @@ use crate::connection::TlsConnection;
 #[derive(Debug)]
 enum Connector {
     Direct(TlsStream<TcpStream>),
-    Lazy(String, SocketAddr, TlsConnector),
+    Lazy(String, String, TlsConnector),
 }
 
 pub struct TlsClientTransport {
@@ -19,6 +19,14 @@ pub struct TlsClientTransport {
 
 impl TlsClientTransport {
     pub fn new(domain: String, addr: SocketAddr, connector: TlsConnector) -> Self {
+        Self {
+            connector: Connector::Lazy(domain, addr.to_string(), connector),
+        }
+    }
+
+    /// Connects to the port of the domain, resolving it when connecting.
+    pub fn host(domain: String, port: u16, connector: TlsConnector) -> Self {
+        let addr = format!("{}:{}", domain, port);
         Self {
             connector: Connector::Lazy(domain, addr, connector),
         }
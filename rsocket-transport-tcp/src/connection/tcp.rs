@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use futures::{SinkExt, StreamExt};
 use rsocket_rust::error::RSocketError;
 use rsocket_rust::transport::{Connection, FrameSink, FrameStream};
@@ -19,6 +21,10 @@ impl Connection for TcpConnection {
             Box::new(stream.map(|next| next.map_err(|e| RSocketError::Other(e.into())))),
         )
     }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.peer_addr().ok()
+    }
 }
 
 impl From<TcpStream> for TcpConnection {
@@ -1,3 +1,5 @@
+use std::net::SocketAddr;
+
 use futures::{SinkExt, StreamExt};
 use rsocket_rust::error::RSocketError;
 use rsocket_rust::transport::{Connection, FrameSink, FrameStream};
@@ -20,6 +22,10 @@ impl Connection for TlsConnection {
             Box::new(stream.map(|it| it.map_err(|e| RSocketError::Other(e.into())))),
         )
     }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.stream.get_ref().get_ref().get_ref().peer_addr().ok()
+    }
 }
 
 impl From<TlsStream<TcpStream>> for TlsConnection {
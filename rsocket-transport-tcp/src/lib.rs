@@ -8,10 +8,12 @@ extern crate cfg_if;
 mod client;
 mod connection;
 mod misc;
+mod resolver;
 mod server;
 
-pub use client::{TcpClientTransport, UnixClientTransport};
+pub use client::{register_schemes, TcpClientTransport, UnixClientTransport};
 pub use connection::{TcpConnection, UnixConnection};
+pub use resolver::FileResolver;
 pub use server::{TcpServerTransport, UnixServerTransport};
 
 cfg_if! {
@@ -22,3 +24,9 @@ cfg_if! {
         pub use server::TlsServerTransport;
     }
 }
+
+cfg_if! {
+    if #[cfg(feature = "dns")] {
+        pub use resolver::DnsResolver;
+    }
+}
@@ -0,0 +1,151 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use hickory_resolver::config::{NameServerConfig, Protocol, ResolverConfig, ResolverOpts};
+use hickory_resolver::TokioAsyncResolver;
+use rsocket_rust::balancer::{Endpoint, Resolver};
+use rsocket_rust::prelude::Flux;
+use rsocket_rust::{error::RSocketError, stream, Result};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone)]
+enum Query {
+    Host(String, u16),
+    Srv(String),
+}
+
+/// Resolves endpoints with DNS lookups, which are repeated periodically.
+///
+/// A/AAAA lookups are used by `DnsResolver::host`, SRV lookups by `DnsResolver::srv`.
+/// The system configuration decides which name servers are queried, unless
+/// `DnsResolver::nameserver` is set.
+#[derive(Debug, Clone)]
+pub struct DnsResolver {
+    query: Query,
+    interval: Duration,
+    nameserver: Option<SocketAddr>,
+}
+
+impl DnsResolver {
+    /// Resolves the A/AAAA records of `host`, each address becomes an endpoint on `port`.
+    pub fn host(host: impl Into<String>, port: u16) -> DnsResolver {
+        Self::new(Query::Host(host.into(), port))
+    }
+
+    /// Resolves the SRV records of `name`, e.g. `_rsocket._tcp.example.com`.
+    ///
+    /// Only records of the lowest priority are used, SRV weights become endpoint weights.
+    pub fn srv(name: impl Into<String>) -> DnsResolver {
+        Self::new(Query::Srv(name.into()))
+    }
+
+    fn new(query: Query) -> DnsResolver {
+        DnsResolver {
+            query,
+            interval: DEFAULT_INTERVAL,
+            nameserver: None,
+        }
+    }
+
+    /// Sets how often the lookup is repeated.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Queries the given name server over UDP instead of the system configured ones.
+    pub fn nameserver(mut self, addr: SocketAddr) -> Self {
+        self.nameserver = Some(addr);
+        self
+    }
+
+    fn create_resolver(&self) -> Result<TokioAsyncResolver> {
+        match self.nameserver {
+            Some(addr) => {
+                let mut config = ResolverConfig::new();
+                config.add_name_server(NameServerConfig::new(addr, Protocol::Udp));
+                let mut opts = ResolverOpts::default();
+                // always see the latest records when polling
+                opts.cache_size = 0;
+                Ok(TokioAsyncResolver::tokio(config, opts))
+            }
+            None => TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|e| RSocketError::WithDescription(e.to_string()).into()),
+        }
+    }
+}
+
+impl Resolver for DnsResolver {
+    fn resolve(&self) -> Flux<Result<Vec<Endpoint>>> {
+        let resolver = self.create_resolver();
+        let query = self.query.clone();
+        let interval = self.interval;
+        Box::pin(stream! {
+            let resolver = match resolver {
+                Ok(it) => it,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let mut last: Option<Vec<Endpoint>> = None;
+            let mut failing = false;
+            loop {
+                match lookup(&resolver, &query).await {
+                    Ok(endpoints) => {
+                        failing = false;
+                        if last.as_ref() != Some(&endpoints) {
+                            last = Some(endpoints.clone());
+                            yield Ok(endpoints);
+                        }
+                    }
+                    Err(e) => {
+                        // only report the first error of a row
+                        if !failing {
+                            failing = true;
+                            yield Err(e);
+                        }
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+async fn lookup(resolver: &TokioAsyncResolver, query: &Query) -> Result<Vec<Endpoint>> {
+    let mut endpoints = vec![];
+    match query {
+        Query::Host(host, port) => {
+            let ips = resolver
+                .lookup_ip(host.as_str())
+                .await
+                .map_err(|e| RSocketError::WithDescription(e.to_string()))?;
+            for ip in ips.iter() {
+                endpoints.push(Endpoint::from(SocketAddr::new(ip, *port)));
+            }
+        }
+        Query::Srv(name) => {
+            let records = resolver
+                .srv_lookup(name.as_str())
+                .await
+                .map_err(|e| RSocketError::WithDescription(e.to_string()))?;
+            let priority = records.iter().map(|it| it.priority()).min();
+            for srv in records.iter().filter(|it| Some(it.priority()) == priority) {
+                let ips = resolver
+                    .lookup_ip(srv.target().clone())
+                    .await
+                    .map_err(|e| RSocketError::WithDescription(e.to_string()))?;
+                // a weight of zero means "rarely", which still needs to be selectable
+                let weight = u32::from(srv.weight()).max(1);
+                for ip in ips.iter() {
+                    let addr = SocketAddr::new(ip, srv.port());
+                    endpoints.push(Endpoint::from(addr).with_weight(weight));
+                }
+            }
+        }
+    }
+    endpoints.sort();
+    Ok(endpoints)
+}
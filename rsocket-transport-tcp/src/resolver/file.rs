@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rsocket_rust::balancer::{Endpoint, Resolver};
+use rsocket_rust::prelude::Flux;
+use rsocket_rust::{error::RSocketError, stream, Result};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Resolves endpoints from a local file, which is polled for changes.
+///
+/// Each line holds an address and an optional weight, separated by whitespace.
+/// Empty lines and lines starting with `#` are ignored.
+///
+/// ```text
+/// # host:port weight
+/// 10.0.0.1:7878
+/// 10.0.0.2:7878 3
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileResolver {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl FileResolver {
+    pub fn new(path: impl Into<PathBuf>) -> FileResolver {
+        FileResolver {
+            path: path.into(),
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Sets how often the file is read again.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl Resolver for FileResolver {
+    fn resolve(&self) -> Flux<Result<Vec<Endpoint>>> {
+        let path = self.path.clone();
+        let interval = self.interval;
+        Box::pin(stream! {
+            let mut last: Option<Vec<Endpoint>> = None;
+            let mut failing = false;
+            loop {
+                let next = match tokio::fs::read_to_string(&path).await {
+                    Ok(content) => parse(&content),
+                    Err(e) => Err(RSocketError::IO(e).into()),
+                };
+                match next {
+                    Ok(endpoints) => {
+                        failing = false;
+                        if last.as_ref() != Some(&endpoints) {
+                            last = Some(endpoints.clone());
+                            yield Ok(endpoints);
+                        }
+                    }
+                    Err(e) => {
+                        // only report the first error of a row
+                        if !failing {
+                            failing = true;
+                            yield Err(e);
+                        }
+                    }
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+fn parse(content: &str) -> Result<Vec<Endpoint>> {
+    let mut endpoints = vec![];
+    for line in content.lines().map(|it| it.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let addr = fields.next().unwrap_or_default();
+        let endpoint = match fields.next() {
+            Some(weight) => match weight.parse::<u32>() {
+                Ok(weight) => Endpoint::new(addr).with_weight(weight),
+                Err(_) => {
+                    let desc = format!("invalid weight of endpoint '{}'", line);
+                    return Err(RSocketError::WithDescription(desc).into());
+                }
+            },
+            None => Endpoint::new(addr),
+        };
+        endpoints.push(endpoint);
+    }
+    endpoints.sort();
+    Ok(endpoints)
+}
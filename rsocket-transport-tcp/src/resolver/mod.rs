@@ -0,0 +1,10 @@
+mod file;
+
+pub use file::FileResolver;
+
+cfg_if! {
+    if #[cfg(feature = "dns")] {
+        mod dns;
+        pub use dns::DnsResolver;
+    }
+}
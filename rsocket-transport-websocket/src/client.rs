@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 
-use rsocket_rust::{async_trait, error::RSocketError, transport::Transport, Result};
+use rsocket_rust::transport::{BoxTransport, Transport, Transports};
+use rsocket_rust::{async_trait, error::RSocketError, Result};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{
     accept_async, connect_async, tungstenite::handshake::client::Request, MaybeTlsStream,
@@ -54,6 +55,16 @@ impl Transport for WebsocketClientTransport {
     }
 }
 
+/// Registers the schemes `ws://` and `wss://`.
+pub fn register_schemes(transports: &mut Transports) {
+    for scheme in ["ws", "wss"] {
+        transports.register(scheme, |uri| {
+            let url = Url::parse(uri)?;
+            Ok(BoxTransport::new(WebsocketClientTransport::from(url)))
+        });
+    }
+}
+
 impl From<MaybeTlsStream<TcpStream>> for WebsocketClientTransport {
     fn from(socket: MaybeTlsStream<TcpStream>) -> WebsocketClientTransport {
         WebsocketClientTransport::new(Connector::Direct(socket))
@@ -1,3 +1,4 @@
+use std::net::SocketAddr;
 use std::result::Result;
 
 use bytes::{BufMut, BytesMut};
@@ -80,4 +81,14 @@ impl Connection for WebsocketConnection {
             })),
         )
     }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        match self.stream.get_ref() {
+            MaybeTlsStream::Plain(stream) => stream.peer_addr().ok(),
+            MaybeTlsStream::NativeTls(stream) => {
+                stream.get_ref().get_ref().get_ref().peer_addr().ok()
+            }
+            _ => None,
+        }
+    }
 }
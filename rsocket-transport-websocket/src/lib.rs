@@ -7,7 +7,7 @@ mod client;
 mod connection;
 mod server;
 
-pub use client::{WebsocketClientTransport, WebsocketRequest};
+pub use client::{register_schemes, WebsocketClientTransport, WebsocketRequest};
 pub use server::WebsocketServerTransport;
 
 #[cfg(test)]
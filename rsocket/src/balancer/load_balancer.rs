@@ -0,0 +1,515 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+
+use async_stream::stream;
+use async_trait::async_trait;
+use futures::future::{join_all, AbortHandle, Abortable};
+use futures::StreamExt;
+
+use super::member::Member;
+use super::resolver::{Endpoint, Resolver};
+use super::strategy::{RoundRobin, Stats, Strategy};
+use crate::error::RSocketError;
+use crate::payload::Payload;
+use crate::spi::{Flux, RSocket};
+use crate::{runtime, Result};
+
+type Connecting = Pin<Box<dyn Send + Future<Output = Result<Box<dyn RSocket>>>>>;
+type Connector = Box<dyn Send + Sync + Fn() -> Connecting>;
+type Dialer = Arc<dyn Send + Sync + Fn(String) -> Connecting>;
+
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A client side load balancer which spreads requests over several RSockets.
+///
+/// Members which fail with a connection error, or whose connection is closed while idle, are
+/// evicted. Members registered through
+/// `LoadBalancerBuilder::target` or discovered by a `Resolver` are reconnected in the
+/// background and re-added on success.
+///
+/// # Example
+/// ```no_run,ignore
+/// let lb = LoadBalancer::builder()
+///     .strategy(LeastRequests::default())
+///     .target(|| async {
+///         RSocketFactory::connect()
+///             .transport(TcpClientTransport::from("127.0.0.1:7878"))
+///             .start()
+///             .await
+///     })
+///     .target(|| async {
+///         RSocketFactory::connect()
+///             .transport(TcpClientTransport::from("127.0.0.1:7979"))
+///             .start()
+///             .await
+///     })
+///     .build()
+///     .await;
+/// let res = lb.request_response(Payload::from("Hello!")).await?;
+/// ```
+#[derive(Clone)]
+pub struct LoadBalancer {
+    inner: Arc<Inner>,
+}
+
+pub struct LoadBalancerBuilder {
+    strategy: Option<Box<dyn Strategy>>,
+    members: Vec<(Box<dyn RSocket>, u32)>,
+    targets: Vec<Target>,
+    discovery: Option<(Box<dyn Resolver>, Dialer)>,
+    retry_interval: Duration,
+}
+
+struct Inner {
+    strategy: Box<dyn Strategy>,
+    members: RwLock<Vec<Entry>>,
+    discovered: Mutex<HashMap<Endpoint, Arc<Target>>>,
+    retry_interval: Duration,
+    /// Background tasks which run as long as the load balancer is alive.
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+struct Target {
+    connector: Connector,
+    weight: u32,
+    removed: AtomicBool,
+}
+
+struct Entry {
+    member: Arc<Member>,
+    target: Option<Arc<Target>>,
+}
+
+impl LoadBalancerBuilder {
+    fn new() -> LoadBalancerBuilder {
+        LoadBalancerBuilder {
+            strategy: None,
+            members: vec![],
+            targets: vec![],
+            discovery: None,
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+        }
+    }
+
+    /// Sets the strategy used to pick members, `RoundRobin` by default.
+    pub fn strategy<S>(mut self, strategy: S) -> Self
+    where
+        S: Strategy + 'static,
+    {
+        self.strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Adds a connected RSocket. It won't come back once evicted.
+    pub fn member(self, rsocket: Box<dyn RSocket>) -> Self {
+        self.weighted_member(rsocket, 1)
+    }
+
+    pub fn weighted_member(mut self, rsocket: Box<dyn RSocket>, weight: u32) -> Self {
+        self.members.push((rsocket, weight));
+        self
+    }
+
+    /// Adds a target which is connected by calling `connector`, and reconnected after eviction.
+    pub fn target<F, Fut, R>(self, connector: F) -> Self
+    where
+        F: Send + Sync + Fn() -> Fut + 'static,
+        Fut: Send + Future<Output = Result<R>> + 'static,
+        R: RSocket + 'static,
+    {
+        self.weighted_target(connector, 1)
+    }
+
+    pub fn weighted_target<F, Fut, R>(mut self, connector: F, weight: u32) -> Self
+    where
+        F: Send + Sync + Fn() -> Fut + 'static,
+        Fut: Send + Future<Output = Result<R>> + 'static,
+        R: RSocket + 'static,
+    {
+        let connector: Connector = Box::new(move || {
+            let fut = connector();
+            Box::pin(async move {
+                let rsocket: Box<dyn RSocket> = Box::new(fut.await?);
+                Ok(rsocket)
+            })
+        });
+        self.targets.push(Target::new(connector, weight));
+        self
+    }
+
+    /// Subscribes to `resolver`, calling `dial` with the address of every new endpoint.
+    ///
+    /// Members of endpoints which are no longer resolved are removed.
+    pub fn resolver<D, Fut, R>(mut self, resolver: impl Resolver + 'static, dial: D) -> Self
+    where
+        D: Send + Sync + Fn(String) -> Fut + 'static,
+        Fut: Send + Future<Output = Result<R>> + 'static,
+        R: RSocket + 'static,
+    {
+        let dialer: Dialer = Arc::new(move |addr| {
+            let fut = dial(addr);
+            Box::pin(async move {
+                let rsocket: Box<dyn RSocket> = Box::new(fut.await?);
+                Ok(rsocket)
+            })
+        });
+        self.discovery = Some((Box::new(resolver), dialer));
+        self
+    }
+
+    /// Sets the delay between two reconnect attempts of an evicted target, which is also how
+    /// often members are checked for closed connections.
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Connects all targets. Targets which can't be connected yet are retried in background.
+    ///
+    /// If a resolver is set, it also waits for the first set of endpoints to be connected.
+    pub async fn build(self) -> LoadBalancer {
+        let inner = Arc::new(Inner {
+            strategy: self
+                .strategy
+                .unwrap_or_else(|| Box::new(RoundRobin::default())),
+            members: RwLock::new(vec![]),
+            discovered: Mutex::new(HashMap::new()),
+            retry_interval: self.retry_interval,
+            tasks: Mutex::new(vec![]),
+        });
+        for (rsocket, weight) in self.members.into_iter() {
+            inner.add(Arc::new(Member::new(rsocket, weight)), None);
+        }
+        let targets = self.targets.into_iter().map(Arc::new).collect();
+        inner.connect_all(targets).await;
+
+        // connections closed while idle fail no request, look for them
+        let weak = Arc::downgrade(&inner);
+        let interval = self.retry_interval;
+        inner.spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match weak.upgrade() {
+                    Some(inner) => inner.evict_closed(),
+                    None => break,
+                }
+            }
+        });
+
+        if let Some((resolver, dialer)) = self.discovery {
+            let mut endpoints = resolver.resolve();
+            if let Some(first) = endpoints.next().await {
+                inner.on_resolve(first, &dialer).await;
+            }
+            // resolvers usually never complete, so the task is aborted once the balancer is dropped
+            let weak = Arc::downgrade(&inner);
+            inner.spawn(async move {
+                while let Some(next) = endpoints.next().await {
+                    match weak.upgrade() {
+                        Some(inner) => inner.on_resolve(next, &dialer).await,
+                        None => break,
+                    }
+                }
+            });
+        }
+        LoadBalancer { inner }
+    }
+}
+
+impl Target {
+    fn new(connector: Connector, weight: u32) -> Target {
+        Target {
+            connector,
+            weight,
+            removed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LoadBalancer {
+    pub fn builder() -> LoadBalancerBuilder {
+        LoadBalancerBuilder::new()
+    }
+
+    /// Returns the amount of members which are currently in rotation.
+    pub fn len(&self) -> usize {
+        self.inner.members.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Inner {
+    /// Spawns a task which is aborted when the load balancer is dropped.
+    fn spawn<F>(&self, task: F)
+    where
+        F: Send + Future<Output = ()> + 'static,
+    {
+        let (handle, registration) = AbortHandle::new_pair();
+        self.tasks.lock().unwrap().push(handle);
+        runtime::spawn(async move {
+            let _ = Abortable::new(task, registration).await;
+        });
+    }
+
+    fn add(&self, member: Arc<Member>, target: Option<Arc<Target>>) {
+        let mut members = self.members.write().unwrap();
+        if let Some(target) = &target {
+            if target.removed.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+        members.push(Entry { member, target });
+    }
+
+    async fn connect_all(self: &Arc<Self>, targets: Vec<Arc<Target>>) {
+        let results = join_all(targets.iter().map(|it| (it.connector)())).await;
+        for (target, res) in targets.into_iter().zip(results) {
+            match res {
+                Ok(rsocket) => {
+                    let member = Member::new(rsocket, target.weight);
+                    self.add(Arc::new(member), Some(target));
+                }
+                Err(e) => {
+                    warn!("connect load balancer target failed: {}", e);
+                    Self::reconnect(Arc::downgrade(self), target);
+                }
+            }
+        }
+    }
+
+    async fn on_resolve(self: &Arc<Self>, resolved: Result<Vec<Endpoint>>, dialer: &Dialer) {
+        let endpoints = match resolved {
+            Ok(it) => it,
+            Err(e) => {
+                warn!("resolve endpoints failed: {}", e);
+                return;
+            }
+        };
+        let mut added = vec![];
+        let mut removed = vec![];
+        {
+            let mut discovered = self.discovered.lock().unwrap();
+            discovered.retain(|endpoint, target| {
+                if endpoints.contains(endpoint) {
+                    true
+                } else {
+                    removed.push(target.clone());
+                    false
+                }
+            });
+            for endpoint in endpoints.into_iter() {
+                if discovered.contains_key(&endpoint) {
+                    continue;
+                }
+                let dialer = dialer.clone();
+                let addr = endpoint.addr().to_owned();
+                let connector: Connector = Box::new(move || dialer(addr.clone()));
+                let target = Arc::new(Target::new(connector, endpoint.weight()));
+                info!("discovered load balancer endpoint: {}", endpoint);
+                discovered.insert(endpoint, target.clone());
+                added.push(target);
+            }
+        }
+        if !removed.is_empty() {
+            for target in removed.iter() {
+                target.removed.store(true, Ordering::SeqCst);
+            }
+            self.members
+                .write()
+                .unwrap()
+                .retain(|entry| match &entry.target {
+                    Some(target) => !removed.iter().any(|it| Arc::ptr_eq(it, target)),
+                    None => true,
+                });
+        }
+        self.connect_all(added).await;
+    }
+
+    fn select(self: &Arc<Self>) -> Result<Arc<Member>> {
+        self.evict_closed();
+        let members = self.members.read().unwrap();
+        let (candidates, stats): (Vec<&Arc<Member>>, Vec<Stats>) = members
+            .iter()
+            .map(|it| (&it.member, it.member.stats()))
+            .filter(|(_, stats)| stats.availability > 0.0)
+            .unzip();
+        if candidates.is_empty() {
+            return Err(RSocketError::WithDescription("no available member".into()).into());
+        }
+        let i = self.strategy.select(&stats) % candidates.len();
+        Ok(candidates[i].clone())
+    }
+
+    fn on_error(self: &Arc<Self>, member: &Arc<Member>, e: &anyhow::Error) {
+        if is_connection_error(e) {
+            self.evict(member, e);
+        }
+    }
+
+    /// Evicts the members whose connection has been closed, e.g. by the peer while idle.
+    fn evict_closed(self: &Arc<Self>) {
+        let closed: Vec<Arc<Member>> = self
+            .members
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|it| it.member.rsocket.is_closed())
+            .map(|it| it.member.clone())
+            .collect();
+        for member in closed.iter() {
+            self.evict(member, "connection closed");
+        }
+    }
+
+    /// Removes a member, and reconnects its target in background.
+    fn evict(self: &Arc<Self>, member: &Arc<Member>, reason: impl fmt::Display) {
+        let mut members = self.members.write().unwrap();
+        let pos = members
+            .iter()
+            .position(|it| Arc::ptr_eq(&it.member, member));
+        if let Some(pos) = pos {
+            let evicted = members.remove(pos);
+            drop(members);
+            warn!("evict load balancer member: {}", reason);
+            if let Some(target) = evicted.target {
+                Self::reconnect(Arc::downgrade(self), target);
+            }
+        }
+    }
+
+    fn reconnect(inner: Weak<Inner>, target: Arc<Target>) {
+        runtime::spawn(async move {
+            loop {
+                let interval = match inner.upgrade() {
+                    Some(it) => it.retry_interval,
+                    None => break,
+                };
+                tokio::time::sleep(interval).await;
+                if target.removed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let connecting = (target.connector)();
+                match connecting.await {
+                    Ok(rsocket) => {
+                        if let Some(inner) = inner.upgrade() {
+                            info!("load balancer target recovered");
+                            let member = Member::new(rsocket, target.weight);
+                            inner.add(Arc::new(member), Some(target));
+                        }
+                        break;
+                    }
+                    Err(e) => debug!("reconnect load balancer target failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+fn is_connection_error(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<RSocketError>() {
+        Some(RSocketError::ConnectionClosed(_))
+        | Some(RSocketError::ConnectionException(_))
+        | Some(RSocketError::IO(_)) => true,
+        _ => e.downcast_ref::<std::io::Error>().is_some(),
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        for task in self.tasks.get_mut().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+#[async_trait]
+impl RSocket for LoadBalancer {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        let member = self.inner.select()?;
+        let res = member.rsocket.metadata_push(req).await;
+        if let Err(e) = &res {
+            self.inner.on_error(&member, e);
+        }
+        res
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        let member = self.inner.select()?;
+        let mut pending = member.start();
+        let res = member.rsocket.fire_and_forget(req).await;
+        pending.respond();
+        if let Err(e) = &res {
+            self.inner.on_error(&member, e);
+        }
+        res
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        let member = self.inner.select()?;
+        let mut pending = member.start();
+        let res = member.rsocket.request_response(req).await;
+        pending.respond();
+        if let Err(e) = &res {
+            self.inner.on_error(&member, e);
+        }
+        res
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        match self.inner.select() {
+            Ok(member) => {
+                let results = member.rsocket.request_stream(req);
+                self.watch(member, results)
+            }
+            Err(e) => Box::pin(futures::stream::once(async move { Err(e) })),
+        }
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        match self.inner.select() {
+            Ok(member) => {
+                let results = member.rsocket.request_channel(reqs);
+                self.watch(member, results)
+            }
+            Err(e) => Box::pin(futures::stream::once(async move { Err(e) })),
+        }
+    }
+
+    fn availability(&self) -> f64 {
+        self.inner
+            .members
+            .read()
+            .unwrap()
+            .iter()
+            .map(|it| it.member.rsocket.availability())
+            .fold(0.0, f64::max)
+    }
+}
+
+impl LoadBalancer {
+    fn watch(
+        &self,
+        member: Arc<Member>,
+        mut results: Flux<Result<Payload>>,
+    ) -> Flux<Result<Payload>> {
+        let inner = self.inner.clone();
+        let mut pending = member.start();
+        Box::pin(stream! {
+            while let Some(next) = results.next().await {
+                pending.respond();
+                if let Err(e) = &next {
+                    inner.on_error(&member, e);
+                }
+                yield next;
+            }
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::strategy::Stats;
+use crate::runtime::Instant;
+use crate::spi::RSocket;
+
+// Weight of the newest sample in the latency moving average.
+const EWMA_ALPHA: f64 = 0.3;
+
+pub(crate) struct Member {
+    pub(crate) rsocket: Box<dyn RSocket>,
+    pub(crate) weight: u32,
+    pending: AtomicU32,
+    // microseconds as f64 bits, zero until the first sample
+    latency: AtomicU64,
+}
+
+/// Keeps a member's pending counter up while a request is in flight.
+pub(crate) struct Pending {
+    member: Arc<Member>,
+    start: Instant,
+    recorded: bool,
+}
+
+impl Member {
+    pub(crate) fn new(rsocket: Box<dyn RSocket>, weight: u32) -> Member {
+        Member {
+            rsocket,
+            weight,
+            pending: AtomicU32::new(0),
+            latency: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Stats {
+        Stats {
+            weight: self.weight,
+            pending: self.pending.load(Ordering::Relaxed),
+            latency: Duration::from_micros(
+                f64::from_bits(self.latency.load(Ordering::Relaxed)) as u64
+            ),
+            availability: self.rsocket.availability(),
+        }
+    }
+
+    pub(crate) fn start(self: &Arc<Self>) -> Pending {
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        Pending {
+            member: self.clone(),
+            start: Instant::now(),
+            recorded: false,
+        }
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros() as f64;
+        let _ = self
+            .latency
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |prev| {
+                let prev = f64::from_bits(prev);
+                let next = if prev == 0.0 {
+                    sample
+                } else {
+                    prev + EWMA_ALPHA * (sample - prev)
+                };
+                Some(next.to_bits())
+            });
+    }
+}
+
+impl Pending {
+    /// Records the latency of the first response, later calls are ignored.
+    pub(crate) fn respond(&mut self) {
+        if !self.recorded {
+            self.recorded = true;
+            self.member.record_latency(self.start.elapsed());
+        }
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.member.pending.fetch_sub(1, Ordering::SeqCst);
+    }
+}
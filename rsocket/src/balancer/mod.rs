@@ -0,0 +1,12 @@
+mod load_balancer;
+mod member;
+mod pool;
+mod resolver;
+mod scatter;
+mod strategy;
+
+pub use load_balancer::{LoadBalancer, LoadBalancerBuilder};
+pub use pool::{Pool, PoolBuilder};
+pub use resolver::{Endpoint, Resolver, StaticResolver};
+pub use scatter::{Gathered, MemberError, Policy, ScatterGather, ScatterGatherBuilder};
+pub use strategy::{LeastRequests, RoundRobin, Stats, Strategy, Weighted, WeightedLatency};
@@ -0,0 +1,140 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::load_balancer::LoadBalancer;
+use super::strategy::LeastRequests;
+use crate::error::RSocketError;
+use crate::payload::Payload;
+use crate::spi::{Flux, RSocket};
+use crate::Result;
+
+const DEFAULT_SIZE: usize = 4;
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A fixed size pool of connections to the same endpoint.
+///
+/// Every request goes to the connection with the least outstanding requests and streams,
+/// so that bulk workloads are not limited by a single connection. Connections which fail a
+/// request, or are closed by the peer while idle, are re-established in background, every
+/// `retry_interval` until they succeed.
+///
+/// # Example
+/// ```no_run,ignore
+/// let pool = Pool::builder()
+///     .size(8)
+///     .connect(|| async {
+///         RSocketFactory::connect()
+///             .transport(TcpClientTransport::from("127.0.0.1:7878"))
+///             .start()
+///             .await
+///     })
+///     .build()
+///     .await?;
+/// let res = pool.request_response(Payload::from("Hello!")).await?;
+/// ```
+#[derive(Clone)]
+pub struct Pool {
+    lb: LoadBalancer,
+}
+
+pub struct PoolBuilder<F> {
+    size: usize,
+    connector: Option<F>,
+    retry_interval: Duration,
+}
+
+impl Pool {
+    pub fn builder<F>() -> PoolBuilder<F> {
+        PoolBuilder {
+            size: DEFAULT_SIZE,
+            connector: None,
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+        }
+    }
+
+    /// Returns the amount of connections which are currently established.
+    pub fn connections(&self) -> usize {
+        self.lb.len()
+    }
+}
+
+impl<F> PoolBuilder<F> {
+    /// Sets the amount of connections, 4 by default.
+    pub fn size(mut self, size: usize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets how a single connection is established, usually by starting a `Client`.
+    pub fn connect(mut self, connector: F) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// Sets the delay between two attempts to re-establish a broken connection, which is also
+    /// how often idle connections are checked for being closed.
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+}
+
+impl<F, Fut, R> PoolBuilder<F>
+where
+    F: Send + Sync + Fn() -> Fut + 'static,
+    Fut: Send + Future<Output = Result<R>> + 'static,
+    R: RSocket + 'static,
+{
+    /// Establishes all connections.
+    ///
+    /// Fails if none of them could be established, otherwise the missing ones are retried
+    /// in background.
+    pub async fn build(self) -> Result<Pool> {
+        let connector = match self.connector {
+            Some(it) => Arc::new(it),
+            None => return Err(RSocketError::WithDescription("missing connector".into()).into()),
+        };
+        let mut builder = LoadBalancer::builder()
+            .strategy(LeastRequests::default())
+            .retry_interval(self.retry_interval);
+        for _ in 0..self.size {
+            let connector = connector.clone();
+            builder = builder.target(move || connector());
+        }
+        let lb = builder.build().await;
+        if lb.is_empty() {
+            return Err(RSocketError::WithDescription("no connection established".into()).into());
+        }
+        Ok(Pool { lb })
+    }
+}
+
+#[async_trait]
+impl RSocket for Pool {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        self.lb.metadata_push(req).await
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        self.lb.fire_and_forget(req).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        self.lb.request_response(req).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        self.lb.request_stream(req)
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        self.lb.request_channel(reqs)
+    }
+
+    fn availability(&self) -> f64 {
+        self.lb.availability()
+    }
+}
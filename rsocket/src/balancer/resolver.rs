@@ -0,0 +1,94 @@
+use std::fmt;
+use std::net::SocketAddr;
+
+use futures::stream;
+
+use crate::spi::Flux;
+use crate::Result;
+
+/// An address resolved by a `Resolver`, together with its load balancing weight.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Endpoint {
+    addr: String,
+    weight: u32,
+}
+
+impl Endpoint {
+    pub fn new(addr: impl Into<String>) -> Endpoint {
+        Endpoint {
+            addr: addr.into(),
+            weight: 1,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+}
+
+impl From<&str> for Endpoint {
+    fn from(addr: &str) -> Endpoint {
+        Endpoint::new(addr)
+    }
+}
+
+impl From<String> for Endpoint {
+    fn from(addr: String) -> Endpoint {
+        Endpoint::new(addr)
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Endpoint {
+        Endpoint::new(addr.to_string())
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.addr)
+    }
+}
+
+/// Discovers the endpoints of a service.
+///
+/// `LoadBalancerBuilder::resolver` subscribes to it, connects new endpoints and drops the
+/// connections of endpoints which are gone.
+pub trait Resolver: Send + Sync {
+    /// Returns a stream which emits the whole set of endpoints every time it changes.
+    fn resolve(&self) -> Flux<Result<Vec<Endpoint>>>;
+}
+
+/// A resolver which always returns the same endpoints.
+#[derive(Debug, Clone)]
+pub struct StaticResolver {
+    endpoints: Vec<Endpoint>,
+}
+
+impl StaticResolver {
+    pub fn new<I, E>(endpoints: I) -> StaticResolver
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<Endpoint>,
+    {
+        StaticResolver {
+            endpoints: endpoints.into_iter().map(|it| it.into()).collect(),
+        }
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn resolve(&self) -> Flux<Result<Vec<Endpoint>>> {
+        let endpoints = self.endpoints.clone();
+        Box::pin(stream::once(async move { Ok(endpoints) }))
+    }
+}
@@ -0,0 +1,367 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_stream::stream;
+use futures::stream::{select_all, FuturesUnordered};
+use futures::{future, StreamExt};
+
+use crate::error::RSocketError;
+use crate::payload::Payload;
+use crate::spi::{Flux, RSocket};
+use crate::Result;
+
+type Deadline = Pin<Box<dyn Send + Future<Output = ()>>>;
+
+/// When a scatter-gather stops waiting for its members.
+///
+/// Members which are still running once the policy is satisfied, or can't be satisfied anymore,
+/// are cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Stops at the first member which succeeds.
+    FirstSuccess,
+    /// Stops once the given amount of members succeeded.
+    Quorum(usize),
+    /// Waits for every member, and stops at the first failure.
+    All,
+}
+
+/// Fans one request out to several RSockets concurrently and gathers their results.
+///
+/// # Example
+/// ```no_run,ignore
+/// let sg = ScatterGather::builder()
+///     .member(Box::new(inventory_a))
+///     .member(Box::new(inventory_b))
+///     .timeout(Duration::from_millis(500))
+///     .policy(Policy::Quorum(1))
+///     .build();
+/// let gathered = sg.request_response(Payload::from("sku-42")).await;
+/// for e in gathered.failures() {
+///     warn!("{}", e);
+/// }
+/// let responses = gathered.into_result()?;
+/// ```
+#[derive(Clone)]
+pub struct ScatterGather {
+    members: Vec<Arc<Box<dyn RSocket>>>,
+    timeout: Option<Duration>,
+    policy: Policy,
+}
+
+pub struct ScatterGatherBuilder {
+    members: Vec<Arc<Box<dyn RSocket>>>,
+    timeout: Option<Duration>,
+    policy: Policy,
+}
+
+/// Results of the members of a scatter-gather, in completion order.
+#[derive(Debug)]
+pub struct Gathered<T> {
+    required: usize,
+    successes: Vec<(usize, T)>,
+    failures: Vec<MemberError>,
+    cancelled: Vec<usize>,
+}
+
+/// The failure of a member, which is identified by its index in the scatter-gather.
+#[derive(Debug)]
+pub struct MemberError {
+    member: usize,
+    timed_out: bool,
+    error: anyhow::Error,
+}
+
+enum Event {
+    Next(Payload),
+    Complete,
+    Failed(MemberError),
+}
+
+impl ScatterGatherBuilder {
+    fn new() -> ScatterGatherBuilder {
+        ScatterGatherBuilder {
+            members: vec![],
+            timeout: None,
+            policy: Policy::All,
+        }
+    }
+
+    pub fn member(mut self, rsocket: Box<dyn RSocket>) -> Self {
+        self.members.push(Arc::new(rsocket));
+        self
+    }
+
+    /// Sets how long a member may take to respond, or to complete its stream. No limit by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the early termination policy, `Policy::All` by default.
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn build(self) -> ScatterGather {
+        ScatterGather {
+            members: self.members,
+            timeout: self.timeout,
+            policy: self.policy,
+        }
+    }
+}
+
+impl ScatterGather {
+    pub fn builder() -> ScatterGatherBuilder {
+        ScatterGatherBuilder::new()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Sends a request-response to every member.
+    pub async fn request_response(&self, req: Payload) -> Gathered<Option<Payload>> {
+        let calls = self.members.iter().enumerate().map(|(i, member)| {
+            let res = member.request_response(req.clone());
+            async move { (i, self.within(i, res).await) }
+        });
+        self.gather(calls).await
+    }
+
+    /// Sends a request-stream to every member and collects the whole stream of each one.
+    ///
+    /// A member fails as soon as its stream yields an error.
+    pub async fn request_stream(&self, req: Payload) -> Gathered<Vec<Payload>> {
+        let calls = self.members.iter().enumerate().map(|(i, member)| {
+            let mut results = member.request_stream(req.clone());
+            let collect = async move {
+                let mut payloads = vec![];
+                while let Some(next) = results.next().await {
+                    payloads.push(next?);
+                }
+                Ok(payloads)
+            };
+            async move { (i, self.within(i, collect).await) }
+        });
+        self.gather(calls).await
+    }
+
+    /// Sends a request-stream to every member and merges their payloads as they arrive.
+    ///
+    /// A member counts as a success once its stream completes. The merged stream completes when
+    /// the policy is satisfied, or ends with an error describing the failed members when it can't
+    /// be satisfied anymore.
+    pub fn request_stream_merged(&self, req: Payload) -> Flux<Result<Payload>> {
+        let total = self.members.len();
+        let required = self.required();
+        let timeout = self.timeout;
+        let members: Vec<_> = self
+            .members
+            .iter()
+            .enumerate()
+            .map(|(i, member)| member_events(i, member.request_stream(req.clone()), timeout))
+            .collect();
+        Box::pin(stream! {
+            let mut merged = select_all(members);
+            let mut completed = 0;
+            let mut failures = vec![];
+            if required > total {
+                yield Err(unsatisfied(required, &failures));
+                return;
+            }
+            while completed < required {
+                match merged.next().await {
+                    Some(Event::Next(payload)) => yield Ok(payload),
+                    Some(Event::Complete) => completed += 1,
+                    Some(Event::Failed(e)) => {
+                        failures.push(e);
+                        if total - failures.len() < required {
+                            yield Err(unsatisfied(required, &failures));
+                            return;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        })
+    }
+
+    async fn gather<I, F, T>(&self, calls: I) -> Gathered<T>
+    where
+        I: IntoIterator<Item = F>,
+        F: Future<Output = (usize, std::result::Result<T, MemberError>)>,
+    {
+        let required = self.required();
+        let mut running: Vec<bool> = vec![true; self.members.len()];
+        let mut gathered = Gathered {
+            required,
+            successes: vec![],
+            failures: vec![],
+            cancelled: vec![],
+        };
+        let mut calls: FuturesUnordered<F> = calls.into_iter().collect();
+        while gathered.successes.len() < required
+            && gathered.successes.len() + calls.len() >= required
+        {
+            match calls.next().await {
+                Some((i, res)) => {
+                    running[i] = false;
+                    match res {
+                        Ok(it) => gathered.successes.push((i, it)),
+                        Err(e) => gathered.failures.push(e),
+                    }
+                }
+                None => break,
+            }
+        }
+        gathered.cancelled = (0..running.len()).filter(|i| running[*i]).collect();
+        gathered
+    }
+
+    async fn within<F, T>(&self, member: usize, fut: F) -> std::result::Result<T, MemberError>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let res = match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, fut).await {
+                Ok(res) => res,
+                Err(_) => return Err(MemberError::timeout(member, timeout)),
+            },
+            None => fut.await,
+        };
+        res.map_err(|error| MemberError {
+            member,
+            timed_out: false,
+            error,
+        })
+    }
+
+    fn required(&self) -> usize {
+        match self.policy {
+            Policy::FirstSuccess => 1,
+            Policy::Quorum(n) => n,
+            Policy::All => self.members.len(),
+        }
+    }
+}
+
+impl<T> Gathered<T> {
+    /// Returns true if enough members succeeded to satisfy the policy.
+    pub fn is_satisfied(&self) -> bool {
+        self.successes.len() >= self.required
+    }
+
+    /// Returns the results of the members which succeeded, with their index.
+    pub fn successes(&self) -> &[(usize, T)] {
+        &self.successes
+    }
+
+    pub fn failures(&self) -> &[MemberError] {
+        &self.failures
+    }
+
+    /// Returns the index of the members which were cancelled by an early termination.
+    pub fn cancelled(&self) -> &[usize] {
+        &self.cancelled
+    }
+
+    pub fn into_successes(self) -> Vec<(usize, T)> {
+        self.successes
+    }
+
+    /// Returns the results of the members which succeeded, or an error describing the failed
+    /// members if the policy isn't satisfied.
+    pub fn into_result(self) -> Result<Vec<T>> {
+        if self.is_satisfied() {
+            Ok(self.successes.into_iter().map(|(_, it)| it).collect())
+        } else {
+            Err(unsatisfied(self.required, &self.failures))
+        }
+    }
+}
+
+impl MemberError {
+    fn timeout(member: usize, timeout: Duration) -> MemberError {
+        MemberError {
+            member,
+            timed_out: true,
+            error: RSocketError::WithDescription(format!("timeout after {:?}", timeout)).into(),
+        }
+    }
+
+    pub fn member(&self) -> usize {
+        self.member
+    }
+
+    pub fn is_timeout(&self) -> bool {
+        self.timed_out
+    }
+
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    pub fn into_error(self) -> anyhow::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for MemberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "member {}: {}", self.member, self.error)
+    }
+}
+
+impl std::error::Error for MemberError {}
+
+fn member_events(
+    member: usize,
+    mut results: Flux<Result<Payload>>,
+    timeout: Option<Duration>,
+) -> Flux<Event> {
+    let mut deadline: Deadline = match timeout {
+        Some(timeout) => Box::pin(tokio::time::sleep(timeout)),
+        None => Box::pin(future::pending()),
+    };
+    Box::pin(stream! {
+        loop {
+            tokio::select! {
+                next = results.next() => match next {
+                    Some(Ok(payload)) => yield Event::Next(payload),
+                    Some(Err(error)) => {
+                        yield Event::Failed(MemberError { member, timed_out: false, error });
+                        break;
+                    }
+                    None => {
+                        yield Event::Complete;
+                        break;
+                    }
+                },
+                _ = &mut deadline => {
+                    yield Event::Failed(MemberError::timeout(member, timeout.unwrap()));
+                    break;
+                }
+            }
+        }
+    })
+}
+
+fn unsatisfied(required: usize, failures: &[MemberError]) -> anyhow::Error {
+    let details: Vec<String> = failures.iter().map(|it| it.to_string()).collect();
+    RSocketError::WithDescription(format!(
+        "scatter-gather needs {} successful members, failures: [{}]",
+        required,
+        details.join(", ")
+    ))
+    .into()
+}
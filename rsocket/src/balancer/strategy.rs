@@ -0,0 +1,162 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// A snapshot of the load of a balancer member, taken right before a request is issued.
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub(crate) weight: u32,
+    pub(crate) pending: u32,
+    pub(crate) latency: Duration,
+    pub(crate) availability: f64,
+}
+
+impl Stats {
+    /// Static weight given when the member was registered.
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// Amount of requests and streams currently in flight.
+    pub fn pending(&self) -> u32 {
+        self.pending
+    }
+
+    /// Moving average of the response latency, zero until the first response arrives.
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// Availability reported by the member, see `RSocket::availability`.
+    pub fn availability(&self) -> f64 {
+        self.availability
+    }
+}
+
+/// Decides which member of a `LoadBalancer` serves the next request.
+pub trait Strategy: Send + Sync {
+    /// Returns the index of the chosen member.
+    ///
+    /// `members` is never empty and only contains members with a positive availability.
+    fn select(&self, members: &[Stats]) -> usize;
+}
+
+/// Picks members one after another.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    cursor: AtomicUsize,
+}
+
+impl Strategy for RoundRobin {
+    fn select(&self, members: &[Stats]) -> usize {
+        self.cursor.fetch_add(1, Ordering::Relaxed) % members.len()
+    }
+}
+
+/// Picks members randomly, in proportion to `weight * availability`.
+///
+/// Members with LEASE frames granting fewer requests are picked less often.
+#[derive(Debug)]
+pub struct Weighted {
+    seed: AtomicU64,
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(0);
+        Weighted {
+            seed: AtomicU64::new(hasher.finish() | 1),
+        }
+    }
+}
+
+impl Weighted {
+    // xorshift64*, good enough for spreading requests.
+    fn next_f64(&self) -> f64 {
+        let mut x = self.seed.load(Ordering::Relaxed);
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.seed.store(x, Ordering::Relaxed);
+        let n = x.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (n >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Strategy for Weighted {
+    fn select(&self, members: &[Stats]) -> usize {
+        let weights: Vec<f64> = members
+            .iter()
+            .map(|it| f64::from(it.weight) * it.availability)
+            .collect();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return 0;
+        }
+        let mut point = self.next_f64() * total;
+        for (i, w) in weights.iter().enumerate() {
+            if point < *w {
+                return i;
+            }
+            point -= w;
+        }
+        members.len() - 1
+    }
+}
+
+/// Picks the member with the fewest outstanding requests.
+///
+/// Ties are broken in round-robin order.
+#[derive(Debug, Default)]
+pub struct LeastRequests {
+    cursor: AtomicUsize,
+}
+
+impl Strategy for LeastRequests {
+    fn select(&self, members: &[Stats]) -> usize {
+        let offset = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let n = members.len();
+        (0..n)
+            .map(|i| (i + offset) % n)
+            .min_by_key(|i| members[*i].pending)
+            .unwrap_or(0)
+    }
+}
+
+/// Picks the member with the best `availability * weight / (pending * latency)` score,
+/// similar to `WeightedLoadbalanceStrategy` of rsocket-java.
+///
+/// Ties are broken in round-robin order.
+#[derive(Debug, Default)]
+pub struct WeightedLatency {
+    cursor: AtomicUsize,
+}
+
+impl WeightedLatency {
+    fn score(stats: &Stats) -> f64 {
+        // Members without any samples yet are treated as 1ms away.
+        let latency = (stats.latency.as_micros() as f64).max(1000.0);
+        let pending = f64::from(stats.pending) + 1.0;
+        stats.availability * f64::from(stats.weight) / (pending * latency)
+    }
+}
+
+impl Strategy for WeightedLatency {
+    fn select(&self, members: &[Stats]) -> usize {
+        let offset = self.cursor.fetch_add(1, Ordering::Relaxed);
+        let n = members.len();
+        let mut best = offset % n;
+        let mut best_score = Self::score(&members[best]);
+        for i in 1..n {
+            let cur = (i + offset) % n;
+            let score = Self::score(&members[cur]);
+            if score > best_score {
+                best = cur;
+                best_score = score;
+            }
+        }
+        best
+    }
+}
@@ -10,18 +10,22 @@ use tokio::sync::{mpsc, Mutex, Notify};
 
 use crate::error::{RSocketError, ERR_CONN_CLOSED};
 use crate::frame::{self, Frame};
+use crate::interceptor::{ConnectionInfo, Interceptor, InterceptorChain};
 use crate::payload::{Payload, SetupPayload, SetupPayloadBuilder};
 use crate::runtime;
 use crate::spi::{ClientResponder, Flux, RSocket};
+use crate::transport::instrument::{self, ConnectionSpan, Side};
 use crate::transport::{
     self, Connection, DuplexSocket, FrameSink, FrameStream, ClientRequester, Splitter, Transport,
 };
+use crate::utils::EmptyRSocket;
 use crate::Result;
 
 #[derive(Clone)]
 pub struct Client {
     closed: Arc<Notify>,
-    requester: ClientRequester,
+    socket: ClientRequester,
+    requester: Arc<Box<dyn RSocket>>,
     closing: mpsc::Sender<()>,
 }
 
@@ -30,6 +34,7 @@ pub struct ClientBuilder<T, C> {
     setup: SetupPayloadBuilder,
     responder: Option<ClientResponder>,
     closer: Option<Box<dyn FnMut() + Send + Sync>>,
+    interceptors: InterceptorChain,
     mtu: usize,
     _c: PhantomData<C>,
 }
@@ -45,6 +50,7 @@ where
             responder: None,
             setup: SetupPayload::builder(),
             closer: None,
+            interceptors: InterceptorChain::default(),
             mtu: 0,
             _c: PhantomData,
         }
@@ -103,11 +109,26 @@ where
         self
     }
 
+    /// Asks the server to send LEASE frames, which then drive `Client::availability`.
+    pub fn lease(mut self) -> Self {
+        self.setup = self.setup.set_honor_lease(true);
+        self
+    }
+
     pub fn acceptor(mut self, acceptor: ClientResponder) -> Self {
         self.responder = Some(acceptor);
         self
     }
 
+    /// Adds an interceptor which is applied to the connection once it is established.
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: Interceptor + 'static,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
     pub fn on_close(mut self, callback: Box<dyn FnMut() + Sync + Send>) -> Self {
         self.closer = Some(callback);
         self
@@ -120,7 +141,10 @@ where
     C: Send + Sync + Connection + 'static,
 {
     pub async fn start(mut self) -> Result<Client> {
-        let tp: T = self.transport.take().expect("missint transport");
+        let tp: T = match self.transport.take() {
+            Some(it) => it,
+            None => return Err(RSocketError::WithDescription("missing transport".into()).into()),
+        };
 
         let splitter = if self.mtu == 0 {
             None
@@ -130,23 +154,34 @@ where
 
         let (snd_tx, mut snd_rx) = mpsc::unbounded_channel::<Frame>();
         let cloned_snd_tx = snd_tx.clone();
-        let mut socket = DuplexSocket::new(1, snd_tx, splitter);
+        let span = ConnectionSpan::new(Side::Client);
+        let mut socket = DuplexSocket::new(1, snd_tx, splitter, span.clone());
 
-        let requester = socket.client_requester();
-
-        if let Some(f) = self.responder {
-            let responder = f();
-            socket.bind_responder(responder).await;
-        }
+        let client_requester = socket.client_requester();
 
         let conn = tp.connect().await?;
-        let (mut sink, mut stream) = conn.split();
-
+        span.record_peer_addr(conn.peer_addr());
         let setup = self.setup.build();
 
+        let info = ConnectionInfo::new(setup.clone(), conn.peer_addr());
+        self.interceptors.on_setup(&info).await?;
+        let requester = self
+            .interceptors
+            .requester(&info, Box::new(client_requester.clone()));
+        let responder: Box<dyn RSocket> = match self.responder {
+            Some(f) => f(),
+            None => Box::new(EmptyRSocket),
+        };
+        socket
+            .bind_responder(self.interceptors.responder(&info, responder))
+            .await;
+        instrument::connection_opened(Side::Client);
+
+        let (mut sink, mut stream) = conn.split();
+
         // begin write loop
         let tick_period = setup.keepalive_interval();
-        runtime::spawn(async move {
+        runtime::spawn(span.instrument(async move {
             loop {
                 // send keepalive if timeout
                 match tokio::time::timeout(tick_period, snd_rx.recv()).await {
@@ -156,6 +191,7 @@ where
                                 break;
                             }
                         }
+                        instrument::frame_sent(&frame);
                         if let Err(e) = sink.send(frame).await {
                             error!("write frame failed: {}", e);
                             break;
@@ -166,6 +202,7 @@ where
                         // keepalive
                         let keepalive_frame =
                             frame::Keepalive::builder(0, Frame::FLAG_RESPOND).build();
+                        instrument::frame_sent(&keepalive_frame);
                         if let Err(e) = sink.send(keepalive_frame).await {
                             error!("write frame failed: {}", e);
                             break;
@@ -173,7 +210,7 @@ where
                     }
                 }
             }
-        });
+        }));
 
         // begin read loop
         let closer = self.closer.take();
@@ -215,7 +252,7 @@ where
         socket.setup(setup).await?;
 
         // process frames
-        runtime::spawn(async move {
+        runtime::spawn(span.instrument(async move {
             while let Some(next) = read_rx.recv().await {
                 if let Err(e) = socket.dispatch(next, None).await {
                     error!("dispatch frame failed: {}", e);
@@ -231,6 +268,8 @@ where
                 debug!("send close notify frame failed: {}", e);
             }
 
+            instrument::connection_closed(Side::Client);
+
             // notify client closed
             close_notify_clone.notify_one();
 
@@ -238,16 +277,27 @@ where
             if let Some(mut invoke) = closer {
                 invoke();
             }
-        });
+        }));
 
-        Ok(Client::new(requester, close_notify, closing))
+        Ok(Client::new(
+            client_requester,
+            requester,
+            close_notify,
+            closing,
+        ))
     }
 }
 
 impl Client {
-    fn new(requester: ClientRequester, closed: Arc<Notify>, closing: mpsc::Sender<()>) -> Client {
+    fn new(
+        socket: ClientRequester,
+        requester: Box<dyn RSocket>,
+        closed: Arc<Notify>,
+        closing: mpsc::Sender<()>,
+    ) -> Client {
         Client {
-            requester,
+            socket,
+            requester: Arc::new(requester),
             closed,
             closing,
         }
@@ -279,4 +329,12 @@ impl RSocket for Client {
     fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
         self.requester.request_channel(reqs)
     }
+
+    fn availability(&self) -> f64 {
+        self.socket.availability()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.socket.is_closed()
+    }
 }
@@ -10,9 +10,11 @@ use tokio::sync::mpsc;
 
 use crate::error::RSocketError;
 use crate::frame::{self, Frame};
+use crate::interceptor::{Interceptor, InterceptorChain};
 use crate::payload::SetupPayload;
 use crate::runtime;
 use crate::spi::{RSocket, ServerResponder};
+use crate::transport::instrument::{self, ConnectionSpan, Side};
 use crate::transport::{Connection, DuplexSocket, ServerTransport, Splitter, Transport, MIN_MTU};
 use crate::utils::EmptyRSocket;
 use crate::Result;
@@ -21,6 +23,7 @@ pub struct ServerBuilder<T, C> {
     transport: Option<T>,
     on_setup: Option<ServerResponder>,
     start_handler: Option<Box<dyn FnMut() + Send + Sync>>,
+    interceptors: InterceptorChain,
     mtu: usize,
     _c: PhantomData<C>,
 }
@@ -35,6 +38,7 @@ where
             transport: None,
             on_setup: None,
             start_handler: None,
+            interceptors: InterceptorChain::default(),
             mtu: 0,
             _c: PhantomData,
         }
@@ -52,6 +56,15 @@ where
         self
     }
 
+    /// Adds an interceptor which is applied to every accepted connection.
+    pub fn interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: Interceptor + 'static,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
     pub fn on_start(mut self, hanlder: Box<dyn FnMut() + Send + Sync>) -> Self {
         self.start_handler = Some(hanlder);
         self
@@ -81,12 +94,14 @@ where
         }
 
         let acceptor = Arc::new(self.on_setup);
+        let interceptors = self.interceptors;
         while let Some(next) = server_transport.next().await {
             match next {
                 Ok(tp) => {
                     let acceptor = acceptor.clone();
+                    let interceptors = interceptors.clone();
                     runtime::spawn(async move {
-                        if let Err(e) = Self::on_transport(mtu, tp, acceptor).await {
+                        if let Err(e) = Self::on_transport(mtu, tp, acceptor, interceptors).await {
                             error!("handle transport failed: {}", e);
                         }
                     });
@@ -100,9 +115,18 @@ where
     }
 
     #[inline]
-    async fn on_transport(mtu: usize, tp: C, acceptor: Arc<Option<ServerResponder>>) -> Result<()> {
+    async fn on_transport(
+        mtu: usize,
+        tp: C,
+        acceptor: Arc<Option<ServerResponder>>,
+        interceptors: InterceptorChain,
+    ) -> Result<()> {
         // Establish connection.
         let conn = tp.connect().await?;
+        let peer_addr = conn.peer_addr();
+        let span = ConnectionSpan::new(Side::Server);
+        span.record_peer_addr(peer_addr);
+        instrument::connection_opened(Side::Server);
         let (mut writer, mut reader) = conn.split();
 
         // Create frame splitter.
@@ -114,17 +138,19 @@ where
 
         // Init duplex socket.
         let (snd_tx, mut snd_rx) = mpsc::unbounded_channel::<Frame>();
-        let mut socket = DuplexSocket::new(0, snd_tx, splitter);
+        let mut socket = DuplexSocket::new(0, snd_tx, splitter, span.clone());
+        socket.intercept(interceptors, peer_addr);
 
         // Begin loop for writing frames.
-        runtime::spawn(async move {
+        runtime::spawn(span.instrument(async move {
             while let Some(frame) = snd_rx.recv().await {
+                instrument::frame_sent(&frame);
                 if let Err(e) = writer.send(frame).await {
                     error!("write frame failed: {}", e);
                     break;
                 }
             }
-        });
+        }));
 
         let (read_tx, mut read_rx) = mpsc::unbounded_channel::<Frame>();
 
@@ -148,12 +174,16 @@ where
             }
         });
 
-        while let Some(frame) = read_rx.recv().await {
-            if let Err(e) = socket.dispatch(frame, acceptor.as_ref().as_ref()).await {
-                error!("dispatch frame failed: {}", e);
-                break;
+        let dispatch = async {
+            while let Some(frame) = read_rx.recv().await {
+                if let Err(e) = socket.dispatch(frame, acceptor.as_ref().as_ref()).await {
+                    error!("dispatch frame failed: {}", e);
+                    break;
+                }
             }
-        }
+        };
+        span.instrument(dispatch).await;
+        instrument::connection_closed(Side::Server);
         Ok(())
     }
 }
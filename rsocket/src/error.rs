@@ -1,6 +1,7 @@
 use std::fmt;
 use std::io;
 
+use bytes::Bytes;
 use thiserror::Error;
 
 pub const ERR_INVALID_SETUP: u32 = 0x0000_0001;
@@ -31,6 +32,9 @@ pub enum RSocketError {
     ConnectionClosed(String),
     #[error("APPLICATION_ERROR: {0}")]
     ApplicationException(String),
+    /// An application error whose data isn't text, e.g. a structured error body.
+    #[error("APPLICATION_ERROR: {}", String::from_utf8_lossy(.0))]
+    ApplicationData(Bytes),
     #[error("REJECTED: {0}")]
     RequestRejected(String),
     #[error("CANCELLED: {0}")]
@@ -68,4 +72,13 @@ impl RSocketError {
             _ => RSocketError::Reserved(code, desc),
         }
     }
+
+    /// Returns the data of an application error, whether it is text or not.
+    pub fn application_data(&self) -> Option<&[u8]> {
+        match self {
+            RSocketError::ApplicationException(desc) => Some(desc.as_bytes()),
+            RSocketError::ApplicationData(data) => Some(data),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,197 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::composite::CompositeMetadata;
+use super::metadata::MetadataExtension;
+use super::mime::MimeType;
+use crate::error::RSocketError;
+use crate::utils::Writeable;
+
+const WELL_KNOWN_SIMPLE: u8 = 0x00;
+const WELL_KNOWN_BEARER: u8 = 0x01;
+const MAX_AUTH_TYPE_LEN: usize = 0x7F + 1;
+const MAX_USERNAME_LEN: usize = u16::MAX as usize;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AuthType {
+    Simple,
+    Bearer,
+    Custom(String),
+}
+
+/// Authentication metadata of `message/x.rsocket.authentication.v0`, in the SETUP frame or
+/// in a request.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuthMetadata {
+    auth_type: AuthType,
+    payload: Bytes,
+}
+
+pub struct AuthMetadataBuilder {
+    inner: Option<AuthMetadata>,
+}
+
+impl AuthMetadataBuilder {
+    pub fn simple(mut self, username: &str, password: &str) -> Self {
+        assert!(
+            username.len() <= MAX_USERNAME_LEN,
+            "exceeded maximum username length!"
+        );
+        let mut bf = BytesMut::with_capacity(2 + username.len() + password.len());
+        bf.put_u16(username.len() as u16);
+        bf.put_slice(username.as_bytes());
+        bf.put_slice(password.as_bytes());
+        self.inner = Some(AuthMetadata {
+            auth_type: AuthType::Simple,
+            payload: bf.freeze(),
+        });
+        self
+    }
+
+    pub fn bearer(mut self, token: &str) -> Self {
+        self.inner = Some(AuthMetadata {
+            auth_type: AuthType::Bearer,
+            payload: Bytes::from(token.to_owned()),
+        });
+        self
+    }
+
+    pub fn custom<A>(mut self, auth_type: &str, payload: A) -> Self
+    where
+        A: AsRef<[u8]>,
+    {
+        assert!(
+            !auth_type.is_empty() && auth_type.len() <= MAX_AUTH_TYPE_LEN,
+            "invalid length of auth type!"
+        );
+        assert!(auth_type.is_ascii(), "auth type must be ASCII!");
+        self.inner = Some(AuthMetadata {
+            auth_type: AuthType::Custom(String::from(auth_type)),
+            payload: Bytes::copy_from_slice(payload.as_ref()),
+        });
+        self
+    }
+
+    pub fn build(self) -> AuthMetadata {
+        self.inner.expect("missing authentication")
+    }
+}
+
+impl AuthMetadata {
+    pub fn builder() -> AuthMetadataBuilder {
+        AuthMetadataBuilder { inner: None }
+    }
+
+    pub fn decode(bf: &mut BytesMut) -> crate::Result<AuthMetadata> {
+        if bf.is_empty() {
+            return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+        }
+        let first = bf.get_u8();
+        let auth_type = if first & 0x80 != 0 {
+            match first & 0x7F {
+                WELL_KNOWN_SIMPLE => AuthType::Simple,
+                WELL_KNOWN_BEARER => AuthType::Bearer,
+                n => {
+                    let desc = format!("unknown well-known auth type: {:#04x}", n);
+                    return Err(RSocketError::WithDescription(desc).into());
+                }
+            }
+        } else {
+            let size = first as usize + 1;
+            if bf.len() < size {
+                return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+            }
+            AuthType::Custom(String::from_utf8(bf.split_to(size).to_vec())?)
+        };
+        let payload = bf.split().freeze();
+        if auth_type == AuthType::Simple {
+            if payload.len() < 2 {
+                return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+            }
+            let size = (&payload[..2]).get_u16() as usize;
+            if payload.len() < 2 + size {
+                return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+            }
+            std::str::from_utf8(&payload[2..2 + size])?;
+            std::str::from_utf8(&payload[2 + size..])?;
+        } else if auth_type == AuthType::Bearer {
+            std::str::from_utf8(&payload)?;
+        }
+        Ok(AuthMetadata { auth_type, payload })
+    }
+
+    /// Adds it to the composite metadata of a SETUP frame or a request.
+    pub fn inject(&self, composite: &mut CompositeMetadata) {
+        composite.insert(self);
+    }
+
+    /// Finds and decodes it in composite metadata.
+    pub fn extract(composite: &CompositeMetadata) -> crate::Result<Option<AuthMetadata>> {
+        composite.get()
+    }
+
+    pub fn get_auth_type(&self) -> &AuthType {
+        &self.auth_type
+    }
+
+    /// Returns the encoded credentials, following the auth type.
+    pub fn get_payload(&self) -> &Bytes {
+        &self.payload
+    }
+
+    pub fn get_username(&self) -> Option<&str> {
+        self.simple().map(|(username, _)| username)
+    }
+
+    pub fn get_password(&self) -> Option<&str> {
+        self.simple().map(|(_, password)| password)
+    }
+
+    pub fn get_token(&self) -> Option<&str> {
+        match self.auth_type {
+            AuthType::Bearer => std::str::from_utf8(&self.payload).ok(),
+            _ => None,
+        }
+    }
+
+    fn simple(&self) -> Option<(&str, &str)> {
+        if self.auth_type != AuthType::Simple {
+            return None;
+        }
+        let size = (&self.payload[..2]).get_u16() as usize;
+        let username = std::str::from_utf8(&self.payload[2..2 + size]).ok()?;
+        let password = std::str::from_utf8(&self.payload[2 + size..]).ok()?;
+        Some((username, password))
+    }
+}
+
+impl MetadataExtension for AuthMetadata {
+    fn mime_type() -> MimeType {
+        MimeType::MESSAGE_X_RSOCKET_AUTHENTICATION_V0
+    }
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+        AuthMetadata::decode(bf)
+    }
+}
+
+impl Writeable for AuthMetadata {
+    fn write_to(&self, bf: &mut BytesMut) {
+        match &self.auth_type {
+            AuthType::Simple => bf.put_u8(0x80 | WELL_KNOWN_SIMPLE),
+            AuthType::Bearer => bf.put_u8(0x80 | WELL_KNOWN_BEARER),
+            AuthType::Custom(s) => {
+                bf.put_u8((s.len() - 1) as u8);
+                bf.put_slice(s.as_bytes());
+            }
+        }
+        bf.put_slice(&self.payload);
+    }
+
+    fn len(&self) -> usize {
+        let mut n = 1 + self.payload.len();
+        if let AuthType::Custom(s) = &self.auth_type {
+            n += s.len();
+        }
+        n
+    }
+}
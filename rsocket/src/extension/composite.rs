@@ -4,6 +4,7 @@ use std::result::Result;
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+use super::metadata::MetadataExtension;
 use super::mime::MimeType;
 use crate::error::RSocketError;
 use crate::utils::{u24, Writeable};
@@ -25,6 +26,11 @@ pub struct CompositeMetadataBuilder {
     inner: CompositeMetadata,
 }
 
+/// Entries decoded one by one from encoded composite metadata, sharing its bytes.
+pub struct CompositeMetadataEntries {
+    bs: Bytes,
+}
+
 impl CompositeMetadataBuilder {
     pub fn push<A>(mut self, mime_type: MimeType, payload: A) -> Self
     where
@@ -95,9 +101,10 @@ impl CompositeMetadata {
     }
 
     pub fn decode(b: &mut BytesMut) -> crate::Result<CompositeMetadata> {
+        let mut bs = b.split().freeze();
         let mut metadatas = LinkedList::new();
         loop {
-            match Self::decode_once(b) {
+            match Self::decode_once(&mut bs) {
                 Ok(Some(v)) => metadatas.push_back(v),
                 Ok(None) => break,
                 Err(e) => return Err(e),
@@ -110,8 +117,50 @@ impl CompositeMetadata {
         self.metadatas.iter()
     }
 
+    /// Decodes the entries lazily, without copying the encoded bytes.
+    pub fn entries(bs: Bytes) -> CompositeMetadataEntries {
+        CompositeMetadataEntries { bs }
+    }
+
+    /// Returns the first entry of the MIME type, whether it was encoded as well-known or not.
+    pub(crate) fn find(&self, mime_type: &MimeType) -> Option<&CompositeMetadataEntry> {
+        self.iter().find(|it| it.get_mime_type().same(mime_type))
+    }
+
+    /// Finds and decodes the first entry of the extension.
+    pub fn get<T>(&self) -> crate::Result<Option<T>>
+    where
+        T: MetadataExtension,
+    {
+        match self.find(&T::mime_type()) {
+            Some(entry) => entry.decode().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Encodes the extension as an entry, replacing any existing entries of its MIME type.
+    pub fn insert<T>(&mut self, metadata: &T)
+    where
+        T: MetadataExtension,
+    {
+        let mime_type = T::mime_type();
+        self.remove(&mime_type);
+        let metadata = Bytes::from(metadata.bytes());
+        self.push(CompositeMetadataEntry::new(mime_type, metadata));
+    }
+
+    /// Removes the entries of the MIME type, whether they were encoded as well-known or not.
+    pub(crate) fn remove(&mut self, mime_type: &MimeType) {
+        if self.iter().any(|it| it.get_mime_type().same(mime_type)) {
+            self.metadatas = std::mem::take(&mut self.metadatas)
+                .into_iter()
+                .filter(|it| !it.get_mime_type().same(mime_type))
+                .collect();
+        }
+    }
+
     #[inline]
-    fn decode_once(bs: &mut BytesMut) -> crate::Result<Option<CompositeMetadataEntry>> {
+    fn decode_once(bs: &mut Bytes) -> crate::Result<Option<CompositeMetadataEntry>> {
         if bs.is_empty() {
             return Ok(None);
         }
@@ -139,12 +188,12 @@ impl CompositeMetadata {
             )
             .into());
         }
-        let payload_size = u24::read_advance(bs).into();
+        let payload_size = u24::parse(&bs.split_to(3)).into();
         if bs.len() < payload_size {
             let desc = format!("broken composite metadata: require {} bytes!", payload_size);
             return Err(RSocketError::WithDescription(desc).into());
         }
-        let metadata = bs.split_to(payload_size).freeze();
+        let metadata = bs.split_to(payload_size);
         Ok(Some(CompositeMetadataEntry::new(mime_type, metadata)))
     }
 
@@ -173,6 +222,14 @@ impl CompositeMetadataEntry {
     pub fn get_metadata_utf8(&self) -> Option<&str> {
         std::str::from_utf8(&self.metadata).ok()
     }
+
+    /// Decodes the metadata as the extension, regardless of the MIME type of the entry.
+    pub fn decode<T>(&self) -> crate::Result<T>
+    where
+        T: MetadataExtension,
+    {
+        T::decode(&mut BytesMut::from(&self.metadata[..]))
+    }
 }
 
 impl Writeable for CompositeMetadataEntry {
@@ -207,3 +264,19 @@ impl Writeable for CompositeMetadataEntry {
         amount
     }
 }
+
+impl Iterator for CompositeMetadataEntries {
+    type Item = crate::Result<CompositeMetadataEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match CompositeMetadata::decode_once(&mut self.bs) {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => None,
+            Err(e) => {
+                // nothing can be decoded after a broken entry
+                self.bs.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use bytes::BytesMut;
+
+use super::mime::MimeType;
+use crate::utils::Writeable;
+
+/// A typed metadata extension, carried as an entry of composite metadata under its MIME type.
+///
+/// Encoding is provided by `Writeable`, so a new extension only has to name its MIME type and
+/// decode itself:
+///
+/// ```no_run,ignore
+/// impl MetadataExtension for RoutingMetadata {
+///     fn mime_type() -> MimeType {
+///         MimeType::MESSAGE_X_RSOCKET_ROUTING_V0
+///     }
+///
+///     fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+///         RoutingMetadata::decode(bf)
+///     }
+/// }
+///
+/// let routing: Option<RoutingMetadata> = composite.get()?;
+/// ```
+pub trait MetadataExtension: Writeable + Sized {
+    fn mime_type() -> MimeType;
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self>;
+}
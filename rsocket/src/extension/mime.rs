@@ -47,6 +47,11 @@ impl MimeType {
             Self::WellKnown(n) => U8_TO_STR.get(n).copied(),
         }
     }
+
+    /// Returns true if both are the same MIME type, whether encoded as well-known or not.
+    pub(crate) fn same(&self, other: &MimeType) -> bool {
+        self == other || (self.as_str().is_some() && self.as_str() == other.as_str())
+    }
 }
 
 impl Into<String> for MimeType {
@@ -92,7 +97,7 @@ macro_rules! mime {
 }
 
 mime!(APPLICATION_AVRO, 0x00, "application/avro");
-mime!(APPLICATION_CBOR, 0x01, "application/avro");
+mime!(APPLICATION_CBOR, 0x01, "application/cbor");
 mime!(APPLICATION_GRAPHQL, 0x02, "application/graphql");
 mime!(APPLICATION_GZIP, 0x03, "application/gzip");
 mime!(APPLICATION_JAVASCRIPT, 0x04, "application/javascript");
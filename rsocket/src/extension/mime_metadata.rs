@@ -0,0 +1,194 @@
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::composite::CompositeMetadata;
+use super::metadata::MetadataExtension;
+use super::mime::MimeType;
+use crate::error::RSocketError;
+use crate::utils::Writeable;
+
+const MAX_MIME_LEN: usize = 0x7F + 1;
+
+/// The data MIME type of a single stream, overriding the one of the connection.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MimeTypeMetadata {
+    mime_type: MimeType,
+}
+
+/// The data MIME types a requester accepts in responses, in the order of preference.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AcceptMimeTypesMetadata {
+    mime_types: Vec<MimeType>,
+}
+
+pub struct AcceptMimeTypesMetadataBuilder {
+    inner: AcceptMimeTypesMetadata,
+}
+
+impl From<MimeType> for MimeTypeMetadata {
+    fn from(mime_type: MimeType) -> MimeTypeMetadata {
+        MimeTypeMetadata::new(mime_type)
+    }
+}
+
+impl MimeTypeMetadata {
+    pub fn new(mime_type: MimeType) -> MimeTypeMetadata {
+        check(&mime_type);
+        MimeTypeMetadata { mime_type }
+    }
+
+    pub fn decode(bf: &mut BytesMut) -> crate::Result<MimeTypeMetadata> {
+        match decode_once(bf)? {
+            Some(mime_type) => Ok(MimeTypeMetadata { mime_type }),
+            None => Err(RSocketError::WithDescription("require more bytes!".into()).into()),
+        }
+    }
+
+    /// Adds it to the composite metadata of a request or a response.
+    pub fn inject(&self, composite: &mut CompositeMetadata) {
+        composite.insert(self);
+    }
+
+    /// Finds and decodes it in composite metadata.
+    pub fn extract(composite: &CompositeMetadata) -> crate::Result<Option<MimeTypeMetadata>> {
+        composite.get()
+    }
+
+    pub fn get_mime_type(&self) -> &MimeType {
+        &self.mime_type
+    }
+}
+
+impl MetadataExtension for MimeTypeMetadata {
+    fn mime_type() -> MimeType {
+        MimeType::MESSAGE_X_RSOCKET_MIME_TYPE_V0
+    }
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+        MimeTypeMetadata::decode(bf)
+    }
+}
+
+impl Writeable for MimeTypeMetadata {
+    fn write_to(&self, bf: &mut BytesMut) {
+        write_mime(&self.mime_type, bf);
+    }
+
+    fn len(&self) -> usize {
+        mime_len(&self.mime_type)
+    }
+}
+
+impl AcceptMimeTypesMetadataBuilder {
+    pub fn push(mut self, mime_type: MimeType) -> Self {
+        check(&mime_type);
+        self.inner.mime_types.push(mime_type);
+        self
+    }
+
+    pub fn build(self) -> AcceptMimeTypesMetadata {
+        self.inner
+    }
+}
+
+impl AcceptMimeTypesMetadata {
+    pub fn builder() -> AcceptMimeTypesMetadataBuilder {
+        AcceptMimeTypesMetadataBuilder {
+            inner: AcceptMimeTypesMetadata::default(),
+        }
+    }
+
+    pub fn decode(bf: &mut BytesMut) -> crate::Result<AcceptMimeTypesMetadata> {
+        let mut bu = AcceptMimeTypesMetadata::builder();
+        while let Some(mime_type) = decode_once(bf)? {
+            bu.inner.mime_types.push(mime_type);
+        }
+        Ok(bu.build())
+    }
+
+    /// Adds it to the composite metadata of a request.
+    pub fn inject(&self, composite: &mut CompositeMetadata) {
+        composite.insert(self);
+    }
+
+    /// Finds and decodes it in composite metadata.
+    pub fn extract(
+        composite: &CompositeMetadata,
+    ) -> crate::Result<Option<AcceptMimeTypesMetadata>> {
+        composite.get()
+    }
+
+    pub fn get_mime_types(&self) -> &Vec<MimeType> {
+        &self.mime_types
+    }
+
+    /// Returns the first accepted MIME type which is also offered.
+    pub fn negotiate<'a>(&self, offered: &'a [MimeType]) -> Option<&'a MimeType> {
+        self.mime_types
+            .iter()
+            .find_map(|accepted| offered.iter().find(|it| it.same(accepted)))
+    }
+}
+
+impl MetadataExtension for AcceptMimeTypesMetadata {
+    fn mime_type() -> MimeType {
+        MimeType::MESSAGE_X_RSOCKET_ACCEPT_TIME_TYPES_V0
+    }
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+        AcceptMimeTypesMetadata::decode(bf)
+    }
+}
+
+impl Writeable for AcceptMimeTypesMetadata {
+    fn write_to(&self, bf: &mut BytesMut) {
+        for it in &self.mime_types {
+            write_mime(it, bf);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.mime_types.iter().map(mime_len).sum()
+    }
+}
+
+fn check(mime_type: &MimeType) {
+    if let MimeType::Normal(s) = mime_type {
+        assert!(
+            !s.is_empty() && s.len() <= MAX_MIME_LEN,
+            "invalid length of MimeType!"
+        );
+    }
+}
+
+fn write_mime(mime_type: &MimeType, bf: &mut BytesMut) {
+    match mime_type {
+        MimeType::WellKnown(n) => bf.put_u8(0x80 | n),
+        MimeType::Normal(s) => {
+            bf.put_u8((s.len() - 1) as u8);
+            bf.put_slice(s.as_bytes());
+        }
+    }
+}
+
+fn mime_len(mime_type: &MimeType) -> usize {
+    match mime_type {
+        MimeType::WellKnown(_) => 1,
+        MimeType::Normal(s) => 1 + s.len(),
+    }
+}
+
+fn decode_once(bf: &mut BytesMut) -> crate::Result<Option<MimeType>> {
+    if bf.is_empty() {
+        return Ok(None);
+    }
+    let first = bf.get_u8();
+    if first & 0x80 != 0 {
+        return Ok(Some(MimeType::WellKnown(first & 0x7F)));
+    }
+    let size = first as usize + 1;
+    if bf.len() < size {
+        return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+    }
+    let mime_type = String::from_utf8(bf.split_to(size).to_vec())?;
+    Ok(Some(MimeType::Normal(mime_type)))
+}
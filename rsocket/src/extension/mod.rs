@@ -1,7 +1,21 @@
+mod auth;
 mod composite;
+mod metadata;
 mod mime;
+mod mime_metadata;
 mod routing;
+mod trace_context;
+mod zipkin;
 
-pub use composite::{CompositeMetadata, CompositeMetadataBuilder, CompositeMetadataEntry};
+pub use auth::{AuthMetadata, AuthMetadataBuilder, AuthType};
+pub use composite::{
+    CompositeMetadata, CompositeMetadataBuilder, CompositeMetadataEntries, CompositeMetadataEntry,
+};
+pub use metadata::MetadataExtension;
 pub use mime::MimeType;
+pub use mime_metadata::{
+    AcceptMimeTypesMetadata, AcceptMimeTypesMetadataBuilder, MimeTypeMetadata,
+};
 pub use routing::{RoutingMetadata, RoutingMetadataBuilder};
+pub use trace_context::{TraceContext, TraceContextBuilder};
+pub use zipkin::{TracingMetadata, TracingMetadataBuilder};
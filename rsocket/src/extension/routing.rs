@@ -1,5 +1,8 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 
+use super::composite::CompositeMetadata;
+use super::metadata::MetadataExtension;
+use super::mime::MimeType;
 use crate::error::RSocketError;
 use crate::utils::Writeable;
 
@@ -32,6 +35,8 @@ impl RoutingMetadataBuilder {
 }
 
 impl RoutingMetadata {
+    pub const MAX_TAG_LEN: usize = MAX_ROUTING_TAG_LEN;
+
     pub fn builder() -> RoutingMetadataBuilder {
         RoutingMetadataBuilder {
             inner: RoutingMetadata { tags: vec![] },
@@ -56,6 +61,30 @@ impl RoutingMetadata {
         &self.tags
     }
 
+    /// Finds it in request metadata, which is either routing metadata itself or composite
+    /// metadata carrying it, following the metadata MIME type of the connection.
+    pub(crate) fn find(
+        metadata_mime: &MimeType,
+        metadata: &Bytes,
+    ) -> crate::Result<Option<RoutingMetadata>> {
+        if *metadata_mime == MimeType::MESSAGE_X_RSOCKET_ROUTING_V0 {
+            return Self::decode(&mut BytesMut::from(&metadata[..])).map(Some);
+        }
+        if *metadata_mime != MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0 {
+            return Ok(None);
+        }
+        for entry in CompositeMetadata::entries(metadata.clone()) {
+            let entry = entry?;
+            if entry
+                .get_mime_type()
+                .same(&MimeType::MESSAGE_X_RSOCKET_ROUTING_V0)
+            {
+                return entry.decode().map(Some);
+            }
+        }
+        Ok(None)
+    }
+
     fn decode_once(bf: &mut BytesMut) -> crate::Result<Option<String>> {
         if bf.is_empty() {
             return Ok(None);
@@ -69,6 +98,16 @@ impl RoutingMetadata {
     }
 }
 
+impl MetadataExtension for RoutingMetadata {
+    fn mime_type() -> MimeType {
+        MimeType::MESSAGE_X_RSOCKET_ROUTING_V0
+    }
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+        RoutingMetadata::decode(bf)
+    }
+}
+
 impl Writeable for RoutingMetadata {
     fn write_to(&self, bf: &mut BytesMut) {
         for tag in &self.tags {
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use bytes::Bytes;
+
+use super::composite::{CompositeMetadata, CompositeMetadataEntry};
+use super::mime::MimeType;
+use crate::error::RSocketError;
+
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// W3C trace context, propagated as the `traceparent` and `tracestate` entries of
+/// composite metadata, named after the HTTP headers of the specification.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TraceContext {
+    version: u8,
+    trace_id: u128,
+    parent_id: u64,
+    flags: u8,
+    state: Option<String>,
+}
+
+pub struct TraceContextBuilder {
+    inner: TraceContext,
+}
+
+impl TraceContextBuilder {
+    pub fn trace_id(mut self, trace_id: u128) -> Self {
+        self.inner.trace_id = trace_id;
+        self
+    }
+
+    pub fn parent_id(mut self, parent_id: u64) -> Self {
+        self.inner.parent_id = parent_id;
+        self
+    }
+
+    pub fn sampled(mut self, sampled: bool) -> Self {
+        if sampled {
+            self.inner.flags |= FLAG_SAMPLED;
+        } else {
+            self.inner.flags &= !FLAG_SAMPLED;
+        }
+        self
+    }
+
+    pub fn flags(mut self, flags: u8) -> Self {
+        self.inner.flags = flags;
+        self
+    }
+
+    /// Sets the vendor specific `tracestate`, e.g. `congo=t61rcWkgMzE`.
+    pub fn state(mut self, state: &str) -> Self {
+        self.inner.state = Some(String::from(state));
+        self
+    }
+
+    pub fn build(self) -> TraceContext {
+        self.inner
+    }
+}
+
+impl TraceContext {
+    pub const TRACEPARENT: &'static str = "traceparent";
+    pub const TRACESTATE: &'static str = "tracestate";
+
+    pub fn builder() -> TraceContextBuilder {
+        TraceContextBuilder {
+            inner: TraceContext::default(),
+        }
+    }
+
+    /// Parses a `traceparent` like `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> crate::Result<TraceContext> {
+        let invalid =
+            || RSocketError::WithDescription(format!("invalid traceparent: {}", traceparent));
+        let mut parts = traceparent.trim().split('-');
+        let mut next = |size: usize| match parts.next() {
+            Some(it) if it.len() == size && it.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(it),
+            _ => Err(invalid()),
+        };
+        let version = u8::from_str_radix(next(2)?, 16)?;
+        let trace_id = u128::from_str_radix(next(32)?, 16)?;
+        let parent_id = u64::from_str_radix(next(16)?, 16)?;
+        let flags = u8::from_str_radix(next(2)?, 16)?;
+        // future versions may append fields, version 0 must not
+        if version == 0xFF || (version == 0 && parts.next().is_some()) {
+            return Err(invalid().into());
+        }
+        if trace_id == 0 || parent_id == 0 {
+            return Err(invalid().into());
+        }
+        Ok(TraceContext {
+            version,
+            trace_id,
+            parent_id,
+            flags,
+            state: tracestate.filter(|it| !it.is_empty()).map(String::from),
+        })
+    }
+
+    /// Adds it to the composite metadata of an outgoing request, replacing any existing context.
+    pub fn inject(&self, composite: &mut CompositeMetadata) {
+        composite.remove(&MimeType::from(Self::TRACEPARENT));
+        composite.remove(&MimeType::from(Self::TRACESTATE));
+        composite.push(CompositeMetadataEntry::new(
+            MimeType::from(Self::TRACEPARENT),
+            Bytes::from(self.to_string()),
+        ));
+        if let Some(state) = &self.state {
+            composite.push(CompositeMetadataEntry::new(
+                MimeType::from(Self::TRACESTATE),
+                Bytes::from(state.clone()),
+            ));
+        }
+    }
+
+    /// Finds and parses it in the composite metadata of an incoming request.
+    pub fn extract(composite: &CompositeMetadata) -> crate::Result<Option<TraceContext>> {
+        let traceparent = match composite.find(&MimeType::from(Self::TRACEPARENT)) {
+            Some(entry) => entry.get_metadata_utf8().ok_or_else(|| {
+                RSocketError::WithDescription("invalid traceparent: not UTF-8".into())
+            })?,
+            None => return Ok(None),
+        };
+        let tracestate = composite
+            .find(&MimeType::from(Self::TRACESTATE))
+            .and_then(|it| it.get_metadata_utf8());
+        Self::parse(traceparent, tracestate).map(Some)
+    }
+
+    pub fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn get_trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    pub fn get_parent_id(&self) -> u64 {
+        self.parent_id
+    }
+
+    pub fn get_flags(&self) -> u8 {
+        self.flags
+    }
+
+    pub fn get_state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    pub fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+}
+
+/// Formats the `traceparent`.
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}-{:032x}-{:016x}-{:02x}",
+            self.version, self.trace_id, self.parent_id, self.flags
+        )
+    }
+}
@@ -0,0 +1,214 @@
+use bytes::{Buf, BufMut, BytesMut};
+
+use super::composite::CompositeMetadata;
+use super::metadata::MetadataExtension;
+use super::mime::MimeType;
+use crate::error::RSocketError;
+use crate::utils::Writeable;
+
+const FLAG_EXTENDED_TRACE_ID: u8 = 0b0000_1000;
+const FLAG_PARENT_ID: u8 = 0b0000_0100;
+const FLAG_NOT_SAMPLED: u8 = 0b0001_0000;
+const FLAG_SAMPLED: u8 = 0b0010_0000;
+const FLAG_DEBUG: u8 = 0b0100_0000;
+const FLAG_IDS: u8 = 0b1000_0000;
+
+/// Zipkin B3 propagation in the binary format of `message/x.rsocket.tracing-zipkin.v0`.
+///
+/// A trace id of zero means that no IDs are propagated, only the sampling decision.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct TracingMetadata {
+    trace_id_high: Option<u64>,
+    trace_id: u64,
+    span_id: u64,
+    parent_id: Option<u64>,
+    sampled: Option<bool>,
+    debug: bool,
+}
+
+pub struct TracingMetadataBuilder {
+    inner: TracingMetadata,
+}
+
+impl TracingMetadataBuilder {
+    pub fn trace_id(mut self, trace_id: u64) -> Self {
+        self.inner.trace_id = trace_id;
+        self
+    }
+
+    /// Sets the higher 64 bits of a 128 bit trace id.
+    pub fn trace_id_high(mut self, trace_id_high: u64) -> Self {
+        self.inner.trace_id_high = Some(trace_id_high);
+        self
+    }
+
+    pub fn span_id(mut self, span_id: u64) -> Self {
+        self.inner.span_id = span_id;
+        self
+    }
+
+    pub fn parent_id(mut self, parent_id: u64) -> Self {
+        self.inner.parent_id = Some(parent_id);
+        self
+    }
+
+    pub fn sampled(mut self, sampled: bool) -> Self {
+        self.inner.sampled = Some(sampled);
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.inner.debug = debug;
+        self
+    }
+
+    pub fn build(self) -> TracingMetadata {
+        self.inner
+    }
+}
+
+impl TracingMetadata {
+    pub fn builder() -> TracingMetadataBuilder {
+        TracingMetadataBuilder {
+            inner: TracingMetadata::default(),
+        }
+    }
+
+    pub fn decode(bf: &mut BytesMut) -> crate::Result<TracingMetadata> {
+        if bf.is_empty() {
+            return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+        }
+        let flags = bf.get_u8();
+        let mut bu = TracingMetadata::builder().debug(flags & FLAG_DEBUG != 0);
+        if flags & FLAG_SAMPLED != 0 {
+            bu = bu.sampled(true);
+        } else if flags & FLAG_NOT_SAMPLED != 0 {
+            bu = bu.sampled(false);
+        }
+        if flags & FLAG_IDS == 0 {
+            return Ok(bu.build());
+        }
+        let mut size = 16;
+        if flags & FLAG_EXTENDED_TRACE_ID != 0 {
+            size += 8;
+        }
+        if flags & FLAG_PARENT_ID != 0 {
+            size += 8;
+        }
+        if bf.len() < size {
+            return Err(RSocketError::WithDescription("require more bytes!".into()).into());
+        }
+        if flags & FLAG_EXTENDED_TRACE_ID != 0 {
+            bu = bu.trace_id_high(bf.get_u64());
+        }
+        bu = bu.trace_id(bf.get_u64()).span_id(bf.get_u64());
+        if flags & FLAG_PARENT_ID != 0 {
+            bu = bu.parent_id(bf.get_u64());
+        }
+        Ok(bu.build())
+    }
+
+    /// Adds it to the composite metadata of an outgoing request.
+    pub fn inject(&self, composite: &mut CompositeMetadata) {
+        composite.insert(self);
+    }
+
+    /// Finds and decodes it in the composite metadata of an incoming request.
+    pub fn extract(composite: &CompositeMetadata) -> crate::Result<Option<TracingMetadata>> {
+        composite.get()
+    }
+
+    pub fn get_trace_id(&self) -> u64 {
+        self.trace_id
+    }
+
+    pub fn get_trace_id_high(&self) -> Option<u64> {
+        self.trace_id_high
+    }
+
+    pub fn get_span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    pub fn get_parent_id(&self) -> Option<u64> {
+        self.parent_id
+    }
+
+    /// Returns the sampling decision, `None` if it is deferred to the receiver.
+    pub fn is_sampled(&self) -> Option<bool> {
+        self.sampled
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    /// Returns false if only the sampling decision is propagated.
+    pub fn has_ids(&self) -> bool {
+        self.trace_id != 0 || self.trace_id_high.is_some_and(|it| it != 0)
+    }
+
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.debug {
+            flags |= FLAG_DEBUG;
+        }
+        match self.sampled {
+            Some(true) => flags |= FLAG_SAMPLED,
+            Some(false) => flags |= FLAG_NOT_SAMPLED,
+            None => (),
+        }
+        if !self.has_ids() {
+            return flags;
+        }
+        flags |= FLAG_IDS;
+        if self.trace_id_high.is_some() {
+            flags |= FLAG_EXTENDED_TRACE_ID;
+        }
+        if self.parent_id.is_some() {
+            flags |= FLAG_PARENT_ID;
+        }
+        flags
+    }
+}
+
+impl MetadataExtension for TracingMetadata {
+    fn mime_type() -> MimeType {
+        MimeType::MESSAGE_X_RSOCKET_TRACING_ZIPKIN_V0
+    }
+
+    fn decode(bf: &mut BytesMut) -> crate::Result<Self> {
+        TracingMetadata::decode(bf)
+    }
+}
+
+impl Writeable for TracingMetadata {
+    fn write_to(&self, bf: &mut BytesMut) {
+        bf.put_u8(self.flags());
+        if !self.has_ids() {
+            return;
+        }
+        if let Some(high) = self.trace_id_high {
+            bf.put_u64(high);
+        }
+        bf.put_u64(self.trace_id);
+        bf.put_u64(self.span_id);
+        if let Some(parent_id) = self.parent_id {
+            bf.put_u64(parent_id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        if !self.has_ids() {
+            return 1;
+        }
+        let mut n = 17;
+        if self.trace_id_high.is_some() {
+            n += 8;
+        }
+        if self.parent_id.is_some() {
+            n += 8;
+        }
+        n
+    }
+}
@@ -195,6 +195,11 @@ impl SetupBuilder {
         self
     }
 
+    pub fn set_lease(mut self) -> Self {
+        self.flag |= Frame::FLAG_LEASE;
+        self
+    }
+
     pub fn set_token(mut self, token: Bytes) -> Self {
         self.value.token = Some(token);
         self.flag |= Frame::FLAG_RESUME;
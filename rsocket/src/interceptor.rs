@@ -0,0 +1,175 @@
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use dashmap::DashMap;
+
+use crate::payload::SetupPayload;
+use crate::spi::RSocket;
+use crate::Result;
+
+/// Describes an established connection, shared by all interceptors of it.
+///
+/// Besides the SETUP payload and the peer address, it carries typed attributes so that
+/// interceptors can hand values, e.g. an authenticated principal, to each other or to
+/// the RSockets they wrap.
+#[derive(Clone)]
+pub struct ConnectionInfo {
+    inner: Arc<ConnectionInfoInner>,
+}
+
+struct ConnectionInfoInner {
+    setup: SetupPayload,
+    peer_addr: Option<SocketAddr>,
+    attributes: DashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl fmt::Debug for ConnectionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionInfo")
+            .field("peer_addr", &self.inner.peer_addr)
+            .finish()
+    }
+}
+
+impl ConnectionInfo {
+    pub(crate) fn new(setup: SetupPayload, peer_addr: Option<SocketAddr>) -> ConnectionInfo {
+        ConnectionInfo {
+            inner: Arc::new(ConnectionInfoInner {
+                setup,
+                peer_addr,
+                attributes: DashMap::new(),
+            }),
+        }
+    }
+
+    pub fn setup(&self) -> &SetupPayload {
+        &self.inner.setup
+    }
+
+    /// Returns the address of the remote peer, if the transport knows it.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer_addr
+    }
+
+    /// Stores an attribute, replacing the previous one of the same type.
+    pub fn insert<T>(&self, value: T)
+    where
+        T: Any + Send + Sync,
+    {
+        self.inner
+            .attributes
+            .insert(TypeId::of::<T>(), Box::new(value));
+    }
+
+    pub fn get<T>(&self) -> Option<T>
+    where
+        T: Any + Send + Sync + Clone,
+    {
+        self.inner
+            .attributes
+            .get(&TypeId::of::<T>())
+            .and_then(|it| it.downcast_ref::<T>().cloned())
+    }
+
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Any + Send + Sync,
+    {
+        self.inner.attributes.contains_key(&TypeId::of::<T>())
+    }
+}
+
+/// Hooks into every connection of a client or a server.
+///
+/// An interceptor can reject a connection in `on_setup`, and decorate the requester and
+/// responder RSockets of it, which allows it to rewrite payloads or short-circuit requests
+/// with errors. Wrappers should delegate `availability` and `is_closed` to the wrapped RSocket.
+///
+/// Interceptors are applied in the order they are added, the first one being the outermost.
+///
+/// # Example
+/// ```no_run,ignore
+/// struct Logging;
+///
+/// #[async_trait]
+/// impl Interceptor for Logging {
+///     async fn on_setup(&self, info: &ConnectionInfo) -> Result<()> {
+///         info!("connection established: peer={:?}", info.peer_addr());
+///         Ok(())
+///     }
+/// }
+///
+/// RSocketFactory::receive()
+///     .transport(TcpServerTransport::from("127.0.0.1:7878"))
+///     .interceptor(Logging)
+///     .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+///     .serve()
+///     .await
+/// ```
+#[async_trait]
+pub trait Interceptor: Send + Sync {
+    /// Called before the connection is used, returning an error rejects it.
+    ///
+    /// On the server side the client receives a REJECTED_SETUP error, on the client side
+    /// `ClientBuilder::start` fails.
+    async fn on_setup(&self, info: &ConnectionInfo) -> Result<()> {
+        Ok(())
+    }
+
+    /// Decorates the RSocket used to send requests to the peer.
+    fn requester(&self, info: &ConnectionInfo, requester: Box<dyn RSocket>) -> Box<dyn RSocket> {
+        requester
+    }
+
+    /// Decorates the RSocket which handles requests of the peer.
+    fn responder(&self, info: &ConnectionInfo, responder: Box<dyn RSocket>) -> Box<dyn RSocket> {
+        responder
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct InterceptorChain {
+    interceptors: Vec<Arc<dyn Interceptor>>,
+}
+
+impl InterceptorChain {
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        self.interceptors.push(interceptor);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.interceptors.is_empty()
+    }
+
+    pub(crate) async fn on_setup(&self, info: &ConnectionInfo) -> Result<()> {
+        for it in self.interceptors.iter() {
+            it.on_setup(info).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn requester(
+        &self,
+        info: &ConnectionInfo,
+        mut requester: Box<dyn RSocket>,
+    ) -> Box<dyn RSocket> {
+        for it in self.interceptors.iter().rev() {
+            requester = it.requester(info, requester);
+        }
+        requester
+    }
+
+    pub(crate) fn responder(
+        &self,
+        info: &ConnectionInfo,
+        mut responder: Box<dyn RSocket>,
+    ) -> Box<dyn RSocket> {
+        for it in self.interceptors.iter().rev() {
+            responder = it.responder(info, responder);
+        }
+        responder
+    }
+}
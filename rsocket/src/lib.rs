@@ -108,10 +108,14 @@ extern crate cfg_if;
 #[doc(hidden)]
 pub mod macros;
 
+pub mod balancer;
 pub mod error;
 pub mod extension;
+pub mod interceptor;
 pub mod prelude;
+pub mod router;
 pub mod runtime;
+pub mod security;
 pub mod transport;
 pub mod utils;
 
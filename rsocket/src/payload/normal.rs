@@ -1,6 +1,7 @@
 use bytes::Bytes;
 
 use super::misc::bytes_to_utf8;
+use crate::extension::{CompositeMetadata, CompositeMetadataEntries, MetadataExtension};
 use crate::frame;
 
 #[derive(Debug, Clone)]
@@ -80,6 +81,26 @@ impl Payload {
         bytes_to_utf8(&self.m)
     }
 
+    /// Decodes the metadata as composite metadata lazily, sharing its bytes.
+    pub fn composite_metadata(&self) -> CompositeMetadataEntries {
+        CompositeMetadata::entries(self.m.clone().unwrap_or_default())
+    }
+
+    /// Finds and decodes the extension in composite metadata, decoding no further entries.
+    pub fn metadata_extension<T>(&self) -> crate::Result<Option<T>>
+    where
+        T: MetadataExtension,
+    {
+        let mime_type = T::mime_type();
+        for entry in self.composite_metadata() {
+            let entry = entry?;
+            if entry.get_mime_type().same(&mime_type) {
+                return entry.decode().map(Some);
+            }
+        }
+        Ok(None)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -4,15 +4,18 @@ use bytes::Bytes;
 
 use super::misc::bytes_to_utf8;
 use crate::frame::Setup;
+use crate::interceptor::ConnectionInfo;
 use crate::utils::DEFAULT_MIME_TYPE;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SetupPayload {
     m: Option<Bytes>,
     d: Option<Bytes>,
     keepalive: (Duration, Duration),
     mime_m: Option<Bytes>,
     mime_d: Option<Bytes>,
+    lease: bool,
+    connection: Option<ConnectionInfo>,
 }
 
 #[derive(Debug)]
@@ -35,6 +38,8 @@ impl SetupPayloadBuilder {
                 keepalive: (Duration::from_secs(20), Duration::from_secs(90)),
                 mime_m: Some(Bytes::from(DEFAULT_MIME_TYPE.to_owned())),
                 mime_d: Some(Bytes::from(DEFAULT_MIME_TYPE.to_owned())),
+                lease: false,
+                connection: None,
             },
         }
     }
@@ -95,6 +100,11 @@ impl SetupPayloadBuilder {
         self
     }
 
+    pub fn set_honor_lease(mut self, lease: bool) -> Self {
+        self.inner.lease = lease;
+        self
+    }
+
     pub fn build(self) -> SetupPayload {
         self.inner
     }
@@ -128,6 +138,23 @@ impl SetupPayload {
     pub fn data_mime_type(&self) -> Option<&str> {
         bytes_to_utf8(&self.mime_d)
     }
+
+    pub fn honor_lease(&self) -> bool {
+        self.lease
+    }
+
+    /// Returns the connection which sent this SETUP, along with the attributes set by its
+    /// interceptors, e.g. the `Principal` authenticated by `Security`.
+    ///
+    /// It is only known to the acceptor of a server.
+    pub fn connection(&self) -> Option<&ConnectionInfo> {
+        self.connection.as_ref()
+    }
+
+    pub(crate) fn with_connection(mut self, connection: ConnectionInfo) -> SetupPayload {
+        self.connection = Some(connection);
+        self
+    }
 }
 
 impl From<Setup> for SetupPayload {
@@ -0,0 +1,321 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use crate::error::RSocketError;
+use crate::extension::{MimeType, RoutingMetadata};
+use crate::payload::Payload;
+use crate::spi::{Flux, RSocket, ServerResponder};
+use crate::Result;
+
+mod pattern;
+
+pub use pattern::{RoutePattern, Variables};
+
+type Mono<T> = Pin<Box<dyn Send + Future<Output = Result<T>>>>;
+type FnUnit = Box<dyn Send + Sync + Fn(Payload, Variables) -> Mono<()>>;
+type FnMono = Box<dyn Send + Sync + Fn(Payload, Variables) -> Mono<Option<Payload>>>;
+type FnFlux = Box<dyn Send + Sync + Fn(Payload, Variables) -> Flux<Result<Payload>>>;
+type FnChannel =
+    Box<dyn Send + Sync + Fn(Flux<Result<Payload>>, Variables) -> Flux<Result<Payload>>>;
+
+/// Handlers of a route, one for each interaction model.
+#[derive(Default)]
+struct Handlers {
+    metadata_push: Option<FnUnit>,
+    fire_and_forget: Option<FnUnit>,
+    request_response: Option<FnMono>,
+    request_stream: Option<FnFlux>,
+    request_channel: Option<FnChannel>,
+}
+
+struct Routes {
+    /// Ordered by the precedence of patterns.
+    handlers: Vec<(RoutePattern, Handlers)>,
+    fallback: Option<Box<dyn RSocket>>,
+}
+
+/// A responder dispatching requests to handlers by their route, i.e. the first tag of the
+/// routing metadata carried alone or in composite metadata, like Spring's `@MessageMapping`.
+///
+/// Handlers are registered with `RoutePattern`s, and receive the variables captured from the
+/// route. When several patterns match, the most specific one with a handler for the
+/// interaction model wins.
+///
+/// Requests without a route are rejected with INVALID, and requests whose route has no
+/// handler for the interaction model with REJECTED, unless a fallback RSocket is set.
+///
+/// # Example
+/// ```no_run,ignore
+/// let router = Router::builder()
+///     .request_response("echo", |req: Payload, _| async move { Ok(Some(req)) })
+///     .request_response("user.{id}", |_req: Payload, vars: Variables| async move {
+///         let id = vars.get("id").unwrap();
+///         Ok(Some(Payload::builder().set_data_utf8(id).build()))
+///     })
+///     .request_stream("ticks", |_req: Payload, _| -> Flux<Result<Payload>> {
+///         Box::pin(stream! {
+///             for i in 0..3 {
+///                 yield Ok(Payload::builder().set_data_utf8(&i.to_string()).build());
+///             }
+///         })
+///     })
+///     .build();
+///
+/// RSocketFactory::receive()
+///     .transport(TcpServerTransport::from("127.0.0.1:7878"))
+///     .acceptor(router.acceptor())
+///     .serve()
+///     .await
+/// ```
+#[derive(Clone)]
+pub struct Router {
+    routes: Arc<Routes>,
+    metadata_mime_type: MimeType,
+}
+
+pub struct RouterBuilder {
+    handlers: Vec<(RoutePattern, Handlers)>,
+    fallback: Option<Box<dyn RSocket>>,
+    metadata_mime_type: MimeType,
+}
+
+impl RouterBuilder {
+    /// Sets the MIME type of request metadata, which is composite metadata by default.
+    ///
+    /// `Router::acceptor` takes it from the SETUP frame of each connection instead.
+    pub fn metadata_mime_type<I>(mut self, mime_type: I) -> Self
+    where
+        I: Into<MimeType>,
+    {
+        self.metadata_mime_type = mime_type.into();
+        self
+    }
+
+    pub fn metadata_push<F, Fut>(mut self, route: &str, handler: F) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Payload, Variables) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<()>>,
+    {
+        self.route(route).metadata_push =
+            Some(Box::new(move |req, vars| Box::pin(handler(req, vars))));
+        self
+    }
+
+    pub fn fire_and_forget<F, Fut>(mut self, route: &str, handler: F) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Payload, Variables) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<()>>,
+    {
+        self.route(route).fire_and_forget =
+            Some(Box::new(move |req, vars| Box::pin(handler(req, vars))));
+        self
+    }
+
+    pub fn request_response<F, Fut>(mut self, route: &str, handler: F) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Payload, Variables) -> Fut,
+        Fut: Send + 'static + Future<Output = Result<Option<Payload>>>,
+    {
+        self.route(route).request_response =
+            Some(Box::new(move |req, vars| Box::pin(handler(req, vars))));
+        self
+    }
+
+    pub fn request_stream<F>(mut self, route: &str, handler: F) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Payload, Variables) -> Flux<Result<Payload>>,
+    {
+        self.route(route).request_stream = Some(Box::new(handler));
+        self
+    }
+
+    /// Registers a channel handler, whose inputs start with the payload carrying the route.
+    pub fn request_channel<F>(mut self, route: &str, handler: F) -> Self
+    where
+        F: Send + Sync + 'static + Fn(Flux<Result<Payload>>, Variables) -> Flux<Result<Payload>>,
+    {
+        self.route(route).request_channel = Some(Box::new(handler));
+        self
+    }
+
+    /// Handles requests which match no handler, instead of rejecting them.
+    pub fn fallback<R>(mut self, rsocket: R) -> Self
+    where
+        R: RSocket + 'static,
+    {
+        self.fallback = Some(Box::new(rsocket));
+        self
+    }
+
+    pub fn build(mut self) -> Router {
+        // stable, so that equally specific patterns keep the order of registration
+        self.handlers
+            .sort_by_key(|(pattern, _)| pattern.precedence());
+        Router {
+            routes: Arc::new(Routes {
+                handlers: self.handlers,
+                fallback: self.fallback,
+            }),
+            metadata_mime_type: self.metadata_mime_type,
+        }
+    }
+
+    /// Returns the handlers of a pattern, panics if the pattern is invalid.
+    fn route(&mut self, pattern: &str) -> &mut Handlers {
+        let i = match self
+            .handlers
+            .iter()
+            .position(|(it, _)| it.as_str() == pattern)
+        {
+            Some(i) => i,
+            None => {
+                let pattern = RoutePattern::parse(pattern).unwrap_or_else(|e| panic!("{}", e));
+                self.handlers.push((pattern, Handlers::default()));
+                self.handlers.len() - 1
+            }
+        };
+        &mut self.handlers[i].1
+    }
+}
+
+impl Router {
+    pub fn builder() -> RouterBuilder {
+        RouterBuilder {
+            handlers: vec![],
+            fallback: None,
+            metadata_mime_type: MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0,
+        }
+    }
+
+    /// Returns a server acceptor responding with this router, following the metadata MIME
+    /// type declared in the SETUP frame of each connection.
+    pub fn acceptor(self) -> ServerResponder {
+        Box::new(move |setup, _sending_socket| {
+            let mut router = self.clone();
+            if let Some(mime_type) = setup.metadata_mime_type() {
+                router.metadata_mime_type = MimeType::from(mime_type);
+            }
+            Ok(Box::new(router))
+        })
+    }
+
+    /// Finds the most specific handler picked from the handlers of matching patterns.
+    fn find<'a, H, F>(&'a self, req: &Payload, pick: F) -> Result<Route<'a, H>>
+    where
+        F: Fn(&'a Handlers) -> Option<&'a H>,
+    {
+        let routing = match req.metadata() {
+            Some(metadata) => RoutingMetadata::find(&self.metadata_mime_type, metadata)
+                .map_err(|e| RSocketError::RequestInvalid(e.to_string()))?,
+            None => None,
+        };
+        let route = match routing.and_then(|it| it.get_tags().first().cloned()) {
+            Some(it) => it,
+            None => return Ok(Route::Missing),
+        };
+        for (pattern, handlers) in self.routes.handlers.iter() {
+            if let Some(handler) = pick(handlers) {
+                if let Some(vars) = pattern.matches(&route) {
+                    return Ok(Route::Found(handler, vars));
+                }
+            }
+        }
+        Ok(Route::NotFound(route))
+    }
+
+    fn unmatched<H>(&self, route: Route<'_, H>) -> Result<&dyn RSocket> {
+        if let Some(fallback) = &self.routes.fallback {
+            return Ok(fallback.as_ref());
+        }
+        let e = match route {
+            Route::Missing => RSocketError::RequestInvalid("missing route".into()),
+            Route::Found(..) => unreachable!(),
+            Route::NotFound(route) => {
+                RSocketError::RequestRejected(format!("no handler for route: {}", route))
+            }
+        };
+        Err(e.into())
+    }
+}
+
+enum Route<'a, H> {
+    Found(&'a H, Variables),
+    NotFound(String),
+    Missing,
+}
+
+#[async_trait]
+impl RSocket for Router {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        match self.find(&req, |it| it.metadata_push.as_ref())? {
+            Route::Found(handler, vars) => handler(req, vars).await,
+            route => self.unmatched(route)?.metadata_push(req).await,
+        }
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        match self.find(&req, |it| it.fire_and_forget.as_ref())? {
+            Route::Found(handler, vars) => handler(req, vars).await,
+            route => self.unmatched(route)?.fire_and_forget(req).await,
+        }
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        match self.find(&req, |it| it.request_response.as_ref())? {
+            Route::Found(handler, vars) => handler(req, vars).await,
+            route => self.unmatched(route)?.request_response(req).await,
+        }
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        let route = match self.find(&req, |it| it.request_stream.as_ref()) {
+            Ok(Route::Found(handler, vars)) => return handler(req, vars),
+            Ok(route) => route,
+            Err(e) => return Box::pin(stream::iter(Some(Err(e)))),
+        };
+        match self.unmatched(route) {
+            Ok(fallback) => fallback.request_stream(req),
+            Err(e) => Box::pin(stream::iter(Some(Err(e)))),
+        }
+    }
+
+    fn request_channel(&self, mut reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        let router = self.clone();
+        Box::pin(crate::stream! {
+            // the first payload carries the route of the channel
+            let first = match reqs.next().await {
+                Some(Ok(it)) => it,
+                Some(Err(e)) => {
+                    yield Err(e);
+                    return;
+                }
+                None => return,
+            };
+            let route = match router.find(&first, |it| it.request_channel.as_ref()) {
+                Ok(it) => it,
+                Err(e) => {
+                    yield Err(e);
+                    return;
+                }
+            };
+            let reqs = Box::pin(stream::once(async move { Ok(first) }).chain(reqs));
+            let mut results = match route {
+                Route::Found(handler, vars) => handler(reqs, vars),
+                route => match router.unmatched(route) {
+                    Ok(fallback) => fallback.request_channel(reqs),
+                    Err(e) => {
+                        yield Err(e);
+                        return;
+                    }
+                },
+            };
+            while let Some(next) = results.next().await {
+                yield next;
+            }
+        })
+    }
+}
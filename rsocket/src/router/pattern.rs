@@ -0,0 +1,227 @@
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::error::RSocketError;
+use crate::Result;
+
+const SEPARATOR: char = '.';
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Segment {
+    Literal(String),
+    Variable(String),
+    /// `*`, matching exactly one segment.
+    Wildcard,
+    /// `**`, matching zero or more segments.
+    MultiWildcard,
+}
+
+/// A Spring style route pattern of segments separated by `.`, e.g. `user.{id}.orders`.
+///
+/// Besides literal segments, a segment can be:
+/// - `{name}`, matching one segment and capturing it as a variable.
+/// - `*`, matching exactly one segment.
+/// - `**`, matching zero or more segments.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RoutePattern {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+/// Variables captured by a `RoutePattern` from a route, in the order of the pattern.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Variables {
+    inner: Vec<(String, String)>,
+}
+
+impl RoutePattern {
+    pub fn parse(pattern: &str) -> Result<RoutePattern> {
+        let invalid = |desc: &str| -> anyhow::Error {
+            let desc = format!("invalid route pattern {}: {}", pattern, desc);
+            RSocketError::WithDescription(desc).into()
+        };
+        let mut segments = vec![];
+        for it in pattern.split(SEPARATOR) {
+            let segment = match it {
+                "" => return Err(invalid("empty segment")),
+                "*" => Segment::Wildcard,
+                "**" => {
+                    if segments.last() == Some(&Segment::MultiWildcard) {
+                        return Err(invalid("consecutive '**'"));
+                    }
+                    Segment::MultiWildcard
+                }
+                _ if it.starts_with('{') && it.ends_with('}') => {
+                    let name = &it[1..it.len() - 1];
+                    if name.is_empty() || name.contains(['{', '}', '*']) {
+                        return Err(invalid("bad variable name"));
+                    }
+                    let duplicated = segments
+                        .iter()
+                        .any(|s| matches!(s, Segment::Variable(v) if v == name));
+                    if duplicated {
+                        return Err(invalid("duplicated variable"));
+                    }
+                    Segment::Variable(String::from(name))
+                }
+                _ if it.contains(['{', '}', '*']) => {
+                    return Err(invalid("wildcards and variables must be whole segments"))
+                }
+                _ => Segment::Literal(String::from(it)),
+            };
+            segments.push(segment);
+        }
+        Ok(RoutePattern {
+            source: String::from(pattern),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns true if the pattern has neither variables nor wildcards.
+    pub fn is_literal(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|it| matches!(it, Segment::Literal(_)))
+    }
+
+    /// Matches a route, returning the captured variables.
+    pub fn matches(&self, route: &str) -> Option<Variables> {
+        if self.is_literal() {
+            return if route == self.source {
+                Some(Variables::default())
+            } else {
+                None
+            };
+        }
+        let parts: Vec<&str> = route.split(SEPARATOR).collect();
+        let mut variables = Variables::default();
+        if Self::match_segments(&self.segments, &parts, &mut variables) {
+            Some(variables)
+        } else {
+            None
+        }
+    }
+
+    /// Builds a route by substituting the variables with the arguments in order. Arguments
+    /// must be non-empty and must not contain `.`, so that each fills exactly one segment.
+    pub fn expand(&self, args: &[&dyn fmt::Display]) -> Result<String> {
+        let mut args = args.iter();
+        let mut route = String::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                route.push(SEPARATOR);
+            }
+            match segment {
+                Segment::Literal(s) => route.push_str(s),
+                Segment::Variable(name) => match args.next() {
+                    Some(arg) => {
+                        // the argument must fill a single segment
+                        let arg = arg.to_string();
+                        if arg.is_empty() || arg.contains(SEPARATOR) {
+                            let desc = format!("invalid argument of variable {}: {:?}", name, arg);
+                            return Err(RSocketError::WithDescription(desc).into());
+                        }
+                        route.push_str(&arg);
+                    }
+                    None => {
+                        let desc = format!("missing argument of variable {}", name);
+                        return Err(RSocketError::WithDescription(desc).into());
+                    }
+                },
+                _ => {
+                    let desc = format!("cannot expand wildcards of {}", self.source);
+                    return Err(RSocketError::WithDescription(desc).into());
+                }
+            }
+        }
+        if args.next().is_some() {
+            let desc = format!("too many arguments for {}", self.source);
+            return Err(RSocketError::WithDescription(desc).into());
+        }
+        Ok(route)
+    }
+
+    /// Returns the names of the variables, in the order of the pattern.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().filter_map(|it| match it {
+            Segment::Variable(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Orders patterns from the most specific one: patterns with fewer `**`, then with fewer
+    /// variables and `*`, then with longer literals come first.
+    pub fn precedence(&self) -> impl Ord {
+        let mut multi = 0usize;
+        let mut single = 0usize;
+        let mut literal = 0usize;
+        for it in &self.segments {
+            match it {
+                Segment::MultiWildcard => multi += 1,
+                Segment::Wildcard | Segment::Variable(_) => single += 1,
+                Segment::Literal(s) => literal += s.len(),
+            }
+        }
+        (multi, single, Reverse(literal))
+    }
+
+    fn match_segments(segments: &[Segment], parts: &[&str], variables: &mut Variables) -> bool {
+        let (segment, rest) = match segments.split_first() {
+            Some(it) => it,
+            None => return parts.is_empty(),
+        };
+        if *segment == Segment::MultiWildcard {
+            // try to consume as few segments as possible
+            let captured = variables.inner.len();
+            for skip in 0..=parts.len() {
+                if Self::match_segments(rest, &parts[skip..], variables) {
+                    return true;
+                }
+                variables.inner.truncate(captured);
+            }
+            return false;
+        }
+        let (part, parts) = match parts.split_first() {
+            Some(it) => it,
+            None => return false,
+        };
+        match segment {
+            Segment::Literal(s) if s != part => return false,
+            Segment::Variable(_) | Segment::Wildcard if part.is_empty() => return false,
+            Segment::Variable(name) => variables.inner.push((name.clone(), String::from(*part))),
+            _ => (),
+        }
+        Self::match_segments(rest, parts, variables)
+    }
+}
+
+impl fmt::Display for RoutePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Variables {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.inner
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.inner.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+}
@@ -13,3 +13,33 @@ where
         }
     }
 }
+
+cfg_if! {
+    if #[cfg(not(target_arch = "wasm32"))] {
+        pub(crate) use std::time::Instant;
+    } else {
+        use std::time::Duration;
+
+        /// A point in time read from `Date.now()`, since `std::time::Instant` panics on wasm32.
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+        pub(crate) struct Instant(f64);
+
+        impl Instant {
+            pub(crate) fn now() -> Instant {
+                Instant(js_sys::Date::now())
+            }
+
+            pub(crate) fn elapsed(&self) -> Duration {
+                Duration::from_secs_f64((Self::now().0 - self.0).max(0.0) / 1000.0)
+            }
+        }
+
+        impl std::ops::Add<Duration> for Instant {
+            type Output = Instant;
+
+            fn add(self, rhs: Duration) -> Instant {
+                Instant(self.0 + rhs.as_secs_f64() * 1000.0)
+            }
+        }
+    }
+}
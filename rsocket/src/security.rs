@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::{Bytes, BytesMut};
+use futures::{stream, StreamExt};
+
+use crate::error::RSocketError;
+use crate::extension::{AuthMetadata, CompositeMetadata, MimeType, RoutingMetadata};
+use crate::interceptor::{ConnectionInfo, Interceptor};
+use crate::payload::Payload;
+use crate::router::RoutePattern;
+use crate::spi::{Flux, RSocket};
+use crate::Result;
+
+/// An authenticated user, attached to the `ConnectionInfo` of its connection.
+///
+/// Server acceptors find it with `setup.connection().and_then(|it| it.get::<Principal>())`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Principal {
+    name: String,
+    roles: HashSet<String>,
+}
+
+impl Principal {
+    pub fn new<I, S>(name: &str, roles: I) -> Principal
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Principal {
+            name: String::from(name),
+            roles: roles.into_iter().map(|it| it.into()).collect(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn roles(&self) -> impl Iterator<Item = &str> {
+        self.roles.iter().map(|it| it.as_str())
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains(role)
+    }
+}
+
+/// Verifies credentials, returning an error rejects them.
+#[async_trait]
+pub trait Authenticator: Send + Sync {
+    async fn authenticate(&self, auth: &AuthMetadata) -> Result<Principal>;
+}
+
+#[async_trait]
+impl<F, Fut> Authenticator for F
+where
+    F: Send + Sync + Fn(AuthMetadata) -> Fut,
+    Fut: Send + Future<Output = Result<Principal>>,
+{
+    async fn authenticate(&self, auth: &AuthMetadata) -> Result<Principal> {
+        self(auth.clone()).await
+    }
+}
+
+struct Rule {
+    pattern: RoutePattern,
+    role: String,
+}
+
+/// Authenticates connections and authorizes requests of a server, by the authentication
+/// and routing metadata carried in the SETUP frame and in requests.
+///
+/// The SETUP frame must carry credentials, otherwise the connection is rejected with
+/// REJECTED_SETUP. A request may carry credentials of its own, which take precedence over
+/// those of the connection. Requests whose route is not granted by the rules are rejected
+/// with REJECTED before they reach the responder. Once rules are set, so are requests without
+/// a route, unless `SecurityBuilder::allow_unrouted` allows them.
+///
+/// # Example
+/// ```no_run,ignore
+/// let security = Security::builder()
+///     .authenticator(|auth: AuthMetadata| async move {
+///         match (auth.get_username(), auth.get_password()) {
+///             (Some("admin"), Some("secret")) => Ok(Principal::new("admin", ["admin"])),
+///             _ => Err(RSocketError::WithDescription("bad credentials".into()).into()),
+///         }
+///     })
+///     .rule("admin.**", "admin")
+///     .build();
+///
+/// RSocketFactory::receive()
+///     .transport(TcpServerTransport::from("127.0.0.1:7878"))
+///     .interceptor(security)
+///     .acceptor(Box::new(|_setup, _socket| Ok(Box::new(EchoRSocket))))
+///     .serve()
+///     .await
+/// ```
+pub struct Security {
+    authenticator: Arc<dyn Authenticator>,
+    rules: Arc<Vec<Rule>>,
+    allow_unrouted: bool,
+}
+
+pub struct SecurityBuilder {
+    authenticator: Option<Arc<dyn Authenticator>>,
+    rules: Vec<Rule>,
+    allow_unrouted: bool,
+}
+
+impl SecurityBuilder {
+    pub fn authenticator<A>(mut self, authenticator: A) -> Self
+    where
+        A: Authenticator + 'static,
+    {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Requires the role for routes matching the pattern, a `RoutePattern` like the ones of
+    /// `Router`: `*` matches one segment and `**` any number of segments.
+    ///
+    /// Rules are checked in the order they are added and only the first matching one applies.
+    /// Routes matching no rule only require authentication. Panics if the pattern is invalid.
+    pub fn rule(mut self, pattern: &str, role: &str) -> Self {
+        let pattern = RoutePattern::parse(pattern).unwrap_or_else(|e| panic!("{}", e));
+        self.rules.push(Rule {
+            pattern,
+            role: String::from(role),
+        });
+        self
+    }
+
+    /// Lets authenticated requests without a route through despite the rules, false by default.
+    pub fn allow_unrouted(mut self, allow: bool) -> Self {
+        self.allow_unrouted = allow;
+        self
+    }
+
+    pub fn build(self) -> Security {
+        Security {
+            authenticator: self.authenticator.expect("missing authenticator"),
+            rules: Arc::new(self.rules),
+            allow_unrouted: self.allow_unrouted,
+        }
+    }
+}
+
+impl Security {
+    pub fn builder() -> SecurityBuilder {
+        SecurityBuilder {
+            authenticator: None,
+            rules: vec![],
+            allow_unrouted: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Interceptor for Security {
+    async fn on_setup(&self, info: &ConnectionInfo) -> Result<()> {
+        let setup = info.setup();
+        let auth = match Metadata::parse(setup.metadata_mime_type(), setup.metadata())?.auth {
+            Some(auth) => auth,
+            None => return Err(RSocketError::RejectedSetup("missing credentials".into()).into()),
+        };
+        let principal = self
+            .authenticator
+            .authenticate(&auth)
+            .await
+            .map_err(|e| RSocketError::RejectedSetup(e.to_string()))?;
+        info.insert(principal);
+        Ok(())
+    }
+
+    fn responder(&self, info: &ConnectionInfo, responder: Box<dyn RSocket>) -> Box<dyn RSocket> {
+        Box::new(Secured {
+            inner: Arc::from(responder),
+            guard: Arc::new(Guard {
+                authenticator: self.authenticator.clone(),
+                rules: self.rules.clone(),
+                allow_unrouted: self.allow_unrouted,
+                principal: info.get::<Principal>(),
+                metadata_mime: info.setup().metadata_mime_type().map(String::from),
+            }),
+        })
+    }
+}
+
+/// The routing and authentication metadata of a payload.
+#[derive(Default)]
+struct Metadata {
+    route: Option<String>,
+    auth: Option<AuthMetadata>,
+}
+
+impl Metadata {
+    fn parse(mime: Option<&str>, metadata: Option<&Bytes>) -> Result<Metadata> {
+        let (mime, metadata) = match (mime, metadata) {
+            (Some(mime), Some(metadata)) => (MimeType::from(mime), metadata),
+            _ => return Ok(Metadata::default()),
+        };
+        let mut bf = BytesMut::from(&metadata[..]);
+        if mime == MimeType::MESSAGE_X_RSOCKET_AUTHENTICATION_V0 {
+            return Ok(Metadata {
+                route: None,
+                auth: Some(AuthMetadata::decode(&mut bf)?),
+            });
+        }
+        if mime == MimeType::MESSAGE_X_RSOCKET_ROUTING_V0 {
+            return Ok(Metadata {
+                route: Self::first_tag(&mut bf)?,
+                auth: None,
+            });
+        }
+        if mime != MimeType::MESSAGE_X_RSOCKET_COMPOSITE_METADATA_V0 {
+            return Ok(Metadata::default());
+        }
+        let composite = CompositeMetadata::decode(&mut bf)?;
+        let routing: Option<RoutingMetadata> = composite.get()?;
+        Ok(Metadata {
+            route: routing.and_then(|it| it.get_tags().first().cloned()),
+            auth: composite.get()?,
+        })
+    }
+
+    fn first_tag(bf: &mut BytesMut) -> Result<Option<String>> {
+        let routing = RoutingMetadata::decode(bf)?;
+        Ok(routing.get_tags().first().cloned())
+    }
+}
+
+struct Guard {
+    authenticator: Arc<dyn Authenticator>,
+    rules: Arc<Vec<Rule>>,
+    allow_unrouted: bool,
+    principal: Option<Principal>,
+    metadata_mime: Option<String>,
+}
+
+impl Guard {
+    async fn check(&self, req: &Payload) -> Result<()> {
+        let metadata = Metadata::parse(self.metadata_mime.as_deref(), req.metadata())
+            .map_err(|e| RSocketError::RequestInvalid(e.to_string()))?;
+        let principal = match metadata.auth {
+            Some(auth) => self
+                .authenticator
+                .authenticate(&auth)
+                .await
+                .map_err(|e| RSocketError::RequestRejected(e.to_string()))?,
+            None => match &self.principal {
+                Some(it) => it.clone(),
+                None => return Err(RSocketError::RequestRejected("unauthenticated".into()).into()),
+            },
+        };
+        let route = match metadata.route {
+            Some(it) => it,
+            None if self.rules.is_empty() || self.allow_unrouted => return Ok(()),
+            None => return Err(RSocketError::RequestRejected("missing route".into()).into()),
+        };
+        let rule = self
+            .rules
+            .iter()
+            .find(|it| it.pattern.matches(&route).is_some());
+        match rule {
+            Some(rule) if !principal.has_role(&rule.role) => {
+                let desc = format!("access denied: {} to {}", principal.name(), route);
+                Err(RSocketError::RequestRejected(desc).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+struct Secured {
+    inner: Arc<dyn RSocket>,
+    guard: Arc<Guard>,
+}
+
+#[async_trait]
+impl RSocket for Secured {
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        self.guard.check(&req).await?;
+        self.inner.metadata_push(req).await
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        self.guard.check(&req).await?;
+        self.inner.fire_and_forget(req).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        self.guard.check(&req).await?;
+        self.inner.request_response(req).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        let inner = self.inner.clone();
+        let guard = self.guard.clone();
+        Box::pin(crate::stream! {
+            if let Err(e) = guard.check(&req).await {
+                yield Err(e);
+                return;
+            }
+            let mut results = inner.request_stream(req);
+            while let Some(next) = results.next().await {
+                yield next;
+            }
+        })
+    }
+
+    fn request_channel(&self, mut reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        let inner = self.inner.clone();
+        let guard = self.guard.clone();
+        Box::pin(crate::stream! {
+            // the first payload carries the metadata of the channel
+            let first = match reqs.next().await {
+                Some(Ok(it)) => it,
+                Some(Err(e)) => {
+                    yield Err(e);
+                    return;
+                }
+                None => return,
+            };
+            if let Err(e) = guard.check(&first).await {
+                yield Err(e);
+                return;
+            }
+            let reqs = Box::pin(stream::once(async move { Ok(first) }).chain(reqs));
+            let mut results = inner.request_channel(reqs);
+            while let Some(next) = results.next().await {
+                yield next;
+            }
+        })
+    }
+
+    fn availability(&self) -> f64 {
+        self.inner.availability()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+}
@@ -63,4 +63,50 @@ pub trait RSocket: Sync + Send {
     fn request_stream(&self, req: Payload) -> Flux<Result<Payload>>;
     /// Request-Channel interaction model of RSocket.
     fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>>;
+    /// Returns a value in `[0.0, 1.0]` telling how ready this RSocket is to accept new requests.
+    ///
+    /// `0.0` means it should not be used at all, e.g. the connection has been closed or the
+    /// lease granted by the peer has been used up. Load balancers use it to weight members.
+    fn availability(&self) -> f64 {
+        1.0
+    }
+    /// Returns true once the connection of this RSocket is closed for good, whereas an
+    /// availability of `0.0` may be temporary. Load balancers evict closed members.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+#[async_trait]
+impl<R> RSocket for Box<R>
+where
+    R: RSocket + ?Sized,
+{
+    async fn metadata_push(&self, req: Payload) -> Result<()> {
+        (**self).metadata_push(req).await
+    }
+
+    async fn fire_and_forget(&self, req: Payload) -> Result<()> {
+        (**self).fire_and_forget(req).await
+    }
+
+    async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
+        (**self).request_response(req).await
+    }
+
+    fn request_stream(&self, req: Payload) -> Flux<Result<Payload>> {
+        (**self).request_stream(req)
+    }
+
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        (**self).request_channel(reqs)
+    }
+
+    fn availability(&self) -> f64 {
+        (**self).availability()
+    }
+
+    fn is_closed(&self) -> bool {
+        (**self).is_closed()
+    }
 }
@@ -0,0 +1,92 @@
+use std::net::SocketAddr;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use super::spi::{Connection, FrameSink, FrameStream, Transport};
+use crate::Result;
+
+/// A client transport of any kind, so that transports can be picked at runtime.
+pub struct BoxTransport {
+    inner: Box<dyn DynTransport>,
+}
+
+/// The connection of a `BoxTransport`.
+pub struct BoxConnection {
+    inner: Box<dyn DynConnection>,
+}
+
+trait DynTransport: Send + Sync {
+    fn connect_boxed(self: Box<Self>) -> BoxFuture<'static, Result<BoxConnection>>;
+}
+
+trait DynConnection: Send + Sync {
+    fn split_boxed(self: Box<Self>) -> (Box<FrameSink>, Box<FrameStream>);
+
+    fn peer_addr(&self) -> Option<SocketAddr>;
+}
+
+impl BoxTransport {
+    pub fn new<T>(transport: T) -> BoxTransport
+    where
+        T: Send + Sync + Transport + 'static,
+        T::Conn: Sync + 'static,
+    {
+        BoxTransport {
+            inner: Box::new(transport),
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for BoxTransport {
+    type Conn = BoxConnection;
+
+    async fn connect(self) -> Result<BoxConnection> {
+        self.inner.connect_boxed().await
+    }
+}
+
+impl BoxConnection {
+    pub fn new<C>(conn: C) -> BoxConnection
+    where
+        C: Send + Sync + Connection + 'static,
+    {
+        BoxConnection {
+            inner: Box::new(conn),
+        }
+    }
+}
+
+impl Connection for BoxConnection {
+    fn split(self) -> (Box<FrameSink>, Box<FrameStream>) {
+        self.inner.split_boxed()
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.peer_addr()
+    }
+}
+
+impl<T> DynTransport for T
+where
+    T: Send + Sync + Transport + 'static,
+    T::Conn: Sync + 'static,
+{
+    fn connect_boxed(self: Box<Self>) -> BoxFuture<'static, Result<BoxConnection>> {
+        Box::pin(async move { Ok(BoxConnection::new((*self).connect().await?)) })
+    }
+}
+
+impl<C> DynConnection for C
+where
+    C: Send + Sync + Connection,
+{
+    fn split_boxed(self: Box<Self>) -> (Box<FrameSink>, Box<FrameStream>) {
+        (*self).split()
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        Connection::peer_addr(self)
+    }
+}
@@ -0,0 +1,440 @@
+//! Metrics of connections, frames and interactions, recorded through the `metrics` facade,
+//! and `tracing` spans of connections and streams.
+//!
+//! Everything in here compiles to no-ops unless the `metrics` or `tracing` feature is enabled,
+//! so that call sites don't need to care about it.
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+
+use crate::frame::Frame;
+use crate::payload::{Payload, SetupPayload};
+use crate::spi::Flux;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Side {
+    Client,
+    Server,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Role {
+    Requester,
+    Responder,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Interaction {
+    MetadataPush,
+    FireAndForget,
+    RequestResponse,
+    RequestStream,
+    RequestChannel,
+}
+
+impl Side {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Side::Client => "client",
+            Side::Server => "server",
+        }
+    }
+}
+
+impl Role {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Role::Requester => "requester",
+            Role::Responder => "responder",
+        }
+    }
+}
+
+impl Interaction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Interaction::MetadataPush => "metadata_push",
+            Interaction::FireAndForget => "fire_and_forget",
+            Interaction::RequestResponse => "request_response",
+            Interaction::RequestStream => "request_stream",
+            Interaction::RequestChannel => "request_channel",
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(any(feature = "metrics", feature = "tracing"))] {
+        use crate::extension::{MimeType, RoutingMetadata};
+
+        /// Extracts the first routing tag, if the metadata carries routing metadata.
+        pub(crate) fn route(
+            metadata_mime: Option<&str>,
+            metadata: Option<&Bytes>,
+        ) -> Option<String> {
+            let mime = MimeType::from(metadata_mime?);
+            let routing = RoutingMetadata::find(&mime, metadata?).ok()??;
+            routing.get_tags().first().cloned()
+        }
+    } else {
+        #[inline]
+        pub(crate) fn route(
+            metadata_mime: Option<&str>,
+            metadata: Option<&Bytes>,
+        ) -> Option<String> {
+            None
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "metrics")] {
+        use futures::StreamExt;
+        use metrics::{counter, gauge, histogram, Label};
+
+        use crate::frame::Body;
+        use crate::runtime::Instant;
+        use crate::utils::Writeable;
+
+        pub(crate) fn connection_opened(side: Side) {
+            counter!("rsocket_connections_opened_total", "side" => side.as_str()).increment(1);
+            gauge!("rsocket_connections_active", "side" => side.as_str()).increment(1.0);
+        }
+
+        pub(crate) fn connection_closed(side: Side) {
+            counter!("rsocket_connections_closed_total", "side" => side.as_str()).increment(1);
+            gauge!("rsocket_connections_active", "side" => side.as_str()).decrement(1.0);
+        }
+
+        pub(crate) fn frame_sent(frame: &Frame) {
+            let frame_type = frame_type(frame);
+            counter!("rsocket_frames_sent_total", "frame_type" => frame_type).increment(1);
+            counter!("rsocket_bytes_sent_total", "frame_type" => frame_type)
+                .increment(frame.len() as u64);
+        }
+
+        pub(crate) fn frame_received(frame: &Frame) {
+            let frame_type = frame_type(frame);
+            counter!("rsocket_frames_received_total", "frame_type" => frame_type).increment(1);
+            counter!("rsocket_bytes_received_total", "frame_type" => frame_type)
+                .increment(frame.len() as u64);
+        }
+
+        fn frame_type(frame: &Frame) -> &'static str {
+            match frame.get_body_ref() {
+                Body::Setup(_) => "SETUP",
+                Body::Lease(_) => "LEASE",
+                Body::Keepalive(_) => "KEEPALIVE",
+                Body::RequestResponse(_) => "REQUEST_RESPONSE",
+                Body::RequestFNF(_) => "REQUEST_FNF",
+                Body::RequestStream(_) => "REQUEST_STREAM",
+                Body::RequestChannel(_) => "REQUEST_CHANNEL",
+                Body::RequestN(_) => "REQUEST_N",
+                Body::Cancel() => "CANCEL",
+                Body::Payload(_) => "PAYLOAD",
+                Body::Error(_) => "ERROR",
+                Body::MetadataPush(_) => "METADATA_PUSH",
+                Body::Resume(_) => "RESUME",
+                Body::ResumeOK(_) => "RESUME_OK",
+            }
+        }
+
+        /// Records the outcome and latency of a single interaction.
+        ///
+        /// Dropping it before `complete` or `error` counts the interaction as cancelled.
+        pub(crate) struct InteractionMetrics {
+            labels: Vec<Label>,
+            start: Instant,
+            finished: bool,
+        }
+
+        impl InteractionMetrics {
+            pub(crate) fn start(
+                interaction: Interaction,
+                role: Role,
+                route: Option<String>,
+            ) -> InteractionMetrics {
+                let mut labels = vec![
+                    Label::new("interaction", interaction.as_str()),
+                    Label::new("role", role.as_str()),
+                ];
+                if let Some(route) = route {
+                    labels.push(Label::new("route", route));
+                }
+                counter!("rsocket_requests_started_total", labels.iter()).increment(1);
+                gauge!("rsocket_streams_active", labels.iter()).increment(1.0);
+                InteractionMetrics {
+                    labels,
+                    start: Instant::now(),
+                    finished: false,
+                }
+            }
+
+            pub(crate) fn complete(mut self) {
+                self.finish("rsocket_requests_completed_total");
+            }
+
+            pub(crate) fn error(mut self) {
+                self.finish("rsocket_requests_errored_total");
+            }
+
+            pub(crate) fn result<T>(self, res: &Result<T>) {
+                match res {
+                    Ok(_) => self.complete(),
+                    Err(_) => self.error(),
+                }
+            }
+
+            fn finish(&mut self, name: &'static str) {
+                self.finished = true;
+                counter!(name, self.labels.iter()).increment(1);
+                histogram!("rsocket_request_duration_seconds", self.labels.iter())
+                    .record(self.start.elapsed());
+            }
+        }
+
+        impl Drop for InteractionMetrics {
+            fn drop(&mut self) {
+                if !self.finished {
+                    self.finish("rsocket_requests_cancelled_total");
+                }
+                gauge!("rsocket_streams_active", self.labels.iter()).decrement(1.0);
+            }
+        }
+
+        /// Records the outcome of a stream once it terminates, or is cancelled by dropping it.
+        pub(crate) fn watch(
+            metrics: InteractionMetrics,
+            mut results: Flux<Result<Payload>>,
+        ) -> Flux<Result<Payload>> {
+            Box::pin(crate::stream! {
+                let mut metrics = Some(metrics);
+                while let Some(next) = results.next().await {
+                    if next.is_err() {
+                        if let Some(it) = metrics.take() {
+                            it.error();
+                        }
+                    }
+                    yield next;
+                }
+                if let Some(it) = metrics.take() {
+                    it.complete();
+                }
+            })
+        }
+    } else {
+        #[inline]
+        pub(crate) fn connection_opened(side: Side) {}
+
+        #[inline]
+        pub(crate) fn connection_closed(side: Side) {}
+
+        #[inline]
+        pub(crate) fn frame_sent(frame: &Frame) {}
+
+        #[inline]
+        pub(crate) fn frame_received(frame: &Frame) {}
+
+        pub(crate) struct InteractionMetrics;
+
+        impl InteractionMetrics {
+            #[inline]
+            pub(crate) fn start(
+                interaction: Interaction,
+                role: Role,
+                route: Option<String>,
+            ) -> InteractionMetrics {
+                InteractionMetrics
+            }
+
+            #[inline]
+            pub(crate) fn complete(self) {}
+
+            #[inline]
+            pub(crate) fn error(self) {}
+
+            #[inline]
+            pub(crate) fn result<T>(self, res: &Result<T>) {}
+        }
+
+        #[inline]
+        pub(crate) fn watch(
+            metrics: InteractionMetrics,
+            results: Flux<Result<Payload>>,
+        ) -> Flux<Result<Payload>> {
+            results
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "tracing")] {
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+
+        use futures::Stream;
+        use tracing::field::{display, Empty};
+        use tracing::Instrument;
+
+        /// Span of a connection, the parent of the spans of all streams on it.
+        #[derive(Clone)]
+        pub(crate) struct ConnectionSpan(tracing::Span);
+
+        impl ConnectionSpan {
+            pub(crate) fn new(side: Side) -> ConnectionSpan {
+                ConnectionSpan(tracing::info_span!(
+                    "rsocket.connection",
+                    side = side.as_str(),
+                    peer_addr = Empty,
+                    data_mime_type = Empty,
+                    metadata_mime_type = Empty,
+                ))
+            }
+
+            pub(crate) fn record_peer_addr(&self, peer_addr: Option<SocketAddr>) {
+                if let Some(addr) = peer_addr {
+                    self.0.record("peer_addr", display(addr));
+                }
+            }
+
+            pub(crate) fn record_setup(&self, setup: &SetupPayload) {
+                if let Some(mime) = setup.data_mime_type() {
+                    self.0.record("data_mime_type", mime);
+                }
+                if let Some(mime) = setup.metadata_mime_type() {
+                    self.0.record("metadata_mime_type", mime);
+                }
+            }
+
+            pub(crate) fn stream(
+                &self,
+                interaction: Interaction,
+                role: Role,
+                sid: u32,
+                route: Option<&str>,
+            ) -> StreamSpan {
+                let span = tracing::info_span!(
+                    parent: &self.0,
+                    "rsocket.stream",
+                    interaction = interaction.as_str(),
+                    role = role.as_str(),
+                    stream_id = sid,
+                    route = Empty,
+                );
+                let span = StreamSpan(span);
+                span.record_route(route);
+                span
+            }
+
+            pub(crate) fn instrument<F>(&self, fut: F) -> impl Future<Output = F::Output>
+            where
+                F: Future,
+            {
+                fut.instrument(self.0.clone())
+            }
+        }
+
+        /// Span of a single stream, covering its lifetime.
+        #[derive(Clone)]
+        pub(crate) struct StreamSpan(tracing::Span);
+
+        impl StreamSpan {
+            /// Records the route once it is known, which is the first payload of a channel.
+            pub(crate) fn record_route(&self, route: Option<&str>) {
+                if let Some(route) = route {
+                    self.0.record("route", route);
+                }
+            }
+
+            pub(crate) fn instrument<F>(self, fut: F) -> impl Future<Output = F::Output>
+            where
+                F: Future,
+            {
+                fut.instrument(self.0)
+            }
+
+            pub(crate) fn instrument_stream<T>(self, inner: Flux<T>) -> Flux<T>
+            where
+                T: Send + 'static,
+            {
+                Box::pin(InSpan { inner, span: self.0 })
+            }
+        }
+
+        struct InSpan<T> {
+            inner: Flux<T>,
+            span: tracing::Span,
+        }
+
+        impl<T> Stream for InSpan<T> {
+            type Item = T;
+
+            fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+                let this = &mut *self;
+                let _enter = this.span.enter();
+                this.inner.as_mut().poll_next(cx)
+            }
+        }
+    } else {
+        #[derive(Clone)]
+        pub(crate) struct ConnectionSpan;
+
+        impl ConnectionSpan {
+            #[inline]
+            pub(crate) fn new(side: Side) -> ConnectionSpan {
+                ConnectionSpan
+            }
+
+            #[inline]
+            pub(crate) fn record_peer_addr(&self, peer_addr: Option<SocketAddr>) {}
+
+            #[inline]
+            pub(crate) fn record_setup(&self, setup: &SetupPayload) {}
+
+            #[inline]
+            pub(crate) fn stream(
+                &self,
+                interaction: Interaction,
+                role: Role,
+                sid: u32,
+                route: Option<&str>,
+            ) -> StreamSpan {
+                StreamSpan
+            }
+
+            #[inline]
+            pub(crate) fn instrument<F>(&self, fut: F) -> impl Future<Output = F::Output>
+            where
+                F: Future,
+            {
+                fut
+            }
+        }
+
+        #[derive(Clone)]
+        pub(crate) struct StreamSpan;
+
+        impl StreamSpan {
+            #[inline]
+            pub(crate) fn record_route(&self, route: Option<&str>) {}
+
+            #[inline]
+            pub(crate) fn instrument<F>(self, fut: F) -> impl Future<Output = F::Output>
+            where
+                F: Future,
+            {
+                fut
+            }
+
+            #[inline]
+            pub(crate) fn instrument_stream<T>(self, inner: Flux<T>) -> Flux<T>
+            where
+                T: Send + 'static,
+            {
+                inner
+            }
+        }
+    }
+}
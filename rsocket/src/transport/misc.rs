@@ -1,7 +1,9 @@
 use std::sync::atomic::{AtomicI64, AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use crate::frame::Frame;
+use crate::runtime::Instant;
 
 #[derive(Debug, Clone)]
 pub(crate) struct StreamID {
@@ -43,6 +45,50 @@ impl Counter {
     }
 }
 
+/// Tracks the latest LEASE granted by the peer.
+#[derive(Debug, Default)]
+pub(crate) struct LeaseTracker {
+    inner: Mutex<Option<LeaseState>>,
+}
+
+#[derive(Debug)]
+struct LeaseState {
+    expire_at: Instant,
+    allowed: u32,
+    remaining: u32,
+}
+
+impl LeaseTracker {
+    pub(crate) fn grant(&self, ttl: Duration, number_of_requests: u32) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner = Some(LeaseState {
+            expire_at: Instant::now() + ttl,
+            allowed: number_of_requests,
+            remaining: number_of_requests,
+        });
+    }
+
+    pub(crate) fn consume(&self) {
+        if let Some(state) = self.inner.lock().unwrap().as_mut() {
+            state.remaining = state.remaining.saturating_sub(1);
+        }
+    }
+
+    /// Returns the ratio of remaining requests, or 1.0 if no lease has been granted yet.
+    pub(crate) fn availability(&self) -> f64 {
+        match self.inner.lock().unwrap().as_ref() {
+            None => 1.0,
+            Some(state) => {
+                if state.allowed == 0 || state.expire_at <= Instant::now() {
+                    0.0
+                } else {
+                    f64::from(state.remaining) / f64::from(state.allowed)
+                }
+            }
+        }
+    }
+}
+
 #[inline]
 pub(crate) fn debug_frame(snd: bool, f: &Frame) {
     if snd {
@@ -1,8 +1,13 @@
+mod boxed;
 mod fragmentation;
+pub(crate) mod instrument;
 mod misc;
+mod registry;
 mod socket;
 mod spi;
 
 pub(crate) use fragmentation::{Joiner, Splitter, MIN_MTU};
 pub(crate) use socket::{ClientRequester,DuplexSocket};
+pub use boxed::{BoxConnection, BoxTransport};
+pub use registry::{TransportFactory, Transports};
 pub use spi::*;
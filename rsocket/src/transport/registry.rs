@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::boxed::BoxTransport;
+use crate::error::RSocketError;
+use crate::Result;
+
+/// Creates a client transport from a whole URI, e.g. `tcp://127.0.0.1:7878`.
+pub type TransportFactory = dyn Fn(&str) -> Result<BoxTransport> + Send + Sync;
+
+/// A registry of client transports keyed by URI scheme.
+///
+/// It is empty by default, transport crates register their own schemes into it.
+///
+/// # Example
+/// ```no_run,ignore
+/// let mut transports = Transports::default();
+/// rsocket_rust_transport_tcp::register_schemes(&mut transports);
+/// let client = RSocketFactory::connect()
+///     .transport(transports.resolve("tcp://127.0.0.1:7878")?)
+///     .start()
+///     .await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct Transports {
+    inner: HashMap<String, Arc<TransportFactory>>,
+}
+
+impl Transports {
+    /// Registers the factory of a scheme, replacing the previous one. Schemes are case-insensitive.
+    pub fn register<F>(&mut self, scheme: &str, factory: F)
+    where
+        F: Fn(&str) -> Result<BoxTransport> + Send + Sync + 'static,
+    {
+        self.inner
+            .insert(scheme.to_ascii_lowercase(), Arc::new(factory));
+    }
+
+    pub fn contains(&self, scheme: &str) -> bool {
+        self.inner.contains_key(&scheme.to_ascii_lowercase())
+    }
+
+    /// Creates a transport by the factory of the scheme of the URI.
+    pub fn resolve(&self, uri: &str) -> Result<BoxTransport> {
+        let scheme = match uri.find("://") {
+            Some(n) => uri[..n].to_ascii_lowercase(),
+            None => {
+                let desc = format!("invalid transport uri: {}", uri);
+                return Err(RSocketError::WithDescription(desc).into());
+            }
+        };
+        match self.inner.get(&scheme) {
+            Some(factory) => factory(uri),
+            None => {
+                let desc = format!("unsupported transport scheme: {}", scheme);
+                Err(RSocketError::WithDescription(desc).into())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Transports {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.inner.keys()).finish()
+    }
+}
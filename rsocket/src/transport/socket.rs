@@ -1,6 +1,8 @@
 use std::future::Future;
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::sync::{Arc, Weak};
+use std::time::Duration;
 
 use async_stream::stream;
 use async_trait::async_trait;
@@ -8,13 +10,16 @@ use bytes::{Buf, BufMut, Bytes, BytesMut};
 use dashmap::{mapref::entry::Entry, DashMap};
 use futures::future::{AbortHandle, Abortable};
 use futures::{Sink, SinkExt, Stream, StreamExt};
+use once_cell::sync::OnceCell;
 use tokio::sync::{mpsc, oneshot, RwLock};
 
 use super::fragmentation::{Joiner, Splitter};
-use super::misc::{debug_frame, Counter, StreamID};
+use super::instrument::{self, ConnectionSpan, Interaction, InteractionMetrics, Role, StreamSpan};
+use super::misc::{debug_frame, Counter, LeaseTracker, StreamID};
 use super::spi::*;
 use crate::error::{self, RSocketError};
 use crate::frame::{self, Body, Frame};
+use crate::interceptor::{ConnectionInfo, InterceptorChain};
 use crate::payload::{Payload, SetupPayload};
 use crate::spi::{Flux, RSocket, ServerResponder};
 use crate::utils::EmptyRSocket;
@@ -29,6 +34,9 @@ struct DuplexSocketInner {
     joiners: DashMap<u32, Joiner>,
     /// AbortHandles for Response futures/streams
     abort_handles: Arc<DashMap<u32, AbortHandle>>,
+    lease: LeaseTracker,
+    metadata_mime: OnceCell<String>,
+    span: ConnectionSpan,
 }
 
 #[derive(Clone)]
@@ -43,6 +51,8 @@ pub(crate) struct ServerRequester {
 
 pub(crate) struct DuplexSocket {
     inner: Arc<DuplexSocketInner>,
+    interceptors: InterceptorChain,
+    peer_addr: Option<SocketAddr>,
 }
 
 #[derive(Clone)]
@@ -64,6 +74,7 @@ impl DuplexSocketInner {
         first_stream_id: u32,
         tx: mpsc::UnboundedSender<Frame>,
         splitter: Option<Splitter>,
+        span: ConnectionSpan,
     ) -> Self {
         let (canceller_tx, canceller_rx) = mpsc::channel::<u32>(32);
         let this = Self {
@@ -74,6 +85,9 @@ impl DuplexSocketInner {
             joiners: DashMap::new(),
             splitter,
             abort_handles: Arc::new(DashMap::new()),
+            lease: LeaseTracker::default(),
+            metadata_mime: OnceCell::new(),
+            span,
         };
         this
     }
@@ -84,19 +98,37 @@ impl DuplexSocket {
         first_stream_id: u32,
         tx: mpsc::UnboundedSender<Frame>,
         splitter: Option<Splitter>,
+        span: ConnectionSpan,
     ) -> DuplexSocket {
         DuplexSocket {
-            inner: Arc::new(DuplexSocketInner::new(first_stream_id, tx, splitter)),
+            inner: Arc::new(DuplexSocketInner::new(first_stream_id, tx, splitter, span)),
+            interceptors: InterceptorChain::default(),
+            peer_addr: None,
         }
     }
 
+    /// Sets the interceptors which are applied once the SETUP frame is received.
+    pub(crate) fn intercept(
+        &mut self,
+        interceptors: InterceptorChain,
+        peer_addr: Option<SocketAddr>,
+    ) {
+        self.interceptors = interceptors;
+        self.peer_addr = peer_addr;
+    }
+
     pub(crate) async fn setup(&mut self, setup: SetupPayload) -> Result<()> {
+        self.inner.span.record_setup(&setup);
         let mut bu = frame::Setup::builder(0, 0);
         if let Some(s) = setup.data_mime_type() {
             bu = bu.set_mime_data(s);
         }
         if let Some(s) = setup.metadata_mime_type() {
             bu = bu.set_mime_metadata(s);
+            let _ = self.inner.metadata_mime.set(s.to_owned());
+        }
+        if setup.honor_lease() {
+            bu = bu.set_lease();
         }
         bu = bu.set_keepalive(setup.keepalive_interval());
         bu = bu.set_lifetime(setup.keepalive_lifetime());
@@ -120,6 +152,7 @@ impl DuplexSocket {
         frame: Frame,
         acceptor: Option<&ServerResponder>,
     ) -> Result<()> {
+        instrument::frame_received(&frame);
         if let Some(frame) = self.join_frame(frame).await {
             self.process_once(frame, acceptor).await;
         }
@@ -194,7 +227,7 @@ impl DuplexSocket {
                 self.on_cancel(sid, flag).await;
             }
             Body::Lease(v) => {
-                // TODO: support Lease
+                self.on_lease(v);
             }
         }
     }
@@ -277,11 +310,15 @@ impl DuplexSocket {
         self.inner.joiners.remove(&sid);
         // pick handler
         if let Some((_, handler)) = self.inner.handlers.remove(&sid) {
-            let desc = input
-                .get_data_utf8()
-                .map(|it| it.to_string())
-                .unwrap_or_default();
-            let e = RSocketError::must_new_from_code(input.get_code(), desc);
+            let e = match (input.get_data_utf8(), input.get_data()) {
+                (None, Some(data)) if input.get_code() == error::ERR_APPLICATION => {
+                    RSocketError::ApplicationData(data.clone())
+                }
+                (desc, _) => RSocketError::must_new_from_code(
+                    input.get_code(),
+                    desc.map(|it| it.to_string()).unwrap_or_default(),
+                ),
+            };
             match handler {
                 Handler::ReqRR(tx) => {
                     if tx.send(Err(e.into())).is_err() {
@@ -401,26 +438,36 @@ impl DuplexSocket {
         flag: u16,
         setup: SetupPayload,
     ) -> Result<()> {
-        match acceptor {
-            None => {
-                self.inner.responder.set(Box::new(EmptyRSocket)).await;
-                Ok(())
-            }
-            Some(gen) => match gen(setup, Box::new(self.server_requester())) {
-                Ok(it) => {
-                    self.inner.responder.set(it).await;
-                    Ok(())
-                }
-                Err(e) => Err(e),
-            },
+        if let Some(s) = setup.metadata_mime_type() {
+            let _ = self.inner.metadata_mime.set(s.to_owned());
         }
+        self.inner.span.record_setup(&setup);
+        let info = ConnectionInfo::new(setup.clone(), self.peer_addr);
+        self.interceptors.on_setup(&info).await?;
+        let requester = self
+            .interceptors
+            .requester(&info, Box::new(self.server_requester()));
+        let responder: Box<dyn RSocket> = match acceptor {
+            None => Box::new(EmptyRSocket),
+            Some(gen) => gen(setup.with_connection(info.clone()), requester)?,
+        };
+        let responder = self.interceptors.responder(&info, responder);
+        self.inner.responder.set(responder).await;
+        Ok(())
     }
 
     #[inline]
     async fn on_fire_and_forget(&mut self, sid: u32, input: Payload) {
         // TODO: Spawning a task here in case responer call goes  pending, which would hold
         // up the entire dispatch loop
-        if let Err(e) = self.inner.responder.fire_and_forget(input).await {
+        let (metrics, span) =
+            self.inner
+                .observe(Interaction::FireAndForget, Role::Responder, sid, &input);
+        let res = span
+            .instrument(self.inner.responder.fire_and_forget(input))
+            .await;
+        metrics.result(&res);
+        if let Err(e) = res {
             error!("respond fire_and_forget failed: {:?}", e);
         }
     }
@@ -433,7 +480,10 @@ impl DuplexSocket {
         let splitter = self.inner.splitter.clone();    
         let (abort_handle, abort_registration) = AbortHandle::new_pair();       
         let abort_handles = self.inner.abort_handles.clone();
-        runtime::spawn(async move {
+        let (metrics, span) =
+            self.inner
+                .observe(Interaction::RequestResponse, Role::Responder, sid, &input);
+        runtime::spawn(span.instrument(async move {
                        
             abort_handles.insert(sid, abort_handle);
             let result= Abortable::new(responder.request_response(input), abort_registration).await;
@@ -444,6 +494,7 @@ impl DuplexSocket {
                 // cancelled
                 return;
             };
+            metrics.result(&result);
 
             match result {
                 Ok(Some(res)) => {
@@ -460,16 +511,13 @@ impl DuplexSocket {
                     DuplexSocketInner::try_send_complete(&mut tx, sid, Frame::FLAG_COMPLETE).await;
                 }
                 Err(e) => {
-                    let sending = frame::Error::builder(sid, 0)
-                        .set_code(error::ERR_APPLICATION)
-                        .set_data(Bytes::from(e.to_string()))
-                        .build();
+                    let sending = DuplexSocketInner::error_frame(sid, &e);
                     if let Err(e) = tx.send(sending) {
                         error!("respond REQUEST_RESPONSE failed: {}", e);
                     }
                 }
             };
-        });
+        }));
     }
 
     #[inline]
@@ -478,7 +526,11 @@ impl DuplexSocket {
         let mut tx = self.inner.tx.clone();
         let splitter = self.inner.splitter.clone();
         let abort_handles = self.inner.abort_handles.clone();
-        runtime::spawn(async move {
+        let (metrics, span) =
+            self.inner
+                .observe(Interaction::RequestStream, Role::Responder, sid, &input);
+        let mut metrics = Some(metrics);
+        runtime::spawn(span.instrument(async move {
             let (abort_handle, abort_registration) = AbortHandle::new_pair();
             abort_handles.insert(sid, abort_handle);
             let mut payloads = Abortable::new(responder.request_stream(input), abort_registration);
@@ -495,19 +547,25 @@ impl DuplexSocket {
                         .await;
                     }
                     Err(e) => {
-                        let sending = frame::Error::builder(sid, 0)
-                            .set_code(error::ERR_APPLICATION)
-                            .set_data(Bytes::from(format!("{}", e)))
-                            .build();
+                        if let Some(it) = metrics.take() {
+                            it.error();
+                        }
+                        let sending = DuplexSocketInner::error_frame(sid, &e);
                         tx.send(sending).expect("Send stream response failed");
                     }
                 };
             }
+            // an aborted stream has been cancelled, which is recorded once metrics are dropped
+            if !payloads.is_aborted() {
+                if let Some(it) = metrics.take() {
+                    it.complete();
+                }
+            }
             abort_handles.remove(&sid);
             let complete = frame::Payload::builder(sid, Frame::FLAG_COMPLETE).build();
             tx.send(complete)
                 .expect("Send stream complete response failed");
-        });
+        }));
     }
 
     #[inline]
@@ -515,10 +573,14 @@ impl DuplexSocket {
         let responder = self.inner.responder.clone();
         let tx = self.inner.tx.clone();
         let (sender, mut receiver) = mpsc::channel::<Result<Payload>>(32);
+        let (metrics, span) =
+            self.inner
+                .observe(Interaction::RequestChannel, Role::Responder, sid, &first);
+        let mut metrics = Some(metrics);
         sender.send(Ok(first)).await.expect("Send failed!");
         self.register_handler(sid, Handler::ReqRC(sender));
         let abort_handles = self.inner.abort_handles.clone();
-        runtime::spawn(async move {
+        runtime::spawn(span.instrument(async move {
             // respond client channel
             let outputs = responder.request_channel(Box::pin(stream! {
                 while let Some(it) = receiver.recv().await{
@@ -549,30 +611,50 @@ impl DuplexSocket {
                         }
                         bu.build()
                     }
-                    Err(e) => frame::Error::builder(sid, 0)
-                        .set_code(error::ERR_APPLICATION)
-                        .set_data(Bytes::from(format!("{}", e)))
-                        .build(),
+                    Err(e) => {
+                        if let Some(it) = metrics.take() {
+                            it.error();
+                        }
+                        DuplexSocketInner::error_frame(sid, &e)
+                    }
                 };
                 tx.send(sending).expect("Send failed!");
             }
+            if !outputs.is_aborted() {
+                if let Some(it) = metrics.take() {
+                    it.complete();
+                }
+            }
             abort_handles.remove(&sid);
             let complete = frame::Payload::builder(sid, Frame::FLAG_COMPLETE).build();
             if let Err(e) = tx.send(complete) {
                 error!("complete REQUEST_CHANNEL failed: {}", e);
             }
-        });
+        }));
     }
 
     #[inline]
     async fn on_metadata_push(&mut self, input: Payload) {
         // TODO: Spawning a task here in case responer call goes  pending, which would hold
         // up the entire dispatch loop
-        if let Err(e) = self.inner.responder.metadata_push(input).await {
+        let (metrics, span) =
+            self.inner
+                .observe(Interaction::MetadataPush, Role::Responder, 0, &input);
+        let res = span
+            .instrument(self.inner.responder.metadata_push(input))
+            .await;
+        metrics.result(&res);
+        if let Err(e) = res {
             error!("response metadata_push failed: {:?}", e);
         }
     }
 
+    #[inline]
+    fn on_lease(&self, lease: frame::Lease) {
+        let ttl = Duration::from_millis(u64::from(lease.get_ttl()));
+        self.inner.lease.grant(ttl, lease.get_number_of_requests());
+    }
+
     #[inline]
     async fn on_keepalive(&mut self, keepalive: frame::Keepalive) {
         let (data, _) = keepalive.split();
@@ -600,8 +682,53 @@ impl DuplexSocket {
 
 // These are the immplementation functions for the requesters below
 impl DuplexSocketInner {
+    #[inline]
+    fn check_closed(&self) -> Result<()> {
+        if self.tx.is_closed() {
+            Err(RSocketError::ConnectionClosed("closed".into()).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn availability(&self) -> f64 {
+        if self.tx.is_closed() {
+            0.0
+        } else {
+            self.lease.availability()
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Starts recording the metrics and the span of an interaction.
+    fn observe(
+        &self,
+        interaction: Interaction,
+        role: Role,
+        sid: u32,
+        req: &Payload,
+    ) -> (InteractionMetrics, StreamSpan) {
+        let mime = self.metadata_mime.get().map(|it| it.as_str());
+        let route = instrument::route(mime, req.metadata());
+        let span = self.span.stream(interaction, role, sid, route.as_deref());
+        (InteractionMetrics::start(interaction, role, route), span)
+    }
+
     async fn metadata_push(&self, req: Payload) -> Result<()> {
         let sid = self.seq.next();
+        let (metrics, span) = self.observe(Interaction::MetadataPush, Role::Requester, sid, &req);
+        let res = span
+            .instrument(async { self.send_metadata_push(sid, req) })
+            .await;
+        metrics.result(&res);
+        res
+    }
+
+    fn send_metadata_push(&self, sid: u32, req: Payload) -> Result<()> {
+        self.check_closed()?;
         let tx = self.tx.clone();
         let (_d, m) = req.split();
         let mut bu = frame::MetadataPush::builder(sid, 0);
@@ -614,6 +741,17 @@ impl DuplexSocketInner {
 
     async fn fire_and_forget(&self, req: Payload) -> Result<()> {
         let sid = self.seq.next();
+        let (metrics, span) = self.observe(Interaction::FireAndForget, Role::Requester, sid, &req);
+        let res = span
+            .instrument(async { self.send_fire_and_forget(sid, req) })
+            .await;
+        metrics.result(&res);
+        res
+    }
+
+    fn send_fire_and_forget(&self, sid: u32, req: Payload) -> Result<()> {
+        self.check_closed()?;
+        self.lease.consume();
         let tx = self.tx.clone();
         let splitter = self.splitter.clone();
 
@@ -666,8 +804,18 @@ impl DuplexSocketInner {
     }
 
     async fn request_response(&self, req: Payload) -> Result<Option<Payload>> {
-        let (tx, rx) = oneshot::channel::<Result<Option<Payload>>>();
         let sid = self.seq.next();
+        let (metrics, span) =
+            self.observe(Interaction::RequestResponse, Role::Requester, sid, &req);
+        let res = span.instrument(self.send_request_response(sid, req)).await;
+        metrics.result(&res);
+        res
+    }
+
+    async fn send_request_response(&self, sid: u32, req: Payload) -> Result<Option<Payload>> {
+        self.check_closed()?;
+        self.lease.consume();
+        let (tx, rx) = oneshot::channel::<Result<Option<Payload>>>();
         let sender = self.tx.clone();
         let splitter = self.splitter.clone();
 
@@ -738,6 +886,17 @@ impl DuplexSocketInner {
 
     fn request_stream(&self, input: Payload) -> Flux<Result<Payload>> {
         let sid = self.seq.next();
+        let (metrics, span) =
+            self.observe(Interaction::RequestStream, Role::Requester, sid, &input);
+        let results = span.instrument_stream(self.send_request_stream(sid, input));
+        instrument::watch(metrics, results)
+    }
+
+    fn send_request_stream(&self, sid: u32, input: Payload) -> Flux<Result<Payload>> {
+        if let Err(e) = self.check_closed() {
+            return Box::pin(futures::stream::once(async move { Err(e) }));
+        }
+        self.lease.consume();
         let tx = self.tx.clone();
         // register handler
         let (sender, mut receiver) = mpsc::channel::<Result<Payload>>(32);
@@ -805,14 +964,35 @@ impl DuplexSocketInner {
         })
     }
 
-    fn request_channel(&self, mut reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+    fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
+        // the route is recorded once the first payload has been produced
         let sid = self.seq.next();
+        let interaction = Interaction::RequestChannel;
+        let metrics = InteractionMetrics::start(interaction, Role::Requester, None);
+        let span = self.span.stream(interaction, Role::Requester, sid, None);
+        let results = span
+            .clone()
+            .instrument_stream(self.send_request_channel(sid, reqs, span));
+        instrument::watch(metrics, results)
+    }
+
+    fn send_request_channel(
+        &self,
+        sid: u32,
+        mut reqs: Flux<Result<Payload>>,
+        span: StreamSpan,
+    ) -> Flux<Result<Payload>> {
+        if let Err(e) = self.check_closed() {
+            return Box::pin(futures::stream::once(async move { Err(e) }));
+        }
+        self.lease.consume();
         let mut tx = self.tx.clone();
 
         let (sender, mut receiver) = mpsc::channel::<Result<Payload>>(32);
         // register handler
         self.handlers.insert(sid, Handler::ReqRC(sender));
         let splitter = self.splitter.clone();
+        let mime = self.metadata_mime.get().cloned();
         runtime::spawn(async move {
             let mut first = true;
             while let Some(next) = reqs.next().await {
@@ -820,6 +1000,9 @@ impl DuplexSocketInner {
                     Ok(it) => {
                         if first {
                             first = false;
+                            span.record_route(
+                                instrument::route(mime.as_deref(), it.metadata()).as_deref(),
+                            );
                             Self::try_send_channel(&splitter, &mut tx, sid, it, Frame::FLAG_NEXT)
                                 .await
                         } else {
@@ -911,6 +1094,24 @@ impl DuplexSocketInner {
         }
     }
 
+    /// Builds the ERROR frame of a failed responder, keeping the code of request errors and the
+    /// data of application errors.
+    fn error_frame(sid: u32, e: &anyhow::Error) -> Frame {
+        let (code, data) = match e.downcast_ref::<RSocketError>() {
+            Some(RSocketError::RequestRejected(desc)) => (error::ERR_REJECTED, desc.clone().into()),
+            Some(RSocketError::RequestCancelled(desc)) => {
+                (error::ERR_CANCELED, desc.clone().into())
+            }
+            Some(RSocketError::RequestInvalid(desc)) => (error::ERR_INVALID, desc.clone().into()),
+            Some(RSocketError::ApplicationData(data)) => (error::ERR_APPLICATION, data.clone()),
+            _ => (error::ERR_APPLICATION, Bytes::from(e.to_string())),
+        };
+        frame::Error::builder(sid, 0)
+            .set_code(code)
+            .set_data(data)
+            .build()
+    }
+
     #[inline]
     async fn try_send_complete(tx: &mut mpsc::UnboundedSender<Frame>, sid: u32, flag: u16) {
         let sending = frame::Payload::builder(sid, flag).build();
@@ -1060,6 +1261,14 @@ impl RSocket for ClientRequester {
     fn request_channel(&self, reqs: Flux<Result<Payload>>) -> Flux<Result<Payload>> {
         self.inner.request_channel(reqs)
     }
+
+    fn availability(&self) -> f64 {
+        self.inner.availability()
+    }
+
+    fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
 }
 
 // This implementation, uses the async function trait, as it needs to capture the ugpraded
@@ -1108,4 +1317,18 @@ impl RSocket for ServerRequester {
             )))),
         }
     }
+
+    fn availability(&self) -> f64 {
+        match self.inner.upgrade() {
+            Some(inner) => inner.availability(),
+            None => 0.0,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match self.inner.upgrade() {
+            Some(inner) => inner.is_closed(),
+            None => true,
+        }
+    }
 }
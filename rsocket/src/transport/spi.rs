@@ -1,6 +1,7 @@
 use std::future::Future;
 use std::io::Error as IOError;
 use std::marker::Unpin;
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::result::Result as StdResult;
 use std::sync::Arc;
@@ -20,6 +21,11 @@ pub type FrameStream = dyn Stream<Item = StdResult<Frame, RSocketError>> + Send
 
 pub trait Connection {
     fn split(self) -> (Box<FrameSink>, Box<FrameStream>);
+
+    /// Returns the address of the remote peer, `None` if the transport doesn't know it.
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
 }
 
 #[async_trait]